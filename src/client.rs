@@ -156,19 +156,24 @@
 //! [`Builder`]: struct.Builder.html
 //! [`Error`]: ../struct.Error.html
 
-use {SendStream, RecvStream, ReleaseCapacity, PingPong};
-use codec::{Codec, RecvError, SendError, UserError};
-use frame::{Headers, Pseudo, Reason, Settings, StreamId};
+use {GoAway, SendStream, RecvStream, ReleaseCapacity, PingPong, RemoteSettings};
+use {ExtensionFrame, ExtensionFrames, FlushPolicy, FrameObserver};
+use codec::{self, Codec, RecvError, SendError, UserError};
+use frame::{self, HeaderValidation, Headers, Padding, Pseudo, Reason, Settings, StreamId};
+use hpack::{self, DefaultIndexPolicy, IndexPolicy};
+use observer::Observer;
 use proto;
 
-use bytes::{Bytes, IntoBuf};
+use byteorder::{BigEndian, ByteOrder};
+use bytes::{BufMut, Bytes, BytesMut, IntoBuf};
 use futures::{Async, Future, Poll, Stream};
 use http::{uri, HeaderMap, Request, Response, Method, Version};
 use tokio_io::{AsyncRead, AsyncWrite};
 use tokio_io::io::WriteAll;
 
-use std::fmt;
+use std::{fmt, io};
 use std::marker::PhantomData;
+use std::sync::Arc;
 use std::time::Duration;
 use std::usize;
 
@@ -181,18 +186,29 @@ use std::usize;
 /// settings frame is sent by the client.
 ///
 /// The handshake future does not wait for the initial settings frame from the
-/// server.
+/// server, unless [`Builder::wait_for_remote_settings`] is enabled.
 ///
 /// See [module] level documentation for more details.
 ///
 /// [module]: index.html
+/// [`Builder::wait_for_remote_settings`]: struct.Builder.html#method.wait_for_remote_settings
 #[must_use = "futures do nothing unless polled"]
 pub struct Handshake<T, B = Bytes> {
     builder: Builder,
-    inner: WriteAll<T, &'static [u8]>,
+    state: Handshaking<T>,
     _marker: PhantomData<fn(B)>,
 }
 
+/// Handshake state.
+enum Handshaking<T> {
+    /// Writing the connection preface, followed by the SETTINGS frame when
+    /// waiting for the server's.
+    Flushing(WriteAll<T, Bytes>),
+    /// Reading the server's SETTINGS frame. It is left in the buffer, for the
+    /// connection to decode once built.
+    ReadingSettings(Option<T>, BytesMut),
+}
+
 /// Initializes new HTTP/2.0 streams on a connection by sending a request.
 ///
 /// This type does no work itself. Instead, it is a handle to the inner
@@ -217,6 +233,8 @@ pub struct Handshake<T, B = Bytes> {
 pub struct SendRequest<B: IntoBuf> {
     inner: proto::Streams<B::Buf, Peer>,
     pending: Option<proto::OpaqueStreamRef>,
+    /// Version of the remote settings last returned by `poll_remote_settings`.
+    remote_settings_version: usize,
 }
 
 /// Returns a `SendRequest` instance once it is ready to send at least one
@@ -386,6 +404,38 @@ pub struct Builder {
     /// The stream ID of the first (lowest) stream. Subsequent streams will use
     /// monotonically increasing stream IDs.
     stream_id: StreamId,
+
+    /// The number of stream IDs to leave unused at the end of the stream ID
+    /// space.
+    stream_id_exhaustion_margin: u32,
+
+    /// Whether the handshake completes only once the server's SETTINGS frame
+    /// has been received.
+    wait_for_remote_settings: bool,
+
+    /// Padding of frames sent on streams that don't override it.
+    padding: Padding,
+
+    /// Whether small DATA frames queued on a stream are merged when sent.
+    coalesce_data_frames: bool,
+
+    /// When frames buffered on the connection are flushed.
+    flush_policy: FlushPolicy,
+
+    /// Observer of the frames exchanged on the connection.
+    observer: Observer,
+
+    /// Decides which header fields are added to the HPACK dynamic table.
+    index_policy: Arc<dyn IndexPolicy>,
+
+    /// Whether `cookie` headers are split into one field per cookie pair.
+    crumble_cookies: bool,
+
+    /// How strictly the header fields of messages are validated.
+    header_validation: HeaderValidation,
+
+    /// Size of the buffer that the connection is read into.
+    read_buffer_size: usize,
 }
 
 #[derive(Debug)]
@@ -405,9 +455,16 @@ where
     /// `NotReady` is returned, the task will be notified once the readiness
     /// state changes.
     ///
+    /// An error is returned once the connection has run out of stream IDs, in
+    /// which case [`Error::is_stream_ids_exhausted`] returns `true` and a new
+    /// connection must be used for further requests. See
+    /// [`Builder::stream_id_exhaustion_margin`].
+    ///
     /// See [module] level docs for more details.
     ///
     /// [module]: index.html
+    /// [`Error::is_stream_ids_exhausted`]: ../struct.Error.html#method.is_stream_ids_exhausted
+    /// [`Builder::stream_id_exhaustion_margin`]: struct.Builder.html#method.stream_id_exhaustion_margin
     pub fn poll_ready(&mut self) -> Poll<(), ::Error> {
         try_ready!(self.inner.poll_pending_open(self.pending.as_ref()));
         self.pending = None;
@@ -605,6 +662,84 @@ where
                 (response, stream)
             })
     }
+
+    /// Returns the settings advertised by the server.
+    ///
+    /// Returns `None` if the server's initial SETTINGS frame has not been
+    /// received yet. See [`Builder::wait_for_remote_settings`] to only
+    /// complete the handshake once it has.
+    ///
+    /// [`Builder::wait_for_remote_settings`]: struct.Builder.html#method.wait_for_remote_settings
+    pub fn remote_settings(&self) -> Option<RemoteSettings> {
+        self.inner
+            .remote_settings()
+            .map(RemoteSettings::from_internal)
+    }
+
+    /// Returns `Ready` with the settings advertised by the server once they
+    /// have changed since the last time this function returned `Ready`.
+    ///
+    /// The first call returns `Ready` as soon as the server's initial
+    /// SETTINGS frame has been received. When `NotReady` is returned, the
+    /// task will be notified once the server updates its settings. The
+    /// associated [`Connection`] must be polled for settings to be received.
+    ///
+    /// An error is returned once the connection is closed or going away, as
+    /// the settings won't change anymore.
+    ///
+    /// [`Connection`]: struct.Connection.html
+    pub fn poll_remote_settings(&mut self) -> Poll<RemoteSettings, ::Error> {
+        let settings = try_ready!(self.inner.poll_remote_settings(&mut self.remote_settings_version));
+        Ok(RemoteSettings::from_internal(settings).into())
+    }
+
+    /// Returns the number of new streams that can be opened right now.
+    ///
+    /// This is the server's `SETTINGS_MAX_CONCURRENT_STREAMS` minus the
+    /// number of streams that are currently open or waiting to be opened. A
+    /// request sent when this returns zero is still accepted, but it is
+    /// queued until an open stream completes.
+    ///
+    /// Until the server's SETTINGS frame has been received, the limit is the
+    /// one configured with [`Builder::initial_max_send_streams`].
+    ///
+    /// This makes it possible to compare the load of several connections, for
+    /// example to pick the least loaded one. Unlike [`poll_ready`], which only
+    /// tracks whether the previous request has been opened, this accounts for
+    /// all requests sent on the connection.
+    ///
+    /// [`Builder::initial_max_send_streams`]: struct.Builder.html#method.initial_max_send_streams
+    /// [`poll_ready`]: #method.poll_ready
+    pub fn available_streams(&self) -> usize {
+        self.inner.available_send_streams()
+    }
+
+    /// Returns `Ready` with the number of new streams that can be opened right
+    /// now, once it is at least one.
+    ///
+    /// When `NotReady` is returned, the task will be notified once a stream
+    /// completes or the server raises its concurrency limit. The associated
+    /// [`Connection`] must be polled for this to happen.
+    ///
+    /// See [`available_streams`] for more details.
+    ///
+    /// [`Connection`]: struct.Connection.html
+    /// [`available_streams`]: #method.available_streams
+    pub fn poll_available_streams(&mut self) -> Poll<usize, ::Error> {
+        self.inner.poll_available_send_streams()
+    }
+
+    /// Requests the frames buffered on the connection to be flushed to the
+    /// socket, whatever the flush policy.
+    ///
+    /// This is the same as [`Connection::flush`], for when the connection
+    /// has been spawned: its task is notified, and the frames are flushed
+    /// once it has nothing more to write.
+    ///
+    /// [`Connection::flush`]: struct.Connection.html#method.flush
+    pub fn flush(&mut self) {
+        self.inner.request_flush();
+    }
 }
 
 impl<B> fmt::Debug for SendRequest<B>
@@ -624,6 +759,7 @@ where
         SendRequest {
             inner: self.inner.clone(),
             pending: None,
+            remote_settings_version: self.remote_settings_version,
         }
     }
 }
@@ -710,6 +846,16 @@ impl Builder {
             initial_max_send_streams: usize::MAX,
             settings: Default::default(),
             stream_id: 1.into(),
+            stream_id_exhaustion_margin: 0,
+            wait_for_remote_settings: false,
+            padding: Padding::None,
+            coalesce_data_frames: false,
+            flush_policy: FlushPolicy::Idle,
+            observer: Observer::default(),
+            index_policy: Arc::new(DefaultIndexPolicy::new()),
+            crumble_cookies: false,
+            header_validation: HeaderValidation::default(),
+            read_buffer_size: codec::DEFAULT_READ_BUFFER_SIZE,
         }
     }
 
@@ -861,6 +1007,46 @@ impl Builder {
         self
     }
 
+    /// Sets the size of the buffer that the connection is read into.
+    ///
+    /// Received frames are split off this buffer without being copied. The
+    /// buffer grows as needed to hold a whole frame, so this only sets how
+    /// much space is reserved for each read from the socket. A larger value
+    /// means fewer reads, at the cost of memory held per connection.
+    ///
+    /// The default value is 8,192.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `size` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .read_buffer_size(64 * 1024)
+    ///     .handshake(my_io);
+    /// # client_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    pub fn read_buffer_size(&mut self, size: usize) -> &mut Self {
+        assert!(size > 0, "read buffer size must be greater than zero");
+        self.read_buffer_size = size;
+        self
+    }
+
     /// Sets the maximum number of concurrent streams.
     ///
     /// The maximum concurrent streams setting only controls the maximum number
@@ -1085,6 +1271,434 @@ impl Builder {
         self
     }
 
+    /// Sets whether the handshake waits for the server's initial SETTINGS
+    /// frame before completing.
+    ///
+    /// By default, the handshake completes as soon as the client connection
+    /// preface has been written, before anything is known about the server's
+    /// settings, such as its concurrency limit. When enabled, the handshake
+    /// also reads from the connection until the server's SETTINGS frame has
+    /// been received and applied, so that [`SendRequest::remote_settings`]
+    /// returns the server's actual values.
+    ///
+    /// Default value: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake, including the server's SETTINGS frame.
+    /// let client_fut = Builder::new()
+    ///     .wait_for_remote_settings(true)
+    ///     .handshake(my_io);
+    /// # client_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`SendRequest::remote_settings`]: struct.SendRequest.html#method.remote_settings
+    pub fn wait_for_remote_settings(&mut self, enabled: bool) -> &mut Self {
+        self.wait_for_remote_settings = enabled;
+        self
+    }
+
+    /// Advertises a setting that is not defined by the HTTP/2.0 specification.
+    ///
+    /// Protocol extensions define new settings, identified by `id`, which are
+    /// sent to the server in the initial SETTINGS frame along with the other
+    /// settings. They have no effect on the connection itself. If the same
+    /// `id` is set more than once, the last value wins.
+    ///
+    /// The setting is ignored if `id` identifies one of the settings defined by
+    /// the HTTP/2.0 specification, which have their own `Builder` functions,
+    /// or if [`MAX_UNKNOWN_SETTINGS`] other custom settings are already set.
+    ///
+    /// The server's own custom settings are available from
+    /// [`RemoteSettings::custom_settings`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .custom_setting(0xf000, 1)
+    ///     .handshake(my_io);
+    /// # client_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`RemoteSettings::custom_settings`]: ../struct.RemoteSettings.html#method.custom_settings
+    /// [`MAX_UNKNOWN_SETTINGS`]: ../frame/constant.MAX_UNKNOWN_SETTINGS.html
+    pub fn custom_setting(&mut self, id: u16, value: u32) -> &mut Self {
+        self.settings.set_unknown_setting(id, value);
+        self
+    }
+
+    /// Sets the padding of the DATA, HEADERS and PUSH_PROMISE frames sent on
+    /// the connection.
+    ///
+    /// Padding hides the actual size of messages from observers of the
+    /// encrypted traffic. See [`Padding`] for the available policies, and for
+    /// how padding interacts with flow control.
+    ///
+    /// The padding of a stream's frames can be changed with
+    /// [`SendStream::set_padding`].
+    ///
+    /// The default value is `Padding::None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use h2::Padding;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .padding(Padding::RoundUp(64))
+    ///     .handshake(my_io);
+    /// # client_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`Padding`]: ../enum.Padding.html
+    /// [`SendStream::set_padding`]: ../struct.SendStream.html#method.set_padding
+    pub fn padding(&mut self, padding: Padding) -> &mut Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Enables merging the data chunks queued on a stream into DATA frames.
+    ///
+    /// By default, every call to [`SendStream::send_data`] is sent in DATA
+    /// frames of its own. When enabled, chunks still queued on a stream are
+    /// merged into a single DATA frame when the stream is next written, as
+    /// long as the frame fits in the peer's max frame size and in the
+    /// stream's window. An empty chunk that ends the stream is folded into
+    /// the END_STREAM flag of the frame before it.
+    ///
+    /// This saves the overhead of a frame header per chunk when sending
+    /// many small chunks. Chunks are never split to be merged, and a chunk
+    /// is never held back waiting for more data.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .coalesce_data_frames(true)
+    ///     .handshake(my_io);
+    /// # client_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`SendStream::send_data`]: ../struct.SendStream.html#method.send_data
+    pub fn coalesce_data_frames(&mut self, enabled: bool) -> &mut Self {
+        self.coalesce_data_frames = enabled;
+        self
+    }
+
+    /// Sets when the frames buffered on the connection are flushed to the
+    /// socket.
+    ///
+    /// See [`FlushPolicy`] for the available policies. The policy can be
+    /// changed once the connection is established with
+    /// [`Connection::set_flush_policy`].
+    ///
+    /// The default value is `FlushPolicy::Idle`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # use h2::FlushPolicy;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .flush_policy(FlushPolicy::Buffered(8 * 1024))
+    ///     .handshake(my_io);
+    /// # client_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`FlushPolicy`]: ../enum.FlushPolicy.html
+    /// [`Connection::set_flush_policy`]: struct.Connection.html#method.set_flush_policy
+    pub fn flush_policy(&mut self, policy: FlushPolicy) -> &mut Self {
+        self.flush_policy = policy;
+        self
+    }
+
+    /// Sets an observer of the frames exchanged on the connection, and of its
+    /// events.
+    ///
+    /// The observer sees every frame read from, or written to, the
+    /// connection, with the decoded headers of HEADERS and PUSH_PROMISE
+    /// frames. See [`FrameObserver`] for details, and for how header values
+    /// are redacted.
+    ///
+    /// By default, there is no observer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// use h2::{FrameObserver, ObservedFrame};
+    ///
+    /// struct Logger;
+    ///
+    /// impl FrameObserver for Logger {
+    ///     fn recv_frame(&self, frame: &ObservedFrame) {
+    ///         println!("recv {:?}", frame);
+    ///     }
+    ///
+    ///     fn send_frame(&self, frame: &ObservedFrame) {
+    ///         println!("send {:?}", frame);
+    ///     }
+    /// }
+    ///
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .frame_observer(Logger)
+    ///     .handshake(my_io);
+    /// # client_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`FrameObserver`]: ../trait.FrameObserver.html
+    pub fn frame_observer<O: FrameObserver>(&mut self, observer: O) -> &mut Self {
+        self.observer = Observer::new(observer);
+        self
+    }
+
+    /// Sets the policy deciding which of the headers sent are added to the
+    /// HPACK dynamic table.
+    ///
+    /// Headers added to the table are sent as a single index the next time
+    /// they are sent, but evict older entries once the table is full. Headers
+    /// whose values rarely repeat, such as request IDs, are best kept out of
+    /// the table. See [`IndexPolicy`] for details.
+    ///
+    /// Defaults to [`DefaultIndexPolicy::new()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate http;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// use h2::hpack::DefaultIndexPolicy;
+    /// use http::header::HeaderName;
+    ///
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// let policy = DefaultIndexPolicy::new()
+    ///     .no_index(HeaderName::from_static("x-request-id"));
+    ///
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .index_policy(policy)
+    ///     .handshake(my_io);
+    /// # client_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`IndexPolicy`]: ../hpack/trait.IndexPolicy.html
+    /// [`DefaultIndexPolicy::new()`]: ../hpack/struct.DefaultIndexPolicy.html#method.new
+    pub fn index_policy<P: IndexPolicy>(&mut self, policy: P) -> &mut Self {
+        self.index_policy = Arc::new(policy);
+        self
+    }
+
+    /// Sets whether `cookie` headers are split into one field per cookie
+    /// pair when sent.
+    ///
+    /// [RFC 7540 §8.1.2.5] allows this so that each cookie pair is
+    /// compressed on its own: the cookies that don't change from one request to
+    /// the next are sent as a single index once they are in the HPACK
+    /// dynamic table. Split cookies are always joined again when received.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .crumble_cookies(true)
+    ///     .handshake(my_io);
+    /// # client_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [RFC 7540 §8.1.2.5]: http://httpwg.org/specs/rfc7540.html#CompressCookie
+    pub fn crumble_cookies(&mut self, enabled: bool) -> &mut Self {
+        self.crumble_cookies = enabled;
+        self
+    }
+
+    /// Sets how strictly the header fields of sent and received messages are
+    /// validated.
+    ///
+    /// With `HeaderValidation::Standard`, responses that break the HTTP/2.0
+    /// rules on header fields are rejected with a `PROTOCOL_ERROR`, and
+    /// sending a message with connection-specific header fields fails.
+    /// `HeaderValidation::Strict` only adds rules for requests, so it
+    /// validates responses the same way. With `HeaderValidation::Lenient`,
+    /// such messages are fixed where possible, which suits peers that
+    /// translate HTTP/1.1 messages. See [`HeaderValidation`] for the details.
+    ///
+    /// Defaults to `HeaderValidation::Standard`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// use h2::HeaderValidation;
+    ///
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .header_validation(HeaderValidation::Lenient)
+    ///     .handshake(my_io);
+    /// # client_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`HeaderValidation`]: ../enum.HeaderValidation.html
+    pub fn header_validation(&mut self, validation: HeaderValidation) -> &mut Self {
+        self.header_validation = validation;
+        self
+    }
+
+    /// Sets the number of stream IDs to hold back before the stream ID space
+    /// of the connection is considered exhausted.
+    ///
+    /// Each request uses a new stream ID, and stream IDs cannot be reused. A
+    /// client may open at most 2^30 streams on a single connection, after
+    /// which the connection must be replaced. This setting makes the
+    /// connection report exhaustion early, when `margin` stream IDs are still
+    /// left, giving the caller time to establish a new connection.
+    ///
+    /// Once the stream IDs are exhausted, [`SendRequest::poll_ready`] and
+    /// [`SendRequest::send_request`] return an error for which
+    /// [`Error::is_stream_ids_exhausted`] returns `true`. Streams that are
+    /// already open are not affected and complete normally.
+    ///
+    /// The default value is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::client::*;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `client_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let client_fut = Builder::new()
+    ///     .stream_id_exhaustion_margin(1_000)
+    ///     .handshake(my_io);
+    /// # client_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`SendRequest::poll_ready`]: struct.SendRequest.html#method.poll_ready
+    /// [`SendRequest::send_request`]: struct.SendRequest.html#method.send_request
+    /// [`Error::is_stream_ids_exhausted`]: ../struct.Error.html#method.is_stream_ids_exhausted
+    pub fn stream_id_exhaustion_margin(&mut self, margin: u32) -> &mut Self {
+        self.stream_id_exhaustion_margin = margin;
+        self
+    }
+
     /// Sets the first stream ID to something other than 1.
     #[cfg(feature = "unstable")]
     pub fn initial_stream_id(&mut self, stream_id: u32) -> &mut Self {
@@ -1234,11 +1848,22 @@ where
         debug!("binding client connection");
 
         let msg: &'static [u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
-        let handshake = io::write_all(io, msg);
+
+        let msg = if builder.wait_for_remote_settings {
+            // The server may not send its SETTINGS frame before receiving
+            // ours, so it is written along with the preface.
+            let settings = builder.settings.clone();
+            let mut buf = BytesMut::with_capacity(msg.len() + frame::HEADER_LEN + settings.payload_len());
+            buf.extend_from_slice(msg);
+            settings.encode(&mut buf);
+            buf.freeze()
+        } else {
+            Bytes::from_static(msg)
+        };
 
         Handshake {
             builder,
-            inner: handshake,
+            state: Handshaking::Flushing(io::write_all(io, msg)),
             _marker: PhantomData,
         }
     }
@@ -1265,6 +1890,48 @@ where
         self.inner.set_target_window_size(size);
     }
 
+    /// Sets when the frames buffered on the connection are flushed to the
+    /// socket.
+    ///
+    /// Setting `FlushPolicy::Manual` corks the connection until [`flush`] is
+    /// called, or until the policy is changed again. See [`FlushPolicy`] for
+    /// the other policies.
+    ///
+    /// [`flush`]: #method.flush
+    /// [`FlushPolicy`]: ../enum.FlushPolicy.html
+    pub fn set_flush_policy(&mut self, policy: FlushPolicy) {
+        self.inner.set_flush_policy(policy);
+    }
+
+    /// Requests the frames buffered on the connection to be flushed to the
+    /// socket, whatever the flush policy.
+    ///
+    /// The connection task is notified, and the frames are flushed once the
+    /// connection has nothing more to write. [`SendRequest::flush`] does the
+    /// same from a request handle.
+    ///
+    /// [`SendRequest::flush`]: struct.SendRequest.html#method.flush
+    pub fn flush(&mut self) {
+        self.inner.request_flush();
+    }
+
+    /// Returns the HPACK compression statistics of the header blocks sent
+    /// on the connection, in requests and trailers.
+    ///
+    /// They tell whether tuning the [`index_policy`] or the header table size
+    /// is worth it for this peer.
+    ///
+    /// [`index_policy`]: struct.Builder.html#method.index_policy
+    pub fn hpack_encoder_stats(&self) -> hpack::Stats {
+        self.inner.hpack_encoder_stats()
+    }
+
+    /// Returns the HPACK compression statistics of the header blocks
+    /// received on the connection, in responses, push promises and trailers.
+    pub fn hpack_decoder_stats(&self) -> hpack::Stats {
+        self.inner.hpack_decoder_stats()
+    }
+
     /// Takes a `PingPong` instance from the connection.
     ///
     /// # Note
@@ -1275,6 +1942,55 @@ where
             .take_user_pings()
             .map(PingPong::new)
     }
+
+    /// Takes an `ExtensionFrames` instance from the connection, used to send
+    /// and receive extension frames.
+    ///
+    /// Until this is called, received extension frames are ignored.
+    ///
+    /// # Note
+    ///
+    /// This may only be called once. Calling multiple times will return `None`.
+    pub fn extension_frames(&mut self) -> Option<ExtensionFrames> {
+        self.inner
+            .take_user_extensions()
+            .map(ExtensionFrames::new)
+    }
+
+    /// Queues an extension frame to be sent to the peer.
+    ///
+    /// The frame is written the next time the connection is polled, after any
+    /// header block being written has been completed. See
+    /// [`ExtensionFrames::send_extension_frame`] to send frames from another
+    /// task.
+    ///
+    /// [`ExtensionFrames::send_extension_frame`]: ../struct.ExtensionFrames.html#method.send_extension_frame
+    pub fn send_extension_frame(&mut self, frame: ExtensionFrame) -> Result<(), ::Error> {
+        self.inner.send_extension_frame(frame.into_internal())
+    }
+
+    /// Returns the settings advertised by the server.
+    ///
+    /// Returns `None` if the server's initial SETTINGS frame has not been
+    /// received yet.
+    pub fn remote_settings(&self) -> Option<RemoteSettings> {
+        self.inner
+            .remote_settings()
+            .map(RemoteSettings::from_internal)
+    }
+
+    /// Returns the last GOAWAY frame received from the server, if any.
+    ///
+    /// Once a GOAWAY frame has been received, no new requests may be sent on
+    /// this connection. Requests on streams with an ID greater than the
+    /// frame's [`last_stream_id`] were not processed by the server.
+    ///
+    /// [`last_stream_id`]: ../struct.GoAway.html#method.last_stream_id
+    pub fn received_go_away(&self) -> Option<GoAway> {
+        self.inner
+            .received_go_away()
+            .map(GoAway::from_internal)
+    }
 }
 
 impl<T, B> Future for Connection<T, B>
@@ -1315,15 +2031,105 @@ where
     type Error = ::Error;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        let res = self.inner.poll()
-            .map_err(::Error::from);
+        let io = match self.state {
+            Handshaking::Flushing(ref mut flush) => {
+                let (io, _) = try_ready!(flush.poll());
+                Some(io)
+            },
+            Handshaking::ReadingSettings(..) => None,
+        };
+
+        if let Some(io) = io {
+            debug!("client connection bound");
+
+            if !self.builder.wait_for_remote_settings {
+                return Ok(Async::Ready(self.connect(io, None)));
+            }
+
+            let buf = BytesMut::with_capacity(self.builder.read_buffer_size);
+            self.state = Handshaking::ReadingSettings(Some(io), buf);
+        }
+
+        let (io, buf) = try_ready!(self.poll_remote_settings());
+        let (send_request, mut connection) = self.connect(io, Some(buf));
+
+        // The SETTINGS frame is buffered, so polling the connection once
+        // applies it without waiting on the socket.
+        if connection.poll()?.is_ready() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed before receiving SETTINGS",
+            ).into());
+        }
+
+        if connection.remote_settings().is_none() {
+            proto_err!(conn: "handshake: expected SETTINGS, got SETTINGS ack");
+            return Err(Reason::PROTOCOL_ERROR.into());
+        }
 
-        let (io, _) = try_ready!(res);
+        Ok(Async::Ready((send_request, connection)))
+    }
+}
+
+impl<T, B> Handshake<T, B>
+where
+    T: AsyncRead + AsyncWrite,
+    B: IntoBuf,
+    B::Buf: 'static,
+{
+    /// Reads until the server's first frame is buffered.
+    fn poll_remote_settings(&mut self) -> Poll<(T, BytesMut), ::Error> {
+        let max_frame_size = self.builder.settings
+            .max_frame_size()
+            .unwrap_or(frame::DEFAULT_MAX_FRAME_SIZE) as usize;
+
+        let (inner, buf) = match self.state {
+            Handshaking::ReadingSettings(ref mut inner, ref mut buf) => (inner, buf),
+            _ => unreachable!(),
+        };
+
+        loop {
+            if buf.len() >= frame::HEADER_LEN {
+                let head = frame::Head::parse(&buf[..frame::HEADER_LEN]);
+
+                if head.kind() != frame::Kind::Settings {
+                    proto_err!(conn: "handshake: expected SETTINGS, got {:?}", head.kind());
+                    return Err(Reason::PROTOCOL_ERROR.into());
+                }
 
-        debug!("client connection bound");
+                // A frame over the max size is left for the codec to reject.
+                let len = BigEndian::read_uint(&buf[..3], 3) as usize;
+                if len > max_frame_size || buf.len() >= frame::HEADER_LEN + len {
+                    break;
+                }
+            }
+
+            if buf.remaining_mut() == 0 {
+                buf.reserve(self.builder.read_buffer_size);
+            }
+
+            let io = inner.as_mut().expect("polled after complete");
+            let n = try_ready!(AsyncRead::read_buf(io, buf));
+            if n == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed before receiving SETTINGS",
+                ).into());
+            }
+        }
 
+        Ok(Async::Ready((inner.take().unwrap(), buf.take())))
+    }
+
+    fn connect(&self, io: T, buf: Option<BytesMut>) -> (SendRequest<B>, Connection<T, B>) {
         // Create the codec
         let mut codec = Codec::new(io);
+        codec.set_observer(self.builder.observer.clone());
+        codec.set_index_policy(self.builder.index_policy.clone());
+        codec.set_crumble_cookies(self.builder.crumble_cookies);
+        codec.set_header_validation(self.builder.header_validation);
+        codec.set_read_buffer_size(self.builder.read_buffer_size);
+        codec.set_flush_policy(self.builder.flush_policy);
 
         if let Some(max) = self.builder.settings.max_frame_size() {
             codec.set_max_recv_frame_size(max as usize);
@@ -1333,21 +2139,38 @@ where
             codec.set_max_recv_header_list_size(max as usize);
         }
 
-        // Send initial settings frame
-        codec
-            .buffer(self.builder.settings.clone().into())
-            .expect("invalid SETTINGS frame");
+        match buf {
+            // The initial settings frame was written with the preface.
+            Some(buf) => codec.set_read_buf(buf),
+            None => {
+                // Send initial settings frame
+                codec
+                    .buffer(self.builder.settings.clone().into())
+                    .expect("invalid SETTINGS frame");
+
+                // The handshake must not wait on the flush policy.
+                codec.request_flush();
+            },
+        }
+
+        let max_stream_id = u32::from(StreamId::MAX)
+            .saturating_sub(self.builder.stream_id_exhaustion_margin.saturating_mul(2));
 
         let inner = proto::Connection::new(codec, proto::Config {
             next_stream_id: self.builder.stream_id,
+            max_stream_id: max_stream_id.into(),
             initial_max_send_streams: self.builder.initial_max_send_streams,
             reset_stream_duration: self.builder.reset_stream_duration,
             reset_stream_max: self.builder.reset_stream_max,
+            padding: self.builder.padding,
+            coalesce_data: self.builder.coalesce_data_frames,
+            header_validation: self.builder.header_validation,
             settings: self.builder.settings.clone(),
         });
         let send_request = SendRequest {
             inner: inner.streams().clone(),
             pending: None,
+            remote_settings_version: 0,
         };
 
         let mut connection = Connection { inner };
@@ -1355,7 +2178,7 @@ where
             connection.set_target_window_size(sz);
         }
 
-        Ok(Async::Ready((send_request, connection)))
+        (send_request, connection)
     }
 }
 
@@ -1550,7 +2373,7 @@ impl proto::Peer for Peer {
     }
 
     fn convert_poll_message(
-        pseudo: Pseudo, fields: HeaderMap, stream_id: StreamId
+        pseudo: Pseudo, fields: HeaderMap, stream_id: StreamId, _: HeaderValidation
     ) -> Result<Self::Poll, RecvError> {
         let mut b = Response::builder();
 
@@ -5,8 +5,19 @@ use std::{error, fmt, io};
 /// Errors that are received
 #[derive(Debug)]
 pub enum RecvError {
+    /// A connection error, the connection must be closed with a GOAWAY
+    /// frame.
     Connection(Reason),
-    Stream { id: StreamId, reason: Reason },
+
+    /// A stream error, the stream must be reset with a RST_STREAM frame.
+    Stream {
+        /// The ID of the stream in error.
+        id: StreamId,
+        /// The reason for resetting the stream.
+        reason: Reason,
+    },
+
+    /// I/O error
     Io(io::Error),
 }
 
@@ -24,7 +35,7 @@ pub enum SendError {
 }
 
 /// Errors caused by users of the library
-#[derive(Debug)]
+#[derive(Debug, Eq, PartialEq)]
 pub enum UserError {
     /// The stream ID is no longer accepting frames.
     InactiveStreamId,
@@ -38,6 +49,9 @@ pub enum UserError {
     /// A header size is too big
     HeaderTooBig,
 
+    /// The header list is bigger than the peer's `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    HeaderListTooBig,
+
     /// The application attempted to initiate too many streams to remote.
     Rejected,
 
@@ -60,6 +74,13 @@ pub enum UserError {
 
     /// Calls `PingPong::send_ping` before receiving a pong.
     SendPingWhilePending,
+
+    /// The data sent exceeds the `content-length` of the message.
+    ContentLengthExceeded,
+
+    /// The stream was ended before all of the data declared by the
+    /// `content-length` of the message was sent.
+    ContentLengthIncomplete,
 }
 
 // ===== impl RecvError =====
@@ -135,6 +156,7 @@ impl error::Error for UserError {
             UnexpectedFrameType => "unexpected frame type",
             PayloadTooBig => "payload too big",
             HeaderTooBig => "header too big",
+            HeaderListTooBig => "header list too big",
             Rejected => "rejected",
             ReleaseCapacityTooBig => "release capacity too big",
             OverflowedStreamId => "stream ID overflowed",
@@ -142,6 +164,8 @@ impl error::Error for UserError {
             MissingUriSchemeAndAuthority => "request URI missing scheme and authority",
             PollResetAfterSendResponse => "poll_reset after send_response is illegal",
             SendPingWhilePending => "send_ping before received previous pong",
+            ContentLengthExceeded => "data exceeds content-length",
+            ContentLengthIncomplete => "stream ended before content-length was reached",
         }
     }
 }
@@ -0,0 +1,38 @@
+/// When frames buffered on a connection are flushed to the socket.
+///
+/// Frames are encoded to a write buffer before being written to the socket.
+/// The policy trades latency for fewer, larger writes. Whatever the policy,
+/// the buffer is flushed when it is full, and when the connection closes.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FlushPolicy {
+    /// Flush once the connection has no more frames to write.
+    ///
+    /// This is the default.
+    Idle,
+
+    /// Flush after each frame.
+    ///
+    /// This writes every frame as soon as possible, at the cost of a write
+    /// per frame.
+    Immediate,
+
+    /// Flush once the given number of bytes is buffered, or once the
+    /// connection has no more frames to write.
+    Buffered(usize),
+
+    /// Only flush when requested, with `Connection::flush` or the `flush`
+    /// method of the request and stream handles, or when the write buffer is
+    /// full.
+    ///
+    /// This corks the connection. SETTINGS acknowledgements, PING,
+    /// WINDOW_UPDATE, RST_STREAM and GOAWAY frames are not held back though:
+    /// they are flushed once the connection has no more frames to write,
+    /// along with any frames buffered before them.
+    Manual,
+}
+
+impl Default for FlushPolicy {
+    fn default() -> Self {
+        FlushPolicy::Idle
+    }
+}
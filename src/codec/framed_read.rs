@@ -3,29 +3,46 @@ use frame::{self, Frame, Kind, Reason};
 use frame::{DEFAULT_MAX_FRAME_SIZE, DEFAULT_SETTINGS_HEADER_TABLE_SIZE, MAX_MAX_FRAME_SIZE};
 
 use hpack;
+use observer::{Direction, Observer};
 
 use futures::*;
 
-use bytes::BytesMut;
+use bytes::{BufMut, BytesMut};
+use byteorder::{BigEndian, ByteOrder};
 
-use std::io;
+use std::{cmp, io};
 
 use tokio_io::AsyncRead;
-use tokio_io::codec::length_delimited;
 
 // 16 MB "sane default" taken from golang http2
 const DEFAULT_SETTINGS_MAX_HEADER_LIST_SIZE: usize = 16 << 20;
 
+/// Default size of the read buffer.
+pub const DEFAULT_READ_BUFFER_SIZE: usize = 8 * 1024;
+
 #[derive(Debug)]
 pub struct FramedRead<T> {
-    inner: length_delimited::FramedRead<T>,
+    inner: T,
+
+    /// Bytes read from `inner`. Frames are split off the front of the buffer
+    /// as they complete, without being copied.
+    buf: BytesMut,
+
+    /// Amount of space reserved in `buf` before reading from `inner`
+    read_buffer_size: usize,
+
+    max_frame_size: usize,
 
     // hpack decoder state
     hpack: hpack::Decoder,
 
     max_header_list_size: usize,
 
+    header_validation: frame::HeaderValidation,
+
     partial: Option<Partial>,
+
+    observer: Observer,
 }
 
 /// Partially loaded headers frame
@@ -45,13 +62,46 @@ enum Continuable {
 }
 
 impl<T> FramedRead<T> {
-    pub fn new(inner: length_delimited::FramedRead<T>) -> FramedRead<T> {
+    pub fn new(inner: T) -> FramedRead<T> {
         FramedRead {
             inner: inner,
+            buf: BytesMut::with_capacity(DEFAULT_READ_BUFFER_SIZE),
+            read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE as usize,
             hpack: hpack::Decoder::new(DEFAULT_SETTINGS_HEADER_TABLE_SIZE),
             max_header_list_size: DEFAULT_SETTINGS_MAX_HEADER_LIST_SIZE,
+            header_validation: frame::HeaderValidation::default(),
             partial: None,
+            observer: Observer::default(),
+        }
+    }
+
+    /// Splits the next complete frame, head included, off the read buffer.
+    fn next_frame(&mut self) -> Result<Option<BytesMut>, RecvError> {
+        if self.buf.len() < frame::HEADER_LEN {
+            return Ok(None);
+        }
+
+        // Parse the length in place, the rest of the head is parsed once the
+        // frame is complete.
+        let len = BigEndian::read_uint(&self.buf[..3], 3) as usize;
+
+        if len > self.max_frame_size {
+            proto_err!(conn: "frame size {} over max frame size {}", len, self.max_frame_size);
+            return Err(RecvError::Connection(Reason::FRAME_SIZE_ERROR));
+        }
+
+        let frame_len = frame::HEADER_LEN + len;
+
+        if self.buf.len() < frame_len {
+            // Make room for the rest of the frame, so that it is read into
+            // the same buffer.
+            let rem = frame_len - self.buf.len();
+            self.buf.reserve(rem);
+            return Ok(None);
         }
+
+        Ok(Some(self.buf.split_to(frame_len)))
     }
 
     fn decode_frame(&mut self, mut bytes: BytesMut) -> Result<Option<Frame>, RecvError> {
@@ -99,7 +149,12 @@ impl<T> FramedRead<T> {
                 let is_end_headers = frame.is_end_headers();
 
                 // Load the HPACK encoded headers
-                match frame.load_hpack(&mut payload, self.max_header_list_size, &mut self.hpack) {
+                match frame.load_hpack(
+                    &mut payload,
+                    self.max_header_list_size,
+                    self.header_validation,
+                    &mut self.hpack,
+                ) {
                     Ok(_) => {},
                     Err(frame::Error::Hpack(hpack::DecoderError::NeedMore(_))) if !is_end_headers => {},
                     Err(frame::Error::MalformedMessage) => {
@@ -233,7 +288,8 @@ impl<T> FramedRead<T> {
 
                 // Extend the buf
                 if partial.buf.is_empty() {
-                    partial.buf = bytes.split_off(frame::HEADER_LEN);
+                    let _ = bytes.split_to(frame::HEADER_LEN);
+                    partial.buf = bytes;
                 } else {
                     if partial.frame.is_over_size() {
                         // If there was left over bytes previously, they may be
@@ -254,10 +310,33 @@ impl<T> FramedRead<T> {
                             return Err(Connection(Reason::COMPRESSION_ERROR));
                         }
                     }
-                    partial.buf.extend_from_slice(&bytes[frame::HEADER_LEN..]);
+
+                    let rem = partial.buf.len();
+
+                    if rem <= frame::HEADER_LEN {
+                        // The bytes left over from the previous fragment fit
+                        // in place of the frame head, move them there instead
+                        // of copying the fragment.
+                        let start = frame::HEADER_LEN - rem;
+                        bytes[start..frame::HEADER_LEN].copy_from_slice(&partial.buf);
+                        let _ = bytes.split_to(start);
+                        partial.buf = bytes;
+                    } else {
+                        // The HPACK decoder needs the rest of the block in a
+                        // single buffer, so the fragment is copied after the
+                        // left over bytes. This only happens when more than 9
+                        // bytes of a field split over the frames are left
+                        // over.
+                        partial.buf.extend_from_slice(&bytes[frame::HEADER_LEN..]);
+                    }
                 }
 
-                match partial.frame.load_hpack(&mut partial.buf, self.max_header_list_size, &mut self.hpack) {
+                match partial.frame.load_hpack(
+                    &mut partial.buf,
+                    self.max_header_list_size,
+                    self.header_validation,
+                    &mut self.hpack,
+                ) {
                     Ok(_) => {},
                     Err(frame::Error::Hpack(hpack::DecoderError::NeedMore(_))) if !is_end_headers => {},
                     Err(frame::Error::MalformedMessage) => {
@@ -282,8 +361,12 @@ impl<T> FramedRead<T> {
                 }
             },
             Kind::Unknown => {
-                // Unknown frames are ignored
-                return Ok(None);
+                // Unknown frames are passed on, in case an extension is
+                // interested in them. The frame header does not keep the raw
+                // frame type around, so grab it before dropping the header.
+                let kind = bytes[3];
+                let _ = bytes.split_to(frame::HEADER_LEN);
+                frame::Extension::load(head, kind, bytes.freeze()).into()
             },
         };
 
@@ -291,18 +374,17 @@ impl<T> FramedRead<T> {
     }
 
     pub fn get_ref(&self) -> &T {
-        self.inner.get_ref()
+        &self.inner
     }
 
     pub fn get_mut(&mut self) -> &mut T {
-        self.inner.get_mut()
+        &mut self.inner
     }
 
     /// Returns the current max frame size setting
-    #[cfg(feature = "unstable")]
     #[inline]
     pub fn max_frame_size(&self) -> usize {
-        self.inner.max_frame_length()
+        self.max_frame_size
     }
 
     /// Updates the max frame size setting.
@@ -311,7 +393,29 @@ impl<T> FramedRead<T> {
     #[inline]
     pub fn set_max_frame_size(&mut self, val: usize) {
         assert!(DEFAULT_MAX_FRAME_SIZE as usize <= val && val <= MAX_MAX_FRAME_SIZE as usize);
-        self.inner.set_max_frame_length(val)
+        self.max_frame_size = val;
+    }
+
+    /// Returns the current read buffer size setting.
+    #[inline]
+    pub fn read_buffer_size(&self) -> usize {
+        self.read_buffer_size
+    }
+
+    /// Updates the read buffer size setting.
+    ///
+    /// The builders reject zero. Here it is rounded up, as reading into a
+    /// buffer without room would be mistaken for EOF.
+    #[inline]
+    pub fn set_read_buffer_size(&mut self, val: usize) {
+        self.read_buffer_size = cmp::max(val, 1);
+    }
+
+    /// Sets bytes that were read from `inner` before the codec was built, to
+    /// be decoded before anything read afterwards.
+    pub fn set_read_buf(&mut self, buf: BytesMut) {
+        debug_assert!(self.buf.is_empty());
+        self.buf = buf;
     }
 
     /// Update the max header list size setting.
@@ -319,6 +423,26 @@ impl<T> FramedRead<T> {
     pub fn set_max_header_list_size(&mut self, val: usize) {
         self.max_header_list_size = val;
     }
+
+    /// Returns the current max header list size setting.
+    #[inline]
+    pub fn max_header_list_size(&self) -> usize {
+        self.max_header_list_size
+    }
+
+    /// Sets how strictly the header fields of received messages are
+    /// validated.
+    pub fn set_header_validation(&mut self, validation: frame::HeaderValidation) {
+        self.header_validation = validation;
+    }
+
+    pub fn hpack_stats(&self) -> hpack::Stats {
+        self.hpack.stats()
+    }
+
+    pub fn set_observer(&mut self, observer: Observer) {
+        self.observer = observer;
+    }
 }
 
 impl<T> Stream for FramedRead<T>
@@ -331,31 +455,54 @@ where
     fn poll(&mut self) -> Poll<Option<Frame>, Self::Error> {
         loop {
             trace!("poll");
-            let bytes = match try_ready!(self.inner.poll().map_err(map_err)) {
+            let bytes = match self.next_frame()? {
                 Some(bytes) => bytes,
-                None => return Ok(Async::Ready(None)),
+                None => {
+                    // Splitting frames off leaves little room at the end
+                    // of the buffer, make sure each read has enough.
+                    if self.buf.remaining_mut() < self.read_buffer_size {
+                        self.buf.reserve(self.read_buffer_size);
+                    }
+
+                    let n = try_ready!(AsyncRead::read_buf(&mut self.inner, &mut self.buf));
+
+                    if n == 0 {
+                        if self.buf.is_empty() {
+                            return Ok(Async::Ready(None));
+                        }
+
+                        let err = io::Error::new(io::ErrorKind::UnexpectedEof, "bytes remaining on stream");
+                        return Err(err.into());
+                    }
+
+                    continue;
+                },
             };
 
             trace!("poll; bytes={}B", bytes.len());
-            if let Some(frame) = self.decode_frame(bytes)? {
-                debug!("received; frame={:?}", frame);
-                return Ok(Async::Ready(Some(frame)));
-            }
-        }
-    }
-}
 
-fn map_err(err: io::Error) -> RecvError {
-    use tokio_io::codec::length_delimited::FrameTooBig;
+            // The head is consumed while decoding, keep a copy for the
+            // observer.
+            let mut head = [0; frame::HEADER_LEN];
+            head.copy_from_slice(&bytes[..frame::HEADER_LEN]);
 
-    if let io::ErrorKind::InvalidData = err.kind() {
-        if let Some(custom) = err.get_ref() {
-            if custom.is::<FrameTooBig>() {
-                return RecvError::Connection(Reason::FRAME_SIZE_ERROR);
+            let res = self.decode_frame(bytes);
+
+            if self.observer.is_enabled() {
+                let headers = match res {
+                    Ok(Some(ref frame)) => self.observer.header_block(frame),
+                    _ => None,
+                };
+
+                self.observer.frame(Direction::Recv, &head, headers);
+            }
+
+            if let Some(frame) = res? {
+                debug!("received; frame={:?}", frame);
+                return Ok(Async::Ready(Some(frame)));
             }
         }
     }
-    err.into()
 }
 
 // ===== impl Continuable =====
@@ -379,11 +526,16 @@ impl Continuable {
         &mut self,
         src: &mut BytesMut,
         max_header_list_size: usize,
+        validation: frame::HeaderValidation,
         decoder: &mut hpack::Decoder,
     ) -> Result<(), frame::Error> {
         match *self {
-            Continuable::Headers(ref mut h) => h.load_hpack(src, max_header_list_size, decoder),
-            Continuable::PushPromise(ref mut p) => p.load_hpack(src, max_header_list_size, decoder),
+            Continuable::Headers(ref mut h) => {
+                h.load_hpack(src, max_header_list_size, validation, decoder)
+            },
+            Continuable::PushPromise(ref mut p) => {
+                p.load_hpack(src, max_header_list_size, validation, decoder)
+            },
         }
     }
 }
@@ -1,13 +1,18 @@
-use codec::UserError;
+use codec::{FlushPolicy, UserError};
 use codec::UserError::*;
 use frame::{self, Frame, FrameSize};
-use hpack;
+use hpack::{self, IndexPolicy};
+use observer::{Direction, Observer};
 
 use bytes::{Buf, BufMut, BytesMut};
 use futures::*;
+use iovec::IoVec;
 use tokio_io::{AsyncRead, AsyncWrite};
 
+use std::cmp;
+use std::collections::VecDeque;
 use std::io::{self, Cursor};
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct FramedWrite<T, B> {
@@ -22,20 +27,46 @@ pub struct FramedWrite<T, B> {
     /// TODO: Should this be a ring buffer?
     buf: Cursor<BytesMut>,
 
+    /// DATA frames that are not fully written yet, in the order they were
+    /// buffered.
+    data: VecDeque<Segment<B>>,
+
     /// Next frame to encode
-    next: Option<Next<B>>,
+    next: Option<frame::Continuation>,
 
-    /// Last data frame
-    last_data_frame: Option<frame::Data<B>>,
+    /// DATA frames that have been fully written
+    written: VecDeque<frame::Data<B>>,
 
     /// Max frame size, this is specified by the peer
     max_frame_size: FrameSize,
+
+    /// Observer of the written frames
+    observer: Observer,
+
+    /// When the buffered frames are flushed
+    flush_policy: FlushPolicy,
+
+    /// Set when a flush was requested, or when a frame that must not be held
+    /// back was buffered, until the buffer is flushed
+    flush_requested: bool,
 }
 
+/// A DATA frame whose payload is written from the frame itself, rather than
+/// copied to the write buffer.
 #[derive(Debug)]
-enum Next<B> {
-    Data(frame::Data<B>),
-    Continuation(frame::Continuation),
+struct Segment<B> {
+    /// The position in the write buffer at which the payload goes, after the
+    /// frame head and before any padding.
+    pos: usize,
+
+    frame: frame::Data<B>,
+}
+
+/// The write buffer, interleaved with the payloads of the queued DATA frames,
+/// as a single `Buf`.
+struct Segments<'a, B: 'a> {
+    buf: &'a mut Cursor<BytesMut>,
+    data: &'a mut VecDeque<Segment<B>>,
 }
 
 /// Initialze the connection with this amount of write buffer.
@@ -52,6 +83,10 @@ const MIN_BUFFER_CAPACITY: usize = frame::HEADER_LEN + CHAIN_THRESHOLD;
 /// than 16kb, so not even close).
 const CHAIN_THRESHOLD: usize = 256;
 
+/// Max number of chained payloads queued at once. Each of them takes up to two
+/// of the buffers handed to a vectored write, along with the write buffer.
+const MAX_CHAINED_FRAMES: usize = 16;
+
 // TODO: Make generic
 impl<T, B> FramedWrite<T, B>
 where
@@ -63,9 +98,13 @@ where
             inner: inner,
             hpack: hpack::Encoder::default(),
             buf: Cursor::new(BytesMut::with_capacity(DEFAULT_BUFFER_CAPACITY)),
+            data: VecDeque::new(),
             next: None,
-            last_data_frame: None,
+            written: VecDeque::new(),
             max_frame_size: frame::DEFAULT_MAX_FRAME_SIZE,
+            observer: Observer::default(),
+            flush_policy: FlushPolicy::default(),
+            flush_requested: false,
         }
     }
 
@@ -74,7 +113,7 @@ where
     /// Calling this function may result in the current contents of the buffer
     /// to be flushed to `T`.
     pub fn poll_ready(&mut self) -> Poll<(), io::Error> {
-        if !self.has_capacity() {
+        if !self.has_capacity() || self.is_flush_due() {
             // Try flushing
             self.flush()?;
 
@@ -96,74 +135,125 @@ where
 
         debug!("send; frame={:?}", item);
 
+        // Every frame starts with its head, which is what the observer needs.
+        let head_pos = self.buf.get_ref().len();
+        let headers = self.observer.header_block(&item);
+
         match item {
             Frame::Data(mut v) => {
                 // Ensure that the payload is not greater than the max frame.
                 let len = v.payload().remaining();
 
-                if len > self.max_frame_size() {
+                if len + v.padding_len() > self.max_frame_size() {
                     return Err(PayloadTooBig);
                 }
 
                 if len >= CHAIN_THRESHOLD {
-                    let head = v.head();
-
-                    // Encode the frame head to the buffer
-                    head.encode(len, self.buf.get_mut());
+                    // Encode the frame head, and the padding that follows
+                    // the payload, to the buffer. The payload itself is
+                    // written from the frame.
+                    self.buf.get_mut().reserve(frame::HEADER_LEN + v.padding_len());
+                    v.encode_head(self.buf.get_mut());
+                    let pos = self.buf.get_ref().len();
+                    v.encode_padding(self.buf.get_mut());
 
                     // Save the data frame
-                    self.next = Some(Next::Data(v));
+                    self.data.push_back(Segment {
+                        pos,
+                        frame: v,
+                    });
                 } else {
+                    self.buf.get_mut().reserve(frame::HEADER_LEN + len + v.padding_len());
                     v.encode_chunk(self.buf.get_mut());
 
                     // The chunk has been fully encoded, so there is no need to
-                    // keep it around
+                    // keep its payload around
                     assert_eq!(v.payload().remaining(), 0, "chunk not fully encoded");
 
-                    // Save off the last frame...
-                    self.last_data_frame = Some(v);
+                    // The frame is handed back once the frames buffered before
+                    // it are written.
+                    self.data.push_back(Segment {
+                        pos: self.buf.get_ref().len(),
+                        frame: v,
+                    });
+                    self.release_written();
                 }
             },
             Frame::Headers(v) => {
-                if let Some(continuation) = v.encode(&mut self.hpack, self.buf.get_mut()) {
-                    self.next = Some(Next::Continuation(continuation));
+                let max_frame_size = self.max_frame_size();
+
+                if let Some(continuation) = v.encode(&mut self.hpack, self.buf.get_mut(), max_frame_size) {
+                    self.next = Some(continuation);
                 }
             },
             Frame::PushPromise(v) => {
-                if let Some(continuation) = v.encode(&mut self.hpack, self.buf.get_mut()) {
-                    self.next = Some(Next::Continuation(continuation));
+                let max_frame_size = self.max_frame_size();
+
+                if let Some(continuation) = v.encode(&mut self.hpack, self.buf.get_mut(), max_frame_size) {
+                    self.next = Some(continuation);
                 }
             },
             Frame::Settings(v) => {
+                // Acknowledgements must be sent without delay (RFC 7540
+                // section 6.5.3), whatever the flush policy.
+                if v.is_ack() {
+                    self.flush_requested = true;
+                }
+
+                self.buf.get_mut().reserve(frame::HEADER_LEN + v.payload_len());
                 v.encode(self.buf.get_mut());
                 trace!("encoded settings; rem={:?}", self.buf.remaining());
             },
-            Frame::GoAway(v) => {
+            Frame::GoAway(mut v) => {
+                // The debug data is purely diagnostic, so rather than fail,
+                // trim it to fit in a single frame.
+                v.truncate_payload(self.max_frame_size());
+
+                // The connection is going away, the peer must be told.
+                self.flush_requested = true;
+                self.buf.get_mut().reserve(frame::HEADER_LEN + v.payload_len());
                 v.encode(self.buf.get_mut());
                 trace!("encoded go_away; rem={:?}", self.buf.remaining());
             },
             Frame::Ping(v) => {
+                // Either the peer is waiting on the acknowledgement, or we
+                // are waiting on the peer's.
+                self.flush_requested = true;
                 v.encode(self.buf.get_mut());
                 trace!("encoded ping; rem={:?}", self.buf.remaining());
             },
             Frame::WindowUpdate(v) => {
+                // Holding back window updates could stall the peer.
+                self.flush_requested = true;
                 v.encode(self.buf.get_mut());
                 trace!("encoded window_update; rem={:?}", self.buf.remaining());
             },
 
-            Frame::Priority(_) => {
-                /*
+            Frame::Priority(v) => {
                 v.encode(self.buf.get_mut());
                 trace!("encoded priority; rem={:?}", self.buf.remaining());
-                */
-                unimplemented!();
             },
             Frame::Reset(v) => {
+                // The peer should stop sending on the stream right away.
+                self.flush_requested = true;
                 v.encode(self.buf.get_mut());
                 trace!("encoded reset; rem={:?}", self.buf.remaining());
             },
+            Frame::Extension(v) => {
+                let len = v.payload().len();
+
+                if len > self.max_frame_size() {
+                    return Err(PayloadTooBig);
+                }
+
+                self.buf.get_mut().reserve(frame::HEADER_LEN + len);
+                v.encode(self.buf.get_mut());
+                trace!("encoded extension; rem={:?}", self.buf.remaining());
+            },
         }
 
+        self.observe(head_pos, headers);
+
         Ok(())
     }
 
@@ -173,46 +263,34 @@ where
 
         loop {
             while !self.is_empty() {
-                match self.next {
-                    Some(Next::Data(ref mut frame)) => {
-                        trace!("  -> queued data frame");
-                        let mut buf = Buf::by_ref(&mut self.buf).chain(frame.payload_mut());
-                        try_ready!(self.inner.write_buf(&mut buf));
-                    },
-                    _ => {
-                        trace!("  -> not a queued data frame");
-                        try_ready!(self.inner.write_buf(&mut self.buf));
-                    },
-                }
+                trace!("  -> queued data frames={}", self.data.len());
+
+                let res = self.inner.write_buf(&mut Segments {
+                    buf: &mut self.buf,
+                    data: &mut self.data,
+                });
+
+                // Hand back the frames that were written, even on error
+                self.release_written();
+
+                try_ready!(res);
             }
 
+            debug_assert!(self.data.is_empty());
+
             // Clear internal buffer
             self.buf.set_position(0);
             self.buf.get_mut().clear();
 
-            // The data frame has been written, so unset it
             match self.next.take() {
-                Some(Next::Data(frame)) => {
-                    self.last_data_frame = Some(frame);
-                    debug_assert!(self.is_empty());
-                    break;
-                },
-                Some(Next::Continuation(frame)) => {
+                Some(frame) => {
                     // Buffer the continuation frame, then try to write again
-                    if let Some(continuation) = frame.encode(&mut self.hpack, self.buf.get_mut()) {
-
-                        // We previously had a CONTINUATION, and after encoding
-                        // it, we got *another* one? Let's just double check
-                        // that at least some progress is being made...
-                        if self.buf.get_ref().len() == frame::HEADER_LEN {
-                            // If *only* the CONTINUATION frame header was
-                            // written, and *no* header fields, we're stuck
-                            // in a loop...
-                            panic!("CONTINUATION frame write loop; header value too big to encode");
-                        }
-
-                        self.next = Some(Next::Continuation(continuation));
-                    }
+                    let max_frame_size = self.max_frame_size();
+
+                    let continuation = frame.encode(self.buf.get_mut(), max_frame_size);
+                    self.observe(0, None);
+
+                    self.next = continuation;
                 },
                 None => {
                     break;
@@ -224,9 +302,22 @@ where
         // Flush the upstream
         try_nb!(self.inner.flush());
 
+        self.flush_requested = false;
+
         Ok(Async::Ready(()))
     }
 
+    /// Flush buffered data to the wire, as there is nothing more to write for
+    /// now, unless the flush policy holds it back.
+    pub fn flush_idle(&mut self) -> Poll<(), io::Error> {
+        if self.flush_policy == FlushPolicy::Manual && !self.flush_requested {
+            trace!("flush_idle; waiting for flush request");
+            return Ok(Async::Ready(()));
+        }
+
+        self.flush()
+    }
+
     /// Close the codec
     pub fn shutdown(&mut self) -> Poll<(), io::Error> {
         try_ready!(self.flush());
@@ -234,13 +325,38 @@ where
     }
 
     fn has_capacity(&self) -> bool {
-        self.next.is_none() && self.buf.get_ref().remaining_mut() >= MIN_BUFFER_CAPACITY
+        self.next.is_none() &&
+            self.data.len() < MAX_CHAINED_FRAMES &&
+            self.buf.get_ref().remaining_mut() >= MIN_BUFFER_CAPACITY
+    }
+
+    /// Returns true if the flush policy requires the buffered frames to be
+    /// flushed before buffering another one.
+    fn is_flush_due(&self) -> bool {
+        match self.flush_policy {
+            FlushPolicy::Immediate => !self.is_empty(),
+            FlushPolicy::Buffered(max) => self.buffered_len() >= max,
+            FlushPolicy::Idle | FlushPolicy::Manual => false,
+        }
+    }
+
+    /// Returns the number of bytes buffered, payloads included.
+    fn buffered_len(&self) -> usize {
+        self.data.iter().fold(self.buf.remaining(), |len, segment| {
+            len + segment.frame.payload().remaining()
+        })
     }
 
     fn is_empty(&self) -> bool {
-        match self.next {
-            Some(Next::Data(ref frame)) => !frame.payload().has_remaining(),
-            _ => !self.buf.has_remaining(),
+        !self.buf.has_remaining() && self.data.is_empty()
+    }
+
+    /// Moves the DATA frames that have been fully written, in order, to
+    /// `written`.
+    fn release_written(&mut self) {
+        while self.data.front().map(|s| !s.frame.payload().has_remaining()).unwrap_or(false) {
+            let segment = self.data.pop_front().unwrap();
+            self.written.push_back(segment.frame);
         }
     }
 }
@@ -257,9 +373,51 @@ impl<T, B> FramedWrite<T, B> {
         self.max_frame_size = val as FrameSize;
     }
 
-    /// Retrieve the last data frame that has been sent
-    pub fn take_last_data_frame(&mut self) -> Option<frame::Data<B>> {
-        self.last_data_frame.take()
+    pub fn set_flush_policy(&mut self, policy: FlushPolicy) {
+        self.flush_policy = policy;
+    }
+
+    /// Requests the buffered frames to be flushed, even if the flush policy
+    /// would hold them back.
+    pub fn request_flush(&mut self) {
+        self.flush_requested = true;
+    }
+
+    pub fn observer(&self) -> &Observer {
+        &self.observer
+    }
+
+    pub fn set_observer(&mut self, observer: Observer) {
+        self.observer = observer;
+    }
+
+    pub fn set_index_policy(&mut self, policy: Arc<dyn IndexPolicy>) {
+        self.hpack.set_index_policy(policy);
+    }
+
+    pub fn set_crumble_cookies(&mut self, enabled: bool) {
+        self.hpack.set_crumble_cookies(enabled);
+    }
+
+    pub fn hpack_stats(&self) -> hpack::Stats {
+        self.hpack.stats()
+    }
+
+    /// Reports the frame whose head was written at `head_pos` to the observer.
+    fn observe(&self, head_pos: usize, headers: Option<Vec<(String, String)>>) {
+        if self.observer.is_enabled() {
+            let head = &self.buf.get_ref()[head_pos..head_pos + frame::HEADER_LEN];
+            self.observer.frame(Direction::Send, head, headers);
+        }
+    }
+
+    /// Retrieve the oldest data frame that has been sent
+    pub fn take_written_data_frame(&mut self) -> Option<frame::Data<B>> {
+        self.written.pop_front()
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
     }
 
     pub fn get_mut(&mut self) -> &mut T {
@@ -267,6 +425,105 @@ impl<T, B> FramedWrite<T, B> {
     }
 }
 
+// ===== impl Segments =====
+
+impl<'a, B: Buf> Segments<'a, B> {
+    /// Returns the next DATA frame with payload left to write.
+    fn next_payload(&self) -> Option<&Segment<B>> {
+        self.data.iter().find(|s| s.frame.payload().has_remaining())
+    }
+}
+
+impl<'a, B: Buf> Buf for Segments<'a, B> {
+    fn remaining(&self) -> usize {
+        let payloads: usize = self.data.iter()
+            .map(|s| s.frame.payload().remaining())
+            .sum();
+
+        self.buf.remaining() + payloads
+    }
+
+    fn bytes(&self) -> &[u8] {
+        let pos = self.buf.position() as usize;
+
+        match self.next_payload() {
+            Some(segment) if segment.pos == pos => segment.frame.payload().bytes(),
+            Some(segment) => &self.buf.get_ref()[pos..segment.pos],
+            None => self.buf.bytes(),
+        }
+    }
+
+    fn bytes_vec<'b>(&'b self, dst: &mut [&'b IoVec]) -> usize {
+        let buf = self.buf.get_ref();
+        let mut pos = self.buf.position() as usize;
+        let mut n = 0;
+
+        for segment in self.data.iter() {
+            let payload = segment.frame.payload();
+
+            if !payload.has_remaining() {
+                continue;
+            }
+
+            if n == dst.len() {
+                return n;
+            }
+
+            if segment.pos > pos {
+                dst[n] = buf[pos..segment.pos].into();
+                n += 1;
+                pos = segment.pos;
+            }
+
+            let m = payload.bytes_vec(&mut dst[n..]);
+            let len: usize = dst[n..n + m].iter().map(|v| v.len()).sum();
+            n += m;
+
+            // What follows the payload can only be written once all of it
+            // is.
+            if len < payload.remaining() {
+                return n;
+            }
+        }
+
+        if n < dst.len() && pos < buf.len() {
+            dst[n] = buf[pos..].into();
+            n += 1;
+        }
+
+        n
+    }
+
+    fn advance(&mut self, mut cnt: usize) {
+        while cnt > 0 {
+            let pos = self.buf.position() as usize;
+
+            let segment = self.data.iter_mut()
+                .find(|s| s.frame.payload().has_remaining());
+
+            let n = match segment {
+                Some(segment) => {
+                    if segment.pos == pos {
+                        let n = cmp::min(cnt, segment.frame.payload().remaining());
+                        segment.frame.payload_mut().advance(n);
+                        n
+                    } else {
+                        let n = cmp::min(cnt, segment.pos - pos);
+                        self.buf.advance(n);
+                        n
+                    }
+                },
+                None => {
+                    self.buf.advance(cnt);
+                    cnt
+                },
+            };
+
+            cnt -= n;
+        }
+    }
+}
+
 impl<T: io::Read, B> io::Read for FramedWrite<T, B> {
     fn read(&mut self, dst: &mut [u8]) -> io::Result<usize> {
         self.inner.read(dst)
@@ -285,14 +542,3 @@ impl<T: AsyncRead, B> AsyncRead for FramedWrite<T, B> {
         self.inner.prepare_uninitialized_buffer(buf)
     }
 }
-
-#[cfg(feature = "unstable")]
-mod unstable {
-    use super::*;
-
-    impl<T, B> FramedWrite<T, B> {
-        pub fn get_ref(&self) -> &T {
-            &self.inner
-        }
-    }
-}
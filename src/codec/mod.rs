@@ -2,25 +2,61 @@
 #![allow(deprecated)]
 
 mod error;
+mod flush;
 mod framed_read;
 mod framed_write;
 
 pub use self::error::{RecvError, SendError, UserError};
+pub use self::flush::FlushPolicy;
+
+pub(crate) use self::framed_read::DEFAULT_READ_BUFFER_SIZE;
 
 use self::framed_read::FramedRead;
 use self::framed_write::FramedWrite;
 
 use frame::{self, Data, Frame};
+use hpack::{self, IndexPolicy};
+use observer::Observer;
 
 use futures::*;
 
 use tokio_io::{AsyncRead, AsyncWrite};
-use tokio_io::codec::length_delimited;
 
-use bytes::Buf;
+use bytes::{Buf, BytesMut};
 
 use std::io;
-
+use std::sync::Arc;
+
+/// Reads and writes HTTP/2.0 frames on an I/O handle.
+///
+/// `Codec` is a `Stream` of the frames read from the peer and a `Sink` of the
+/// frames to send to it. It only takes care of the framing layer: the
+/// connection preface, flow control and stream states are left to the caller.
+///
+/// Frames larger than the max frame size, or header blocks larger than the
+/// max header list size, are rejected when they are read. Header blocks
+/// larger than the max frame size are split across CONTINUATION frames when
+/// they are written.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate h2;
+/// # extern crate tokio_io;
+/// # use tokio_io::*;
+/// use h2::Codec;
+/// use h2::frame::{Frame, Ping};
+/// use std::io::Cursor;
+///
+/// # fn doc<T: AsyncRead + AsyncWrite>(io: T) {
+/// let mut codec: Codec<_, Cursor<Vec<u8>>> = Codec::new(io);
+/// codec.set_max_send_frame_size(32_768);
+///
+/// let ping = Ping::new([1, 2, 3, 4, 5, 6, 7, 8]);
+/// codec.buffer(Frame::Ping(ping)).unwrap();
+/// # }
+/// # pub fn main() {}
+/// ```
 #[derive(Debug)]
 pub struct Codec<T, B> {
     inner: FramedRead<FramedWrite<T, B>>,
@@ -42,15 +78,8 @@ where
         // Wrap with writer
         let framed_write = FramedWrite::new(io);
 
-        // Delimit the frames
-        let delimited = length_delimited::Builder::new()
-            .big_endian()
-            .length_field_length(3)
-            .length_adjustment(9)
-            .num_skip(0) // Don't skip the header
-            .new_read(framed_write);
-
-        let mut inner = FramedRead::new(delimited);
+        // Wrap with reader
+        let mut inner = FramedRead::new(framed_write);
 
         // Use FramedRead's method since it checks the value is within range.
         inner.set_max_frame_size(max_frame_size);
@@ -77,12 +106,31 @@ impl<T, B> Codec<T, B> {
     ///
     /// This is the largest size this codec will accept from the wire. Larger
     /// frames will be rejected.
-    #[cfg(feature = "unstable")]
     #[inline]
     pub fn max_recv_frame_size(&self) -> usize {
         self.inner.max_frame_size()
     }
 
+    /// Sets the size of the buffer that the I/O handle is read into.
+    ///
+    /// Frames are split off the buffer without being copied, so the buffer
+    /// is grown as needed to hold a whole frame. This sets how much space is
+    /// reserved for each read otherwise. A `val` of zero is rounded up to one.
+    pub fn set_read_buffer_size(&mut self, val: usize) {
+        self.inner.set_read_buffer_size(val)
+    }
+
+    /// Sets bytes that were read from the I/O handle before the codec was
+    /// built. They are decoded before anything read afterwards.
+    pub(crate) fn set_read_buf(&mut self, buf: BytesMut) {
+        self.inner.set_read_buf(buf)
+    }
+
+    /// Returns the size of the buffer that the I/O handle is read into.
+    pub fn read_buffer_size(&self) -> usize {
+        self.inner.read_buffer_size()
+    }
+
     /// Returns the max frame size that can be sent to the peer.
     pub fn max_send_frame_size(&self) -> usize {
         self.inner.get_ref().max_frame_size()
@@ -98,8 +146,20 @@ impl<T, B> Codec<T, B> {
         self.inner.set_max_header_list_size(val);
     }
 
+    /// Sets how strictly the header fields of received messages are
+    /// validated.
+    pub(crate) fn set_header_validation(&mut self, validation: frame::HeaderValidation) {
+        self.inner.set_header_validation(validation);
+    }
+
+    /// Returns the max header list size that can be received.
+    ///
+    /// Header blocks that decode to a larger header list are rejected.
+    pub fn max_recv_header_list_size(&self) -> usize {
+        self.inner.max_header_list_size()
+    }
+
     /// Get a reference to the inner stream.
-    #[cfg(feature = "unstable")]
     pub fn get_ref(&self) -> &T {
         self.inner.get_ref().get_ref()
     }
@@ -109,9 +169,52 @@ impl<T, B> Codec<T, B> {
         self.inner.get_mut().get_mut()
     }
 
-    /// Takes the data payload value that was fully written to the socket
-    pub(crate) fn take_last_data_frame(&mut self) -> Option<Data<B>> {
-        self.framed_write().take_last_data_frame()
+    /// Sets the observer of the frames read and written.
+    pub(crate) fn set_observer(&mut self, observer: Observer) {
+        self.inner.set_observer(observer.clone());
+        self.framed_write().set_observer(observer);
+    }
+
+    /// Sets the policy deciding which header fields are indexed.
+    pub(crate) fn set_index_policy(&mut self, policy: Arc<dyn IndexPolicy>) {
+        self.framed_write().set_index_policy(policy);
+    }
+
+    /// Sets whether `cookie` headers are split into their cookie pairs.
+    pub(crate) fn set_crumble_cookies(&mut self, enabled: bool) {
+        self.framed_write().set_crumble_cookies(enabled);
+    }
+
+    /// Returns the compression statistics of the header blocks written.
+    pub(crate) fn hpack_encoder_stats(&self) -> hpack::Stats {
+        self.inner.get_ref().hpack_stats()
+    }
+
+    /// Returns the compression statistics of the header blocks read.
+    pub(crate) fn hpack_decoder_stats(&self) -> hpack::Stats {
+        self.inner.hpack_stats()
+    }
+
+    /// Returns the observer of the frames read and written.
+    pub(crate) fn observer(&self) -> &Observer {
+        self.inner.get_ref().observer()
+    }
+
+    /// Sets when the buffered frames are flushed.
+    pub(crate) fn set_flush_policy(&mut self, policy: FlushPolicy) {
+        self.framed_write().set_flush_policy(policy);
+    }
+
+    /// Requests the buffered frames to be flushed the next time the codec is
+    /// idle, whatever the flush policy.
+    pub(crate) fn request_flush(&mut self) {
+        self.framed_write().request_flush();
+    }
+
+    /// Takes the oldest data frame whose payload was fully written to the
+    /// socket
+    pub(crate) fn take_written_data_frame(&mut self) -> Option<Data<B>> {
+        self.framed_write().take_written_data_frame()
     }
 
     fn framed_write(&mut self) -> &mut FramedWrite<T, B> {
@@ -144,6 +247,12 @@ where
         self.framed_write().flush()
     }
 
+    /// Flush buffered data to the wire, as there is nothing more to write for
+    /// now, unless the flush policy holds it back.
+    pub(crate) fn flush_idle(&mut self) -> Poll<(), io::Error> {
+        self.framed_write().flush_idle()
+    }
+
     /// Shutdown the send half
     pub fn shutdown(&mut self) -> Poll<(), io::Error> {
         self.framed_write().shutdown()
@@ -1,5 +1,9 @@
 use codec::{SendError, UserError};
+use frame;
 use proto;
+use share::StreamId;
+
+use bytes::Bytes;
 
 use std::{error, fmt, io};
 
@@ -28,6 +32,14 @@ enum Kind {
     /// action taken by the peer (i.e. a protocol error).
     Proto(Reason),
 
+    /// The remote peer sent a GOAWAY frame.
+    GoAway(frame::GoAway, Reason),
+
+    /// The remote peer did not process the stream. It either reset the
+    /// stream with `REFUSED_STREAM`, or sent a GOAWAY frame with a last
+    /// stream ID lower than the stream's.
+    Unprocessed(Option<frame::GoAway>, Reason),
+
     /// An error resulting from an invalid action taken by the user of this
     /// library.
     User(UserError),
@@ -45,11 +57,90 @@ impl Error {
     /// action taken by the peer (i.e. a protocol error).
     pub fn reason(&self) -> Option<Reason> {
         match self.kind {
-            Kind::Proto(reason) => Some(reason),
+            Kind::Proto(reason) |
+            Kind::GoAway(_, reason) |
+            Kind::Unprocessed(_, reason) => Some(reason),
             _ => None,
         }
     }
 
+    /// Returns true if the error was caused by a GOAWAY frame received from
+    /// the remote peer.
+    pub fn is_go_away(&self) -> bool {
+        self.go_away_last_stream_id().is_some()
+    }
+
+    /// If the error was caused by a GOAWAY frame received from the remote
+    /// peer, the opaque debug data included in that frame.
+    ///
+    /// The debug data is intended for diagnostic purposes only and carries
+    /// no semantic value. It may be empty.
+    pub fn go_away_debug_data(&self) -> Option<&Bytes> {
+        match self.kind {
+            Kind::GoAway(ref frame, _) |
+            Kind::Unprocessed(Some(ref frame), _) => Some(frame.debug_data()),
+            _ => None,
+        }
+    }
+
+    /// If the error was caused by a GOAWAY frame received from the remote
+    /// peer, the last stream ID included in that frame.
+    ///
+    /// Streams with a higher ID were not processed by the peer, and may be
+    /// retried on a new connection. Streams up to this ID may have been
+    /// processed.
+    pub fn go_away_last_stream_id(&self) -> Option<StreamId> {
+        match self.kind {
+            Kind::GoAway(ref frame, _) |
+            Kind::Unprocessed(Some(ref frame), _) => {
+                Some(StreamId::from_internal(frame.last_stream_id()))
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns true if the remote peer did not process the request, so that
+    /// it may safely be retried, e.g. on a new connection.
+    ///
+    /// This is the case when the peer reset the stream with
+    /// `REFUSED_STREAM`, or when it sent a GOAWAY frame with a last stream
+    /// ID lower than the stream's ID. `SendRequest::poll_ready` errors are
+    /// retryable as well after such a GOAWAY frame, or when the connection
+    /// ran out of stream IDs. See [Section 8.1.4] of the HTTP/2.0 spec.
+    ///
+    /// Streams that we reset ourselves, and errors of the connection as a
+    /// whole, are not retryable.
+    ///
+    /// Note that `h2` does not keep a copy of the request or its body, so
+    /// replaying the request is left to the caller.
+    ///
+    /// [Section 8.1.4]: http://httpwg.org/specs/rfc7540.html#Reliability
+    pub fn is_retryable(&self) -> bool {
+        match self.kind {
+            Kind::Unprocessed(..) => true,
+            _ => self.is_stream_ids_exhausted(),
+        }
+    }
+
+    /// Returns true if the connection ran out of stream IDs.
+    ///
+    /// Stream IDs cannot be reused, so no new streams may be opened on the
+    /// connection, and a new connection must be established. Streams that
+    /// were already open are not affected.
+    pub fn is_stream_ids_exhausted(&self) -> bool {
+        self.user_error() == Some(&UserError::OverflowedStreamId)
+    }
+
+    /// Returns true if headers could not be sent because they exceed the
+    /// peer's `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    ///
+    /// The peer would refuse such headers, so nothing was sent. A proxy may
+    /// want to respond to its own client with `431 Request Header Fields Too
+    /// Large` instead.
+    pub fn is_header_list_too_big(&self) -> bool {
+        self.user_error() == Some(&UserError::HeaderListTooBig)
+    }
+
     /// Returns the true if the error is an io::Error
     pub fn is_io(&self) -> bool {
         match self.kind {
@@ -73,6 +164,13 @@ impl Error {
             _ => None,
         }
     }
+
+    fn user_error(&self) -> Option<&UserError> {
+        match self.kind {
+            Kind::User(ref e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 impl From<proto::Error> for Error {
@@ -82,6 +180,8 @@ impl From<proto::Error> for Error {
         Error {
             kind: match src {
                 Proto(reason) => Kind::Proto(reason),
+                GoAway(frame, reason) => Kind::GoAway(frame, reason),
+                Unprocessed(frame, reason) => Kind::Unprocessed(frame, reason),
                 Io(e) => Kind::Io(e),
             },
         }
@@ -127,7 +227,9 @@ impl fmt::Display for Error {
         use self::Kind::*;
 
         match self.kind {
-            Proto(ref reason) => write!(fmt, "protocol error: {}", reason),
+            Proto(ref reason) |
+            GoAway(_, ref reason) |
+            Unprocessed(_, ref reason) => write!(fmt, "protocol error: {}", reason),
             User(ref e) => write!(fmt, "user error: {}", e),
             Io(ref e) => fmt::Display::fmt(e, fmt),
         }
@@ -140,7 +242,9 @@ impl error::Error for Error {
 
         match self.kind {
             Io(ref e) => error::Error::description(e),
-            Proto(ref reason) => reason.description(),
+            Proto(ref reason) |
+            GoAway(_, ref reason) |
+            Unprocessed(_, ref reason) => reason.description(),
             User(ref user) => user.description(),
         }
     }
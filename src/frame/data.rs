@@ -63,18 +63,46 @@ impl<T> Data<T> {
         }
     }
 
-    /// Returns whther the `PADDED` flag is set on this frame.
-    #[cfg(feature = "unstable")]
+    /// Returns whether the `PADDED` flag is set on this frame.
     pub fn is_padded(&self) -> bool {
         self.flags.is_padded()
     }
 
     /// Sets the value for the `PADDED` flag on this frame.
-    #[cfg(feature = "unstable")]
+    ///
+    /// Unlike [`set_padding`], the payload is left as is, so it must hold the
+    /// pad length and the padding, as in a received frame.
+    ///
+    /// [`set_padding`]: #method.set_padding
     pub fn set_padded(&mut self) {
         self.flags.set_padded();
     }
 
+    /// Pads the frame with `pad_len` bytes when it is encoded.
+    pub fn set_padding(&mut self, pad_len: u8) {
+        self.flags.set_padded();
+        self.pad_len = Some(pad_len);
+    }
+
+    /// Removes the frame's padding.
+    pub(crate) fn unset_padding(&mut self) {
+        self.flags.unset_padded();
+        self.pad_len = None;
+    }
+
+    /// Returns the number of bytes of padding, if the frame is padded.
+    pub fn pad_len(&self) -> Option<u8> {
+        self.pad_len
+    }
+
+    /// Returns the number of bytes that padding adds to the frame, including
+    /// the pad length field.
+    ///
+    /// Padding counts against flow control along with the payload.
+    pub(crate) fn padding_len(&self) -> usize {
+        self.pad_len.map(|pad_len| pad_len as usize + 1).unwrap_or(0)
+    }
+
     /// Returns a reference to this frame's payload.
     ///
     /// This does **not** include any padding that might have been originally
@@ -139,6 +167,12 @@ impl Data<Bytes> {
             pad_len: pad_len,
         })
     }
+
+    /// Returns the number of bytes of the frame that count against flow
+    /// control, that is the payload and any padding.
+    pub(crate) fn flow_controlled_len(&self) -> usize {
+        self.data.len() + self.padding_len()
+    }
 }
 
 impl<T: Buf> Data<T> {
@@ -149,11 +183,32 @@ impl<T: Buf> Data<T> {
     /// Panics if `dst` cannot contain the data frame.
     pub(crate) fn encode_chunk<U: BufMut>(&mut self, dst: &mut U) {
         let len = self.data.remaining() as usize;
+        let padding_len = self.padding_len();
 
-        assert!(dst.remaining_mut() >= len);
+        assert!(dst.remaining_mut() >= len + padding_len);
 
-        self.head().encode(len, dst);
+        self.encode_head(dst);
         dst.put(&mut self.data);
+        self.encode_padding(dst);
+    }
+
+    /// Encode the frame head, along with the pad length if the frame is
+    /// padded. This is what precedes the payload.
+    pub(crate) fn encode_head<U: BufMut>(&self, dst: &mut U) {
+        let len = self.data.remaining() + self.padding_len();
+
+        self.head().encode(len, dst);
+
+        if let Some(pad_len) = self.pad_len {
+            dst.put_u8(pad_len);
+        }
+    }
+
+    /// Encode the padding that follows the payload, if any.
+    pub(crate) fn encode_padding<U: BufMut>(&self, dst: &mut U) {
+        if let Some(pad_len) = self.pad_len {
+            util::put_padding(dst, pad_len);
+        }
     }
 }
 
@@ -205,10 +260,13 @@ impl DataFlags {
         self.0 & PADDED == PADDED
     }
 
-    #[cfg(feature = "unstable")]
     fn set_padded(&mut self) {
         self.0 |= PADDED
     }
+
+    fn unset_padded(&mut self) {
+        self.0 &= !PADDED
+    }
 }
 
 impl Default for DataFlags {
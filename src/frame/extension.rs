@@ -0,0 +1,90 @@
+use frame::{self, Head, Kind, StreamId};
+
+use bytes::{BufMut, Bytes};
+
+/// A frame of a type that is not defined by RFC 7540.
+///
+/// These are used by protocol extensions. The frame type, flags and payload
+/// are opaque to the connection.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Extension {
+    kind: u8,
+    flags: u8,
+    stream_id: StreamId,
+    payload: Bytes,
+}
+
+impl Extension {
+    /// Creates a new extension frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `kind` is a frame type defined by RFC 7540.
+    pub fn new(kind: u8, flags: u8, stream_id: StreamId, payload: Bytes) -> Self {
+        assert!(Kind::new(kind) == Kind::Unknown, "frame type is not an extension");
+
+        Extension {
+            kind,
+            flags,
+            stream_id,
+            payload,
+        }
+    }
+
+    /// Returns the frame type.
+    pub fn kind(&self) -> u8 {
+        self.kind
+    }
+
+    /// Returns the frame flags.
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// Returns the stream identifier that this frame is associated with.
+    pub fn stream_id(&self) -> StreamId {
+        self.stream_id
+    }
+
+    /// Returns the frame payload.
+    pub fn payload(&self) -> &Bytes {
+        &self.payload
+    }
+
+    /// Load an extension frame, `kind` being the raw frame type, as `head`
+    /// only knows it is unknown.
+    pub(crate) fn load(head: Head, kind: u8, payload: Bytes) -> Extension {
+        debug_assert_eq!(head.kind(), Kind::Unknown);
+
+        Extension {
+            kind,
+            flags: head.flag(),
+            stream_id: head.stream_id(),
+            payload,
+        }
+    }
+
+    pub(crate) fn encode<B: BufMut>(&self, dst: &mut B) {
+        trace!(
+            "encoding EXTENSION; kind={}; flags={}; id={:?}; len={}",
+            self.kind,
+            self.flags,
+            self.stream_id,
+            self.payload.len()
+        );
+
+        // `Head` can only encode the frame types it knows about, so the frame
+        // header is written by hand.
+        dst.put_uint_be(self.payload.len() as u64, 3);
+        dst.put_u8(self.kind);
+        dst.put_u8(self.flags);
+        dst.put_u32_be(self.stream_id.into());
+        dst.put_slice(&self.payload);
+    }
+}
+
+impl<B> From<Extension> for frame::Frame<B> {
+    fn from(src: Extension) -> Self {
+        frame::Frame::Extension(src)
+    }
+}
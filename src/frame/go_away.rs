@@ -1,49 +1,84 @@
 use frame::{self, Error, Head, Kind, Reason, StreamId};
 
-use bytes::{BufMut};
+use bytes::{BufMut, Bytes};
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// GOAWAY frame
+///
+/// Initiates the shutdown of a connection, or signals a serious error
+/// condition. Streams with an ID greater than `last_stream_id` were not
+/// processed, and may be safely retried on a new connection.
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct GoAway {
     last_stream_id: StreamId,
     error_code: Reason,
+    debug_data: Bytes,
 }
 
 impl GoAway {
+    /// Creates a new GOAWAY frame.
     pub fn new(last_stream_id: StreamId, reason: Reason) -> Self {
+        GoAway::with_debug_data(last_stream_id, reason, Bytes::new())
+    }
+
+    /// Creates a new GOAWAY frame that carries opaque debug data.
+    pub fn with_debug_data(last_stream_id: StreamId, reason: Reason, debug_data: Bytes) -> Self {
         GoAway {
             last_stream_id,
             error_code: reason,
+            debug_data,
         }
     }
 
+    /// Returns the last stream ID that was, or may be, processed.
     pub fn last_stream_id(&self) -> StreamId {
         self.last_stream_id
     }
 
+    /// Returns the reason for closing the connection.
     pub fn reason(&self) -> Reason {
         self.error_code
     }
 
-    pub fn load(payload: &[u8]) -> Result<GoAway, Error> {
+    /// Returns the opaque debug data.
+    pub fn debug_data(&self) -> &Bytes {
+        &self.debug_data
+    }
+
+    /// Truncate the opaque debug data so that the frame payload is no
+    /// longer than `max_len`.
+    pub(crate) fn truncate_payload(&mut self, max_len: usize) {
+        if self.payload_len() > max_len {
+            self.debug_data.truncate(max_len.saturating_sub(8));
+        }
+    }
+
+    pub(crate) fn payload_len(&self) -> usize {
+        8 + self.debug_data.len()
+    }
+
+    pub(crate) fn load(payload: &[u8]) -> Result<GoAway, Error> {
         if payload.len() < 8 {
             return Err(Error::BadFrameSize);
         }
 
         let (last_stream_id, _) = StreamId::parse(&payload[..4]);
         let error_code = unpack_octets_4!(payload, 4, u32);
+        let debug_data = Bytes::from(&payload[8..]);
 
         Ok(GoAway {
             last_stream_id: last_stream_id,
             error_code: error_code.into(),
+            debug_data,
         })
     }
 
-    pub fn encode<B: BufMut>(&self, dst: &mut B) {
+    pub(crate) fn encode<B: BufMut>(&self, dst: &mut B) {
         trace!("encoding GO_AWAY; code={:?}", self.error_code);
         let head = Head::new(Kind::GoAway, 0, StreamId::zero());
-        head.encode(8, dst);
+        head.encode(self.payload_len(), dst);
         dst.put_u32_be(self.last_stream_id.into());
         dst.put_u32_be(self.error_code.into());
+        dst.put_slice(&self.debug_data);
     }
 }
 
@@ -52,3 +87,4 @@ impl<B> From<GoAway> for frame::Frame<B> {
         frame::Frame::GoAway(src)
     }
 }
+
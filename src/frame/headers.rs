@@ -1,24 +1,28 @@
 use super::{util, StreamDependency, StreamId};
-use frame::{Error, Frame, Head, Kind};
+use frame::{self, Error, Frame, Head, HeaderValidation, Kind, Padding};
+use frame::validation::is_connection_specific;
 use hpack;
 
-use http::{uri, HeaderMap, Method, StatusCode, Uri};
+use http::{uri, HeaderMap, Method, Request, Response, StatusCode, Uri};
 use http::header::{self, HeaderName, HeaderValue};
 
 use byteorder::{BigEndian, ByteOrder};
 use bytes::{Bytes, BytesMut};
 use string::String;
 
-use std::fmt;
+use std::{cmp, fmt};
 use std::io::Cursor;
 
-// Minimum MAX_FRAME_SIZE is 16kb, so save some arbitrary space for frame
-// head and other header bits.
-const MAX_HEADER_LENGTH: usize = 1024 * 16 - 100;
+// A header field is encoded whole before it is split across frames, so refuse
+// fields bigger than the largest header list accepted by default, rather than
+// buffering them.
+const MAX_HEADER_LENGTH: usize = 16 << 20;
 
 /// Header frame
 ///
-/// This could be either a request or a response.
+/// This could be either a request, a response or trailers. The header block
+/// is HPACK encoded when the frame is written, and split across CONTINUATION
+/// frames if it does not fit in a single frame.
 #[derive(Eq, PartialEq)]
 pub struct Headers {
     /// The ID of the stream with which this frame is associated.
@@ -32,11 +36,18 @@ pub struct Headers {
 
     /// The associated flags
     flags: HeadersFlag,
+
+    /// The padding to add when encoding the frame
+    padding: Padding,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub struct HeadersFlag(u8);
 
+/// PUSH_PROMISE frame
+///
+/// Reserves a stream for a response the server is going to push, along with
+/// the request that the response answers.
 #[derive(Eq, PartialEq)]
 pub struct PushPromise {
     /// The ID of the stream with which this frame is associated.
@@ -50,6 +61,9 @@ pub struct PushPromise {
 
     /// The associated flags
     flags: PushPromiseFlag,
+
+    /// The padding to add when encoding the frame
+    padding: Padding,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq)]
@@ -63,16 +77,24 @@ pub struct Continuation {
     header_block: EncodingHeaderBlock,
 }
 
+/// The pseudo header fields of a header block.
 // TODO: These fields shouldn't be `pub`
 #[derive(Debug, Default, Eq, PartialEq)]
 pub struct Pseudo {
     // Request
+
+    /// The `:method` pseudo header field.
     pub method: Option<Method>,
+    /// The `:scheme` pseudo header field.
     pub scheme: Option<String<Bytes>>,
+    /// The `:authority` pseudo header field.
     pub authority: Option<String<Bytes>>,
+    /// The `:path` pseudo header field.
     pub path: Option<String<Bytes>>,
 
     // Response
+
+    /// The `:status` pseudo header field.
     pub status: Option<StatusCode>,
 }
 
@@ -100,18 +122,14 @@ struct HeaderBlock {
 
 #[derive(Debug)]
 struct EncodingHeaderBlock {
-    /// Argument to pass to the HPACK encoder to resume encoding
-    hpack: Option<hpack::EncodeState>,
-
-    /// remaining headers to encode
-    headers: Iter,
+    /// The HPACK encoded header block that remains to be written
+    hpack: Bytes,
 }
 
 const END_STREAM: u8 = 0x1;
 const END_HEADERS: u8 = 0x4;
 const PADDED: u8 = 0x8;
 const PRIORITY: u8 = 0x20;
-const ALL: u8 = END_STREAM | END_HEADERS | PADDED | PRIORITY;
 
 // ===== impl Headers =====
 
@@ -127,9 +145,34 @@ impl Headers {
                 pseudo: pseudo,
             },
             flags: HeadersFlag::default(),
+            padding: Padding::None,
         }
     }
 
+    /// Create a new HEADERS frame for the head of `request`.
+    ///
+    /// The header fields are sent as is: connection-specific fields, which
+    /// are not allowed in HTTP/2.0, are not removed.
+    pub fn request(stream_id: StreamId, request: Request<()>) -> Self {
+        let (parts, _) = request.into_parts();
+        let pseudo = Pseudo::request(parts.method, parts.uri);
+
+        Headers::new(stream_id, pseudo, parts.headers)
+    }
+
+    /// Create a new HEADERS frame for the head of `response`.
+    ///
+    /// The header fields are sent as is: connection-specific fields, which
+    /// are not allowed in HTTP/2.0, are not removed.
+    pub fn response(stream_id: StreamId, response: Response<()>) -> Self {
+        let (parts, _) = response.into_parts();
+        let pseudo = Pseudo::response(parts.status);
+
+        Headers::new(stream_id, pseudo, parts.headers)
+    }
+
+    /// Create a new HEADERS frame carrying trailers, with the `END_STREAM`
+    /// flag set.
     pub fn trailers(stream_id: StreamId, fields: HeaderMap) -> Self {
         let mut flags = HeadersFlag::default();
         flags.set_end_stream();
@@ -143,13 +186,14 @@ impl Headers {
                 pseudo: Pseudo::default(),
             },
             flags: flags,
+            padding: Padding::None,
         }
     }
 
     /// Loads the header frame but doesn't actually do HPACK decoding.
     ///
     /// HPACK decoding is done in the `load_hpack` step.
-    pub fn load(head: Head, mut src: BytesMut) -> Result<(Self, BytesMut), Error> {
+    pub(crate) fn load(head: Head, mut src: BytesMut) -> Result<(Self, BytesMut), Error> {
         let flags = HeadersFlag(head.flag());
         let mut pad = 0;
 
@@ -203,36 +247,77 @@ impl Headers {
                 pseudo: Pseudo::default(),
             },
             flags: flags,
+            padding: Padding::received(flags.is_padded(), pad),
         };
 
         Ok((headers, src))
     }
 
-    pub fn load_hpack(&mut self, src: &mut BytesMut, max_header_list_size: usize, decoder: &mut hpack::Decoder) -> Result<(), Error> {
-        self.header_block.load(src, max_header_list_size, decoder)
+    pub(crate) fn load_hpack(
+        &mut self,
+        src: &mut BytesMut,
+        max_header_list_size: usize,
+        validation: HeaderValidation,
+        decoder: &mut hpack::Decoder,
+    ) -> Result<(), Error> {
+        self.header_block.load(src, max_header_list_size, validation, decoder)
     }
 
+    /// Returns the stream identifier that this frame is associated with.
     pub fn stream_id(&self) -> StreamId {
         self.stream_id
     }
 
+    /// Returns true if the `END_HEADERS` flag is set on this frame.
     pub fn is_end_headers(&self) -> bool {
         self.flags.is_end_headers()
     }
 
+    /// Sets the `END_HEADERS` flag on this frame.
+    ///
+    /// The flag is set on the last frame of the header block when the frame
+    /// is split across CONTINUATION frames.
     pub fn set_end_headers(&mut self) {
         self.flags.set_end_headers();
     }
 
+    /// Returns true if the `END_STREAM` flag is set on this frame.
     pub fn is_end_stream(&self) -> bool {
         self.flags.is_end_stream()
     }
 
+    /// Sets the `END_STREAM` flag on this frame.
     pub fn set_end_stream(&mut self) {
         self.flags.set_end_stream()
     }
 
-    pub fn is_over_size(&self) -> bool {
+    /// Returns the stream dependency, if the `PRIORITY` flag is set on this
+    /// frame.
+    pub fn stream_dependency(&self) -> Option<StreamDependency> {
+        self.stream_dep
+    }
+
+    /// Sets the stream dependency, along with the `PRIORITY` flag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stream depends on itself.
+    pub fn set_stream_dependency(&mut self, stream_dep: Option<StreamDependency>) {
+        match stream_dep {
+            Some(ref dep) => {
+                assert!(
+                    dep.dependency_id() != self.stream_id,
+                    "stream cannot depend on itself"
+                );
+                self.flags.set_priority();
+            },
+            None => self.flags.unset_priority(),
+        }
+
+        self.stream_dep = stream_dep;
+    }
+
+    pub(crate) fn is_over_size(&self) -> bool {
         self.header_block.is_over_size
     }
 
@@ -240,32 +325,72 @@ impl Headers {
         self.header_block.has_too_big_field()
     }
 
+    /// Returns the size of the header list, as defined for
+    /// `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    pub(crate) fn header_list_size(&self) -> usize {
+        self.header_block.calculate_header_list_size()
+    }
+
+    /// Consume `self`, returning the parts of the frame
     pub fn into_parts(self) -> (Pseudo, HeaderMap) {
         (self.header_block.pseudo, self.header_block.fields)
     }
 
-    #[cfg(feature = "unstable")]
+    /// Returns the pseudo header fields.
+    pub fn pseudo(&self) -> &Pseudo {
+        &self.header_block.pseudo
+    }
+
+    /// Returns a mutable reference to the pseudo header fields.
     pub fn pseudo_mut(&mut self) -> &mut Pseudo {
         &mut self.header_block.pseudo
     }
 
+    /// Returns the header fields, pseudo header fields excluded.
     pub fn fields(&self) -> &HeaderMap {
         &self.header_block.fields
     }
 
+    /// Returns a mutable reference to the header fields, pseudo header
+    /// fields excluded.
+    pub fn fields_mut(&mut self) -> &mut HeaderMap {
+        &mut self.header_block.fields
+    }
+
+    /// Consume `self`, returning the header fields.
     pub fn into_fields(self) -> HeaderMap {
         self.header_block.fields
     }
 
-    pub fn encode(self, encoder: &mut hpack::Encoder, dst: &mut BytesMut) -> Option<Continuation> {
+    /// Sets the padding to add when encoding the frame.
+    pub fn set_padding(&mut self, padding: Padding) {
+        if padding.is_none() {
+            self.flags.unset_padded();
+        } else {
+            self.flags.set_padded();
+        }
+
+        self.padding = padding;
+    }
+
+    pub(crate) fn encode(
+        self,
+        encoder: &mut hpack::Encoder,
+        dst: &mut BytesMut,
+        max_frame_size: usize,
+    ) -> Option<Continuation> {
         // At this point, the `is_end_headers` flag should always be set
         debug_assert!(self.flags.is_end_headers());
 
         // Get the HEADERS frame head
         let head = self.head();
+        let stream_dep = self.stream_dep;
 
-        self.header_block.into_encoding()
-            .encode(&head, encoder, dst, |_| {
+        self.header_block.into_encoding(encoder)
+            .encode(&head, dst, max_frame_size, self.padding, |dst| {
+                if let Some(ref dep) = stream_dep {
+                    dep.encode(dst);
+                }
             })
     }
 
@@ -291,6 +416,10 @@ impl fmt::Debug for Headers {
             builder.field("stream_dep", dep);
         }
 
+        if !self.padding.is_none() {
+            builder.field("padding", &self.padding);
+        }
+
         // `fields` and `pseudo` purposefully not included
         builder.finish()
     }
@@ -302,7 +431,7 @@ impl PushPromise {
     /// Loads the push promise frame but doesn't actually do HPACK decoding.
     ///
     /// HPACK decoding is done in the `load_hpack` step.
-    pub fn load(head: Head, mut src: BytesMut) -> Result<(Self, BytesMut), Error> {
+    pub(crate) fn load(head: Head, mut src: BytesMut) -> Result<(Self, BytesMut), Error> {
         let flags = PushPromiseFlag(head.flag());
         let mut pad = 0;
 
@@ -345,35 +474,62 @@ impl PushPromise {
             },
             promised_id: promised_id,
             stream_id: head.stream_id(),
+            padding: Padding::received(flags.is_padded(), pad),
         };
         Ok((frame, src))
     }
 
-    pub fn load_hpack(&mut self, src: &mut BytesMut, max_header_list_size: usize, decoder: &mut hpack::Decoder) -> Result<(), Error> {
-        self.header_block.load(src, max_header_list_size, decoder)
+    pub(crate) fn load_hpack(
+        &mut self,
+        src: &mut BytesMut,
+        max_header_list_size: usize,
+        validation: HeaderValidation,
+        decoder: &mut hpack::Decoder,
+    ) -> Result<(), Error> {
+        self.header_block.load(src, max_header_list_size, validation, decoder)
     }
 
+    /// Returns the stream identifier that this frame is associated with.
     pub fn stream_id(&self) -> StreamId {
         self.stream_id
     }
 
+    /// Returns the identifier of the stream reserved by this frame.
     pub fn promised_id(&self) -> StreamId {
         self.promised_id
     }
 
+    /// Returns true if the `END_HEADERS` flag is set on this frame.
     pub fn is_end_headers(&self) -> bool {
         self.flags.is_end_headers()
     }
 
+    /// Sets the `END_HEADERS` flag on this frame.
     pub fn set_end_headers(&mut self) {
         self.flags.set_end_headers();
     }
 
-    pub fn is_over_size(&self) -> bool {
+    pub(crate) fn is_over_size(&self) -> bool {
         self.header_block.is_over_size
     }
 
-    pub fn encode(self, encoder: &mut hpack::Encoder, dst: &mut BytesMut) -> Option<Continuation> {
+    /// Sets the padding to add when encoding the frame.
+    pub fn set_padding(&mut self, padding: Padding) {
+        if padding.is_none() {
+            self.flags.unset_padded();
+        } else {
+            self.flags.set_padded();
+        }
+
+        self.padding = padding;
+    }
+
+    pub(crate) fn encode(
+        self,
+        encoder: &mut hpack::Encoder,
+        dst: &mut BytesMut,
+        max_frame_size: usize,
+    ) -> Option<Continuation> {
         use bytes::BufMut;
 
         // At this point, the `is_end_headers` flag should always be set
@@ -382,8 +538,8 @@ impl PushPromise {
         let head = self.head();
         let promised_id = self.promised_id;
 
-        self.header_block.into_encoding()
-            .encode(&head, encoder, dst, |dst| {
+        self.header_block.into_encoding(encoder)
+            .encode(&head, dst, max_frame_size, self.padding, |dst| {
                 dst.put_u32_be(promised_id.into());
             })
     }
@@ -398,10 +554,20 @@ impl PushPromise {
     pub fn into_parts(self) -> (Pseudo, HeaderMap) {
         (self.header_block.pseudo, self.header_block.fields)
     }
-}
 
-#[cfg(feature = "unstable")]
-impl PushPromise {
+    /// Returns the pseudo header fields of the promised request.
+    pub fn pseudo(&self) -> &Pseudo {
+        &self.header_block.pseudo
+    }
+
+    /// Returns the header fields of the promised request, pseudo header
+    /// fields excluded.
+    pub fn fields(&self) -> &HeaderMap {
+        &self.header_block.fields
+    }
+
+    /// Create a new PUSH_PROMISE frame, reserving `promised_id` for the
+    /// response to the given request.
     pub fn new(
         stream_id: StreamId,
         promised_id: StreamId,
@@ -417,13 +583,19 @@ impl PushPromise {
             },
             promised_id,
             stream_id,
+            padding: Padding::None,
         }
     }
 
-    pub fn fields(&self) -> &HeaderMap {
-        &self.header_block.fields
+    /// Create a new PUSH_PROMISE frame for the head of `request`.
+    pub fn request(stream_id: StreamId, promised_id: StreamId, request: Request<()>) -> Self {
+        let (parts, _) = request.into_parts();
+        let pseudo = Pseudo::request(parts.method, parts.uri);
+
+        PushPromise::new(stream_id, promised_id, pseudo, parts.headers)
     }
 
+    /// Consume `self`, returning the header fields.
     pub fn into_fields(self) -> HeaderMap {
         self.header_block.fields
     }
@@ -453,12 +625,13 @@ impl Continuation {
         Head::new(Kind::Continuation, END_HEADERS, self.stream_id)
     }
 
-    pub fn encode(self, encoder: &mut hpack::Encoder, dst: &mut BytesMut) -> Option<Continuation> {
+    pub(crate) fn encode(self, dst: &mut BytesMut, max_frame_size: usize) -> Option<Continuation> {
         // Get the CONTINUATION frame head
         let head = self.head();
 
+        // CONTINUATION frames cannot be padded
         self.header_block
-            .encode(&head, encoder, dst, |_| {
+            .encode(&head, dst, max_frame_size, Padding::None, |_| {
             })
     }
 }
@@ -466,6 +639,7 @@ impl Continuation {
 // ===== impl Pseudo =====
 
 impl Pseudo {
+    /// Returns the pseudo header fields of a request.
     pub fn request(method: Method, uri: Uri) -> Self {
         let parts = uri::Parts::from(uri);
 
@@ -502,6 +676,7 @@ impl Pseudo {
         pseudo
     }
 
+    /// Returns the pseudo header fields of a response.
     pub fn response(status: StatusCode) -> Self {
         Pseudo {
             method: None,
@@ -512,10 +687,12 @@ impl Pseudo {
         }
     }
 
+    /// Sets the `:scheme` pseudo header field.
     pub fn set_scheme(&mut self, scheme: uri::Scheme) {
         self.scheme = Some(to_string(scheme.into()));
     }
 
+    /// Sets the `:authority` pseudo header field.
     pub fn set_authority(&mut self, authority: String<Bytes>) {
         self.authority = Some(authority);
     }
@@ -530,14 +707,21 @@ fn to_string(src: Bytes) -> String<Bytes> {
 impl EncodingHeaderBlock {
     fn encode<F>(mut self,
                  head: &Head,
-                 encoder: &mut hpack::Encoder,
                  dst: &mut BytesMut,
+                 max_frame_size: usize,
+                 padding: Padding,
                  f: F)
         -> Option<Continuation>
     where F: FnOnce(&mut BytesMut),
     {
+        use bytes::BufMut;
+
         let head_pos = dst.len();
 
+        // Room for the frame head, the pad length and a stream dependency or
+        // a promised stream ID.
+        dst.reserve(frame::HEADER_LEN + 6);
+
         // At this point, we don't know how big the h2 frame will be.
         // So, we write the head with length 0, then write the body, and
         // finally write the length once we know the size.
@@ -545,20 +729,41 @@ impl EncodingHeaderBlock {
 
         let payload_pos = dst.len();
 
+        // Same for the pad length, which depends on the payload size.
+        if !padding.is_none() {
+            dst.put_u8(0);
+        }
+
         f(dst);
 
-        // Now, encode the header payload
-        let continuation = match encoder.encode(self.hpack, &mut self.headers, dst) {
-            hpack::Encode::Full => None,
-            hpack::Encode::Partial(state) => Some(Continuation {
+        // Write as much of the header block as fits in the frame. The rest is
+        // sent in CONTINUATION frames.
+        let room = max_frame_size - (dst.len() - payload_pos);
+        let len = cmp::min(self.hpack.len(), room);
+
+        dst.extend_from_slice(&self.hpack.split_to(len));
+
+        let continuation = if self.hpack.is_empty() {
+            None
+        } else {
+            Some(Continuation {
                 stream_id: head.stream_id(),
-                header_block: EncodingHeaderBlock {
-                    hpack: Some(state),
-                    headers: self.headers,
-                },
-            }),
+                header_block: self,
+            })
         };
 
+        if !padding.is_none() {
+            // The padding takes whatever room is left in the frame. The pad
+            // length field has already been written.
+            let len = dst.len() - payload_pos - 1;
+            let room = max_frame_size - (len + 1);
+            let pad_len = padding.pad_len(len, room + 1).unwrap_or(0);
+
+            dst[payload_pos] = pad_len;
+            dst.reserve(pad_len as usize);
+            util::put_padding(dst, pad_len);
+        }
+
         // Compute the header block length
         let payload_len = (dst.len() - payload_pos) as u64;
 
@@ -591,15 +796,15 @@ impl Iterator for Iter {
             }
 
             if let Some(scheme) = pseudo.scheme.take() {
-                return Some(Scheme(scheme));
+                return Some(Scheme(hpack::BytesStr::from_string(scheme)));
             }
 
             if let Some(authority) = pseudo.authority.take() {
-                return Some(Authority(authority));
+                return Some(Authority(hpack::BytesStr::from_string(authority)));
             }
 
             if let Some(path) = pseudo.path.take() {
-                return Some(Path(path));
+                return Some(Path(hpack::BytesStr::from_string(path)));
             }
 
             if let Some(status) = pseudo.status.take() {
@@ -621,37 +826,45 @@ impl Iterator for Iter {
 // ===== impl HeadersFlag =====
 
 impl HeadersFlag {
-    pub fn empty() -> HeadersFlag {
-        HeadersFlag(0)
-    }
-
-    pub fn load(bits: u8) -> HeadersFlag {
-        HeadersFlag(bits & ALL)
-    }
-
-    pub fn is_end_stream(&self) -> bool {
+    pub(crate) fn is_end_stream(&self) -> bool {
         self.0 & END_STREAM == END_STREAM
     }
 
-    pub fn set_end_stream(&mut self) {
+    pub(crate) fn set_end_stream(&mut self) {
         self.0 |= END_STREAM;
     }
 
-    pub fn is_end_headers(&self) -> bool {
+    pub(crate) fn is_end_headers(&self) -> bool {
         self.0 & END_HEADERS == END_HEADERS
     }
 
-    pub fn set_end_headers(&mut self) {
+    pub(crate) fn set_end_headers(&mut self) {
         self.0 |= END_HEADERS;
     }
 
-    pub fn is_padded(&self) -> bool {
+    pub(crate) fn is_padded(&self) -> bool {
         self.0 & PADDED == PADDED
     }
 
-    pub fn is_priority(&self) -> bool {
+    pub(crate) fn set_padded(&mut self) {
+        self.0 |= PADDED;
+    }
+
+    pub(crate) fn unset_padded(&mut self) {
+        self.0 &= !PADDED;
+    }
+
+    pub(crate) fn is_priority(&self) -> bool {
         self.0 & PRIORITY == PRIORITY
     }
+
+    pub(crate) fn set_priority(&mut self) {
+        self.0 |= PRIORITY;
+    }
+
+    pub(crate) fn unset_priority(&mut self) {
+        self.0 &= !PRIORITY;
+    }
 }
 
 impl Default for HeadersFlag {
@@ -681,24 +894,24 @@ impl fmt::Debug for HeadersFlag {
 // ===== impl PushPromiseFlag =====
 
 impl PushPromiseFlag {
-    pub fn empty() -> PushPromiseFlag {
-        PushPromiseFlag(0)
+    pub(crate) fn is_end_headers(&self) -> bool {
+        self.0 & END_HEADERS == END_HEADERS
     }
 
-    pub fn load(bits: u8) -> PushPromiseFlag {
-        PushPromiseFlag(bits & ALL)
+    pub(crate) fn set_end_headers(&mut self) {
+        self.0 |= END_HEADERS;
     }
 
-    pub fn is_end_headers(&self) -> bool {
-        self.0 & END_HEADERS == END_HEADERS
+    pub(crate) fn is_padded(&self) -> bool {
+        self.0 & PADDED == PADDED
     }
 
-    pub fn set_end_headers(&mut self) {
-        self.0 |= END_HEADERS;
+    pub(crate) fn set_padded(&mut self) {
+        self.0 |= PADDED;
     }
 
-    pub fn is_padded(&self) -> bool {
-        self.0 & PADDED == PADDED
+    pub(crate) fn unset_padded(&mut self) {
+        self.0 &= !PADDED;
     }
 }
 
@@ -728,14 +941,20 @@ impl fmt::Debug for PushPromiseFlag {
 
 
 impl HeaderBlock {
-    fn load(&mut self, src: &mut BytesMut, max_header_list_size: usize, decoder: &mut hpack::Decoder) -> Result<(), Error> {
+    fn load(
+        &mut self,
+        src: &mut BytesMut,
+        max_header_list_size: usize,
+        validation: HeaderValidation,
+        decoder: &mut hpack::Decoder,
+    ) -> Result<(), Error> {
         let mut reg = !self.fields.is_empty();
         let mut malformed = false;
         let mut headers_size = self.calculate_header_list_size();
 
         macro_rules! set_pseudo {
             ($field:ident, $val:expr) => {{
-                if reg {
+                if reg && !validation.is_lenient() {
                     trace!("load_hpack; header malformed -- pseudo not at head of block");
                     malformed = true;
                 } else if self.pseudo.$field.is_some() {
@@ -769,22 +988,14 @@ impl HeaderBlock {
                     value,
                 } => {
                     // Connection level header fields are not supported and must
-                    // result in a protocol error.
-
-                    if name == header::CONNECTION
-                        || name == header::TRANSFER_ENCODING
-                        || name == header::UPGRADE
-                        || name == "keep-alive"
-                        || name == "proxy-connection"
-                    {
-                        trace!("load_hpack; connection level header");
-                        malformed = true;
-                    } else if name == header::TE && value != "trailers" {
-                        trace!("load_hpack; TE header not set to trailers; val={:?}", value);
-                        malformed = true;
-                    } else {
-                        reg = true;
+                    // result in a protocol error, unless they are stripped.
+                    // Either way, pseudo headers may not follow them.
+                    reg = true;
 
+                    if is_connection_specific(&name, &value) {
+                        trace!("load_hpack; connection level header; name={:?}; val={:?}", name, value);
+                        malformed |= !validation.is_lenient();
+                    } else {
                         headers_size += decoded_header_size(name.as_str().len(), value.len());
                         if headers_size < max_header_list_size {
                             self.fields.append(name, value);
@@ -794,10 +1005,10 @@ impl HeaderBlock {
                         }
                     }
                 },
-                Authority(v) => set_pseudo!(authority, v),
+                Authority(v) => set_pseudo!(authority, v.into_string()),
                 Method(v) => set_pseudo!(method, v),
-                Scheme(v) => set_pseudo!(scheme, v),
-                Path(v) => set_pseudo!(path, v),
+                Scheme(v) => set_pseudo!(scheme, v.into_string()),
+                Path(v) => set_pseudo!(path, v.into_string()),
                 Status(v) => set_pseudo!(status, v),
             }
         });
@@ -807,6 +1018,11 @@ impl HeaderBlock {
             return Err(e.into());
         }
 
+        if decoder.has_uppercase_names() && !validation.is_lenient() {
+            trace!("load_hpack; header malformed -- uppercase name");
+            malformed = true;
+        }
+
         if malformed {
             trace!("malformed message");
             return Err(Error::MalformedMessage.into());
@@ -815,13 +1031,32 @@ impl HeaderBlock {
         Ok(())
     }
 
-    fn into_encoding(self) -> EncodingHeaderBlock {
+    /// Encodes the whole header block, so that it can be split across as
+    /// many frames as needed, no matter the size of the individual fields.
+    fn into_encoding(self, encoder: &mut hpack::Encoder) -> EncodingHeaderBlock {
+        // The uncompressed size is usually enough. Leave room for dynamic table
+        // size updates, which can't be resumed.
+        let mut dst = BytesMut::with_capacity(self.calculate_header_list_size() + 16);
+        let mut headers = Iter {
+            pseudo: Some(self.pseudo),
+            fields: self.fields.into_iter(),
+        };
+        let mut state = None;
+
+        loop {
+            match encoder.encode(state.take(), &mut headers, &mut dst) {
+                hpack::Encode::Full => break,
+                hpack::Encode::Partial(s) => {
+                    // The next field did not fit, grow the buffer and resume.
+                    let additional = cmp::max(dst.capacity(), 1024);
+                    dst.reserve(additional);
+                    state = Some(s);
+                },
+            }
+        }
+
         EncodingHeaderBlock {
-            hpack: None,
-            headers: Iter {
-                pseudo: Some(self.pseudo),
-                fields: self.fields.into_iter(),
-            },
+            hpack: dst.freeze(),
         }
     }
 
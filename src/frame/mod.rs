@@ -1,3 +1,35 @@
+//! HTTP/2.0 frames.
+//!
+//! This module provides a type for each of the frames defined in [Section 6]
+//! of the HTTP/2.0 spec, and `Frame`, which is any one of them. Frames are
+//! read from and written to an I/O handle with [`Codec`].
+//!
+//! Frames are only checked to be valid on their own. Whether a frame is
+//! acceptable given the state of the connection is up to the user of the
+//! codec.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate h2;
+//! # extern crate http;
+//! use h2::frame::{Frame, Headers, StreamDependency};
+//! use http::Request;
+//!
+//! # pub fn main() {
+//! let request = Request::get("https://example.com/").body(()).unwrap();
+//!
+//! let mut headers = Headers::request(1.into(), request);
+//! headers.set_stream_dependency(Some(StreamDependency::new(0.into(), 15, false)));
+//! headers.set_end_stream();
+//!
+//! let frame: Frame = headers.into();
+//! # }
+//! ```
+//!
+//! [Section 6]: http://httpwg.org/specs/rfc7540.html#FrameTypes
+//! [`Codec`]: ../struct.Codec.html
+
 use hpack;
 
 use bytes::Bytes;
@@ -27,9 +59,11 @@ macro_rules! unpack_octets_4 {
 }
 
 mod data;
+mod extension;
 mod go_away;
 mod head;
 mod headers;
+mod padding;
 mod ping;
 mod priority;
 mod reason;
@@ -37,18 +71,25 @@ mod reset;
 mod settings;
 mod stream_id;
 mod util;
+mod validation;
 mod window_update;
 
 pub use self::data::Data;
+pub use self::extension::Extension;
 pub use self::go_away::GoAway;
-pub use self::head::{Head, Kind};
-pub use self::headers::{Continuation, Headers, Pseudo, PushPromise};
-pub use self::ping::Ping;
+pub(crate) use self::head::{Head, Kind};
+pub(crate) use self::headers::Continuation;
+pub use self::headers::{Headers, Pseudo, PushPromise};
+pub use self::padding::Padding;
+pub use self::ping::{Payload, Ping};
 pub use self::priority::{Priority, StreamDependency};
 pub use self::reason::Reason;
 pub use self::reset::Reset;
-pub use self::settings::Settings;
-pub use self::stream_id::{StreamId, StreamIdOverflow};
+pub use self::settings::{Settings, UnknownSettings};
+pub use self::stream_id::StreamId;
+pub(crate) use self::stream_id::StreamIdOverflow;
+pub use self::validation::HeaderValidation;
+pub(crate) use self::validation::{find_connection_specific, host_matches_authority, strip_connection_specific};
 pub use self::window_update::WindowUpdate;
 
 // Re-export some constants
@@ -59,26 +100,46 @@ pub use self::settings::{
     DEFAULT_SETTINGS_HEADER_TABLE_SIZE,
     MAX_INITIAL_WINDOW_SIZE,
     MAX_MAX_FRAME_SIZE,
+    MAX_UNKNOWN_SETTINGS,
 };
 
+/// A frame size, as advertised with `SETTINGS_MAX_FRAME_SIZE`.
 pub type FrameSize = u32;
 
+/// The length of a frame header.
 pub const HEADER_LEN: usize = 9;
 
+/// An HTTP/2.0 frame.
+///
+/// `T` is the type of the payload of DATA frames.
 #[derive(Eq, PartialEq)]
 pub enum Frame<T = Bytes> {
+    /// A DATA frame.
     Data(Data<T>),
+    /// A HEADERS frame, along with any CONTINUATION frames that follow it.
     Headers(Headers),
+    /// A PRIORITY frame.
     Priority(Priority),
+    /// A PUSH_PROMISE frame, along with any CONTINUATION frames that follow
+    /// it.
     PushPromise(PushPromise),
+    /// A SETTINGS frame.
     Settings(Settings),
+    /// A PING frame.
     Ping(Ping),
+    /// A GOAWAY frame.
     GoAway(GoAway),
+    /// A WINDOW_UPDATE frame.
     WindowUpdate(WindowUpdate),
+    /// A RST_STREAM frame.
     Reset(Reset),
+    /// A frame of a type that is not defined by the HTTP/2.0 spec.
+    Extension(Extension),
 }
 
 impl<T> Frame<T> {
+    /// Maps the payload of a DATA frame with `f`. Other frames are returned
+    /// as is.
     pub fn map<F, U>(self, f: F) -> Frame<U>
     where
         F: FnOnce(T) -> U,
@@ -95,6 +156,7 @@ impl<T> Frame<T> {
             GoAway(frame) => frame.into(),
             WindowUpdate(frame) => frame.into(),
             Reset(frame) => frame.into(),
+            Extension(frame) => frame.into(),
         }
     }
 }
@@ -113,13 +175,14 @@ impl<T> fmt::Debug for Frame<T> {
             GoAway(ref frame) => fmt::Debug::fmt(frame, fmt),
             WindowUpdate(ref frame) => fmt::Debug::fmt(frame, fmt),
             Reset(ref frame) => fmt::Debug::fmt(frame, fmt),
+            Extension(ref frame) => fmt::Debug::fmt(frame, fmt),
         }
     }
 }
 
 /// Errors that can occur during parsing an HTTP/2 frame.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Error {
+pub(crate) enum Error {
     /// A length value other than 8 was set on a PING message.
     BadFrameSize,
 
@@ -0,0 +1,92 @@
+use std::cmp;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// Padding added to outgoing DATA, HEADERS and PUSH_PROMISE frames.
+///
+/// Padding obscures the size of messages, as a defense against traffic
+/// analysis. A padded frame carries a one byte pad length field, followed by
+/// up to 255 bytes of padding after the frame's content.
+///
+/// Padding on DATA frames counts against flow control, just like the data
+/// itself. It only uses window capacity that is not already needed to send
+/// data, so DATA frames may be padded less than the policy requests, or not
+/// at all, when the peer's window is short.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Padding {
+    /// Frames are not padded. This is the default.
+    None,
+
+    /// Each frame is padded with the given number of bytes.
+    Fixed(u8),
+
+    /// Each frame is padded so that its payload length, including the pad
+    /// length field, is a multiple of the given value.
+    ///
+    /// A value of zero disables padding.
+    RoundUp(u8),
+
+    /// Each frame is padded with a random number of bytes.
+    Random {
+        /// The minimum number of bytes of padding.
+        min: u8,
+        /// The maximum number of bytes of padding, inclusive.
+        max: u8,
+    },
+}
+
+impl Padding {
+    /// Returns the padding of a received frame, so that the frame is padded
+    /// the same way if it is encoded again.
+    pub(crate) fn received(is_padded: bool, pad_len: usize) -> Padding {
+        if is_padded {
+            Padding::Fixed(pad_len as u8)
+        } else {
+            Padding::None
+        }
+    }
+
+    pub(crate) fn is_none(&self) -> bool {
+        *self == Padding::None || *self == Padding::RoundUp(0)
+    }
+
+    /// Returns the number of bytes of padding for a frame with a payload of
+    /// `len` bytes, or `None` if the frame should not be padded.
+    ///
+    /// `max` is the largest number of bytes that can be added to the frame,
+    /// pad length field included.
+    pub(crate) fn pad_len(&self, len: usize, max: usize) -> Option<u8> {
+        if self.is_none() || max == 0 {
+            return None;
+        }
+
+        let pad_len = match *self {
+            Padding::None => unreachable!(),
+            Padding::Fixed(n) => n as usize,
+            Padding::RoundUp(n) => {
+                let n = n as usize;
+                (n - (len + 1) % n) % n
+            },
+            Padding::Random { min, max } => {
+                let (min, max) = (cmp::min(min, max) as u64, cmp::max(min, max) as u64);
+                (min + random() % (max - min + 1)) as usize
+            },
+        };
+
+        Some(cmp::min(pad_len, max - 1) as u8)
+    }
+}
+
+impl Default for Padding {
+    fn default() -> Self {
+        Padding::None
+    }
+}
+
+/// Returns a random number.
+///
+/// Every `RandomState` is created with different random keys, so hashing
+/// nothing with a new one is enough, without keeping a generator around.
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
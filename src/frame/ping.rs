@@ -3,8 +3,14 @@ use frame::{Error, Frame, Head, Kind, StreamId};
 
 const ACK_FLAG: u8 = 0x1;
 
+/// The opaque data of a PING frame.
 pub type Payload = [u8; 8];
 
+/// PING frame
+///
+/// Measures the round trip time to the peer, or checks that the connection
+/// is still alive. The peer answers a PING with a PING ack carrying the same
+/// payload.
 #[derive(Debug, Eq, PartialEq)]
 pub struct Ping {
     ack: bool,
@@ -17,19 +23,13 @@ const SHUTDOWN_PAYLOAD: Payload = [0x0b, 0x7b, 0xa2, 0xf0, 0x8b, 0x9b, 0xfe, 0x5
 const USER_PAYLOAD: Payload = [0x3b, 0x7c, 0xdb, 0x7a, 0x0b, 0x87, 0x16, 0xb4];
 
 impl Ping {
-
-    #[cfg(feature = "unstable")]
+    /// The payload of the PING sent by a server shutting down gracefully.
     pub const SHUTDOWN: Payload = SHUTDOWN_PAYLOAD;
 
-    #[cfg(not(feature = "unstable"))]
-    pub(crate) const SHUTDOWN: Payload = SHUTDOWN_PAYLOAD;
-
-    #[cfg(feature = "unstable")]
+    /// The payload of the PING sent with `PingPong::send_ping`.
     pub const USER: Payload = USER_PAYLOAD;
 
-    #[cfg(not(feature = "unstable"))]
-    pub(crate) const USER: Payload = USER_PAYLOAD;
-
+    /// Creates a new PING frame.
     pub fn new(payload: Payload) -> Ping {
         Ping {
             ack: false,
@@ -37,6 +37,7 @@ impl Ping {
         }
     }
 
+    /// Creates a new PING ack frame, answering the PING with `payload`.
     pub fn pong(payload: Payload) -> Ping {
         Ping {
             ack: true,
@@ -44,20 +45,23 @@ impl Ping {
         }
     }
 
+    /// Returns true if this frame is a PING ack.
     pub fn is_ack(&self) -> bool {
         self.ack
     }
 
+    /// Returns the frame payload.
     pub fn payload(&self) -> &Payload {
         &self.payload
     }
 
+    /// Consumes `self` and returns the frame payload.
     pub fn into_payload(self) -> Payload {
         self.payload
     }
 
     /// Builds a `Ping` frame from a raw frame.
-    pub fn load(head: Head, bytes: &[u8]) -> Result<Ping, Error> {
+    pub(crate) fn load(head: Head, bytes: &[u8]) -> Result<Ping, Error> {
         debug_assert_eq!(head.kind(), ::frame::Kind::Ping);
 
         // PING frames are not associated with any individual stream. If a PING
@@ -90,7 +94,7 @@ impl Ping {
         })
     }
 
-    pub fn encode<B: BufMut>(&self, dst: &mut B) {
+    pub(crate) fn encode<B: BufMut>(&self, dst: &mut B) {
         let sz = self.payload.len();
         trace!("encoding PING; ack={} len={}", self.ack, sz);
 
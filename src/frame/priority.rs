@@ -1,12 +1,20 @@
 use frame::*;
 
+use bytes::BufMut;
+
+/// PRIORITY frame
+///
+/// Specifies the sender-advised priority of a stream. It can be sent in any
+/// stream state, including idle or closed streams.
 #[derive(Debug, Eq, PartialEq)]
 pub struct Priority {
     stream_id: StreamId,
     dependency: StreamDependency,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+/// The dependency of a stream on another stream, as carried by a PRIORITY
+/// frame or a HEADERS frame with the PRIORITY flag set.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct StreamDependency {
     /// The ID of the stream dependency target
     dependency_id: StreamId,
@@ -20,8 +28,37 @@ pub struct StreamDependency {
     is_exclusive: bool,
 }
 
+const EXCLUSIVE_MASK: u32 = 1 << 31;
+
 impl Priority {
-    pub fn load(head: Head, payload: &[u8]) -> Result<Self, Error> {
+    /// Creates a new PRIORITY frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stream depends on itself.
+    pub fn new(stream_id: StreamId, dependency: StreamDependency) -> Self {
+        assert!(
+            dependency.dependency_id() != stream_id,
+            "stream cannot depend on itself"
+        );
+
+        Priority {
+            stream_id,
+            dependency,
+        }
+    }
+
+    /// Returns the stream identifier that this frame is associated with.
+    pub fn stream_id(&self) -> StreamId {
+        self.stream_id
+    }
+
+    /// Returns the dependency of the stream.
+    pub fn dependency(&self) -> StreamDependency {
+        self.dependency
+    }
+
+    pub(crate) fn load(head: Head, payload: &[u8]) -> Result<Self, Error> {
         let dependency = StreamDependency::load(payload)?;
 
         if dependency.dependency_id() == head.stream_id() {
@@ -33,6 +70,13 @@ impl Priority {
             dependency: dependency,
         })
     }
+
+    pub(crate) fn encode<B: BufMut>(&self, dst: &mut B) {
+        trace!("encoding PRIORITY; id={:?}", self.stream_id);
+        let head = Head::new(Kind::Priority, 0, self.stream_id);
+        head.encode(5, dst);
+        self.dependency.encode(dst);
+    }
 }
 
 impl<B> From<Priority> for Frame<B> {
@@ -44,6 +88,10 @@ impl<B> From<Priority> for Frame<B> {
 // ===== impl StreamDependency =====
 
 impl StreamDependency {
+    /// Creates a new stream dependency.
+    ///
+    /// `weight` is in the range [0, 255], and is one less than the weight
+    /// defined in section 5.3.2.
     pub fn new(dependency_id: StreamId, weight: u8, is_exclusive: bool) -> Self {
         StreamDependency {
             dependency_id,
@@ -52,7 +100,7 @@ impl StreamDependency {
         }
     }
 
-    pub fn load(src: &[u8]) -> Result<Self, Error> {
+    pub(crate) fn load(src: &[u8]) -> Result<Self, Error> {
         if src.len() != 5 {
             return Err(Error::InvalidPayloadLength);
         }
@@ -66,7 +114,29 @@ impl StreamDependency {
         Ok(StreamDependency::new(dependency_id, weight, is_exclusive))
     }
 
+    /// Returns the ID of the stream that the stream depends on.
     pub fn dependency_id(&self) -> StreamId {
         self.dependency_id
     }
+
+    /// Returns the weight of the stream, in the range [0, 255].
+    pub fn weight(&self) -> u8 {
+        self.weight
+    }
+
+    /// Returns true if the dependency is exclusive.
+    pub fn is_exclusive(&self) -> bool {
+        self.is_exclusive
+    }
+
+    pub(crate) fn encode<B: BufMut>(&self, dst: &mut B) {
+        let mut dependency_id: u32 = self.dependency_id.into();
+
+        if self.is_exclusive {
+            dependency_id |= EXCLUSIVE_MASK;
+        }
+
+        dst.put_u32_be(dependency_id);
+        dst.put_u8(self.weight);
+    }
 }
@@ -2,6 +2,9 @@ use frame::{self, Error, Head, Kind, Reason, StreamId};
 
 use bytes::{BufMut};
 
+/// RST_STREAM frame
+///
+/// Terminates a stream immediately.
 #[derive(Debug, Eq, PartialEq)]
 pub struct Reset {
     stream_id: StreamId,
@@ -9,6 +12,7 @@ pub struct Reset {
 }
 
 impl Reset {
+    /// Creates a new RST_STREAM frame.
     pub fn new(stream_id: StreamId, error: Reason) -> Reset {
         Reset {
             stream_id,
@@ -16,15 +20,17 @@ impl Reset {
         }
     }
 
+    /// Returns the stream identifier that this frame is associated with.
     pub fn stream_id(&self) -> StreamId {
         self.stream_id
     }
 
+    /// Returns the reason for resetting the stream.
     pub fn reason(&self) -> Reason {
         self.error_code
     }
 
-    pub fn load(head: Head, payload: &[u8]) -> Result<Reset, Error> {
+    pub(crate) fn load(head: Head, payload: &[u8]) -> Result<Reset, Error> {
         if payload.len() != 4 {
             return Err(Error::InvalidPayloadLength);
         }
@@ -37,7 +43,7 @@ impl Reset {
         })
     }
 
-    pub fn encode<B: BufMut>(&self, dst: &mut B) {
+    pub(crate) fn encode<B: BufMut>(&self, dst: &mut B) {
         trace!(
             "encoding RESET; id={:?} code={:?}",
             self.stream_id,
@@ -1,8 +1,13 @@
-use std::fmt;
+use std::{fmt, slice};
 
 use bytes::{BufMut, BytesMut};
 use frame::{util, Error, Frame, FrameSize, Head, Kind, StreamId};
 
+/// SETTINGS frame
+///
+/// Conveys the configuration parameters of an endpoint, or acknowledges the
+/// receipt of the peer's parameters. Only the parameters that are set are
+/// sent.
 #[derive(Clone, Default, Eq, PartialEq)]
 pub struct Settings {
     flags: SettingsFlags,
@@ -13,6 +18,15 @@ pub struct Settings {
     initial_window_size: Option<u32>,
     max_frame_size: Option<u32>,
     max_header_list_size: Option<u32>,
+    // Settings not defined by RFC 7540, in the order they were set
+    unknown: Vec<(u16, u32)>,
+}
+
+/// An iterator over the settings of a `Settings` frame that are not defined
+/// by RFC 7540, as `(id, value)` pairs in the order they were set.
+#[derive(Debug, Clone)]
+pub struct UnknownSettings<'a> {
+    inner: slice::Iter<'a, (u16, u32)>,
 }
 
 /// An enum that lists all valid settings that can be sent in a SETTINGS
@@ -27,6 +41,7 @@ pub enum Setting {
     InitialWindowSize(u32),
     MaxFrameSize(u32),
     MaxHeaderListSize(u32),
+    Unknown(u16, u32),
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Default)]
@@ -50,9 +65,13 @@ pub const MAX_INITIAL_WINDOW_SIZE: usize = (1 << 31) - 1;
 /// MAX_FRAME_SIZE upper bound
 pub const MAX_MAX_FRAME_SIZE: FrameSize = (1 << 24) - 1;
 
+/// The maximum number of settings not defined by RFC 7540 that are kept.
+pub const MAX_UNKNOWN_SETTINGS: usize = 32;
+
 // ===== impl Settings =====
 
 impl Settings {
+    /// Creates a new SETTINGS frame with the ACK flag set.
     pub fn ack() -> Settings {
         Settings {
             flags: SettingsFlags::ack(),
@@ -60,30 +79,41 @@ impl Settings {
         }
     }
 
+    /// Returns true if the ACK flag is set on this frame.
     pub fn is_ack(&self) -> bool {
         self.flags.is_ack()
     }
 
+    /// Returns the value of `SETTINGS_INITIAL_WINDOW_SIZE`, if set.
     pub fn initial_window_size(&self) -> Option<u32> {
         self.initial_window_size
     }
 
+    /// Sets the value of `SETTINGS_INITIAL_WINDOW_SIZE`.
     pub fn set_initial_window_size(&mut self, size: Option<u32>) {
         self.initial_window_size = size;
     }
 
+    /// Returns the value of `SETTINGS_MAX_CONCURRENT_STREAMS`, if set.
     pub fn max_concurrent_streams(&self) -> Option<u32> {
         self.max_concurrent_streams
     }
 
+    /// Sets the value of `SETTINGS_MAX_CONCURRENT_STREAMS`.
     pub fn set_max_concurrent_streams(&mut self, max: Option<u32>) {
         self.max_concurrent_streams = max;
     }
 
+    /// Returns the value of `SETTINGS_MAX_FRAME_SIZE`, if set.
     pub fn max_frame_size(&self) -> Option<u32> {
         self.max_frame_size
     }
 
+    /// Sets the value of `SETTINGS_MAX_FRAME_SIZE`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is not within 16,384 and 16,777,215.
     pub fn set_max_frame_size(&mut self, size: Option<u32>) {
         if let Some(val) = size {
             assert!(DEFAULT_MAX_FRAME_SIZE <= val && val <= MAX_MAX_FRAME_SIZE);
@@ -91,23 +121,101 @@ impl Settings {
         self.max_frame_size = size;
     }
 
+    /// Returns the value of `SETTINGS_MAX_HEADER_LIST_SIZE`, if set.
     pub fn max_header_list_size(&self) -> Option<u32> {
         self.max_header_list_size
     }
 
+    /// Sets the value of `SETTINGS_MAX_HEADER_LIST_SIZE`.
     pub fn set_max_header_list_size(&mut self, size: Option<u32>) {
         self.max_header_list_size = size;
     }
 
+    /// Returns the value of `SETTINGS_HEADER_TABLE_SIZE`, if set.
+    pub fn header_table_size(&self) -> Option<u32> {
+        self.header_table_size
+    }
+
+    /// Sets the value of `SETTINGS_HEADER_TABLE_SIZE`.
+    pub fn set_header_table_size(&mut self, size: Option<u32>) {
+        self.header_table_size = size;
+    }
+
+    /// Returns the value of `SETTINGS_ENABLE_PUSH`, which defaults to true.
     pub fn is_push_enabled(&self) -> bool {
         self.enable_push.unwrap_or(1) != 0
     }
 
+    /// Sets the value of `SETTINGS_ENABLE_PUSH`.
     pub fn set_enable_push(&mut self, enable: bool) {
         self.enable_push = Some(enable as u32);
     }
 
-    pub fn load(head: Head, payload: &[u8]) -> Result<Settings, Error> {
+    /// Returns the settings not defined by RFC 7540, as `(id, value)` pairs
+    /// in the order they were set, or received.
+    pub fn unknown_settings<'a>(&'a self) -> UnknownSettings<'a> {
+        UnknownSettings {
+            inner: self.unknown.iter(),
+        }
+    }
+
+    /// Returns the value of the setting `id`, if it is not defined by RFC
+    /// 7540 and is set.
+    pub fn unknown_setting(&self, id: u16) -> Option<u32> {
+        self.unknown
+            .iter()
+            .find(|&&(i, _)| i == id)
+            .map(|&(_, val)| val)
+    }
+
+    /// Sets a setting not defined by RFC 7540.
+    ///
+    /// If the setting is already set, its value is replaced in place, keeping
+    /// its position. At most
+    /// `MAX_UNKNOWN_SETTINGS` settings are kept, so that a peer cannot make
+    /// the set grow without bounds.
+    ///
+    /// Returns `false` if the setting was ignored, either because `id`
+    /// identifies a setting defined by RFC 7540, or because there are too
+    /// many unknown settings already.
+    pub fn set_unknown_setting(&mut self, id: u16, val: u32) -> bool {
+        if !Setting::is_unknown(id) {
+            debug!("ignoring known setting as unknown; id={}; val={}", id, val);
+            return false;
+        }
+
+        if let Some(setting) = self.unknown.iter_mut().find(|setting| setting.0 == id) {
+            setting.1 = val;
+            return true;
+        }
+
+        if self.unknown.len() >= MAX_UNKNOWN_SETTINGS {
+            debug!("ignoring unknown setting; id={}; val={}", id, val);
+            return false;
+        }
+
+        self.unknown.push((id, val));
+        true
+    }
+
+    /// Update `self` with every value set in `other`.
+    ///
+    /// A SETTINGS frame only carries the values that changed, so this is used
+    /// to keep track of all the values the peer has advertised.
+    pub(crate) fn merge(&mut self, other: &Settings) {
+        self.header_table_size = other.header_table_size.or(self.header_table_size);
+        self.enable_push = other.enable_push.or(self.enable_push);
+        self.max_concurrent_streams = other.max_concurrent_streams.or(self.max_concurrent_streams);
+        self.initial_window_size = other.initial_window_size.or(self.initial_window_size);
+        self.max_frame_size = other.max_frame_size.or(self.max_frame_size);
+        self.max_header_list_size = other.max_header_list_size.or(self.max_header_list_size);
+
+        for &(id, val) in &other.unknown {
+            self.set_unknown_setting(id, val);
+        }
+    }
+
+    pub(crate) fn load(head: Head, payload: &[u8]) -> Result<Settings, Error> {
         use self::Setting::*;
 
         debug_assert_eq!(head.kind(), ::frame::Kind::Settings);
@@ -140,10 +248,10 @@ impl Settings {
 
         for raw in payload.chunks(6) {
             match Setting::load(raw) {
-                Some(HeaderTableSize(val)) => {
+                HeaderTableSize(val) => {
                     settings.header_table_size = Some(val);
                 },
-                Some(EnablePush(val)) => match val {
+                EnablePush(val) => match val {
                     0 | 1 => {
                         settings.enable_push = Some(val);
                     },
@@ -151,38 +259,40 @@ impl Settings {
                         return Err(Error::InvalidSettingValue);
                     },
                 },
-                Some(MaxConcurrentStreams(val)) => {
+                MaxConcurrentStreams(val) => {
                     settings.max_concurrent_streams = Some(val);
                 },
-                Some(InitialWindowSize(val)) => if val as usize > MAX_INITIAL_WINDOW_SIZE {
+                InitialWindowSize(val) => if val as usize > MAX_INITIAL_WINDOW_SIZE {
                     return Err(Error::InvalidSettingValue);
                 } else {
                     settings.initial_window_size = Some(val);
                 },
-                Some(MaxFrameSize(val)) => {
+                MaxFrameSize(val) => {
                     if val < DEFAULT_MAX_FRAME_SIZE || val > MAX_MAX_FRAME_SIZE {
                         return Err(Error::InvalidSettingValue);
                     } else {
                         settings.max_frame_size = Some(val);
                     }
                 },
-                Some(MaxHeaderListSize(val)) => {
+                MaxHeaderListSize(val) => {
                     settings.max_header_list_size = Some(val);
                 },
-                None => {},
+                Unknown(id, val) => {
+                    settings.set_unknown_setting(id, val);
+                },
             }
         }
 
         Ok(settings)
     }
 
-    fn payload_len(&self) -> usize {
+    pub(crate) fn payload_len(&self) -> usize {
         let mut len = 0;
         self.for_each(|_| len += 6);
         len
     }
 
-    pub fn encode(&self, dst: &mut BytesMut) {
+    pub(crate) fn encode(&self, dst: &mut BytesMut) {
         // Create & encode an appropriate frame head
         let head = Head::new(Kind::Settings, self.flags.into(), StreamId::zero());
         let payload_len = self.payload_len();
@@ -224,6 +334,10 @@ impl Settings {
         if let Some(v) = self.max_header_list_size {
             f(MaxHeaderListSize(v));
         }
+
+        for &(id, v) in &self.unknown {
+            f(Unknown(id, v));
+        }
     }
 }
 
@@ -257,32 +371,55 @@ impl fmt::Debug for Settings {
             Setting::MaxHeaderListSize(v) => {
                 builder.field("max_header_list_size", &v);
             }
+            Setting::Unknown(id, v) => {
+                builder.field("unknown", &(id, v));
+            }
         });
 
         builder.finish()
     }
 }
 
+// ===== impl UnknownSettings =====
+
+impl<'a> Iterator for UnknownSettings<'a> {
+    type Item = (u16, u32);
+
+    fn next(&mut self) -> Option<(u16, u32)> {
+        self.inner.next().cloned()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
 // ===== impl Setting =====
 
 impl Setting {
     /// Creates a new `Setting` with the correct variant corresponding to the
     /// given setting id, based on the settings IDs defined in section
-    /// 6.5.2.
-    pub fn from_id(id: u16, val: u32) -> Option<Setting> {
+    /// 6.5.2. Other IDs result in an `Unknown` setting.
+    pub fn from_id(id: u16, val: u32) -> Setting {
         use self::Setting::*;
 
         match id {
-            1 => Some(HeaderTableSize(val)),
-            2 => Some(EnablePush(val)),
-            3 => Some(MaxConcurrentStreams(val)),
-            4 => Some(InitialWindowSize(val)),
-            5 => Some(MaxFrameSize(val)),
-            6 => Some(MaxHeaderListSize(val)),
-            _ => None,
+            1 => HeaderTableSize(val),
+            2 => EnablePush(val),
+            3 => MaxConcurrentStreams(val),
+            4 => InitialWindowSize(val),
+            5 => MaxFrameSize(val),
+            6 => MaxHeaderListSize(val),
+            _ => Unknown(id, val),
         }
     }
 
+    /// Returns true if `id` is not one of the settings IDs defined in
+    /// section 6.5.2.
+    pub(crate) fn is_unknown(id: u16) -> bool {
+        id == 0 || id > 6
+    }
+
     /// Creates a new `Setting` by parsing the given buffer of 6 bytes, which
     /// contains the raw byte representation of the setting, according to the
     /// "SETTINGS format" defined in section 6.5.1.
@@ -293,7 +430,7 @@ impl Setting {
     /// # Panics
     ///
     /// If given a buffer shorter than 6 bytes, the function will panic.
-    fn load(raw: &[u8]) -> Option<Setting> {
+    fn load(raw: &[u8]) -> Setting {
         let id: u16 = ((raw[0] as u16) << 8) | (raw[1] as u16);
         let val: u32 = unpack_octets_4!(raw, 2, u32);
 
@@ -310,6 +447,7 @@ impl Setting {
             InitialWindowSize(v) => (4, v),
             MaxFrameSize(v) => (5, v),
             MaxHeaderListSize(v) => (6, v),
+            Unknown(id, v) => (id, v),
         };
 
         dst.put_u16_be(kind);
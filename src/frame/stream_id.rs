@@ -14,6 +14,7 @@ use std::u32;
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct StreamId(u32);
 
+/// Returned by `StreamId::next_id` when the stream ID space is exhausted.
 #[derive(Debug, Copy, Clone)]
 pub struct StreamIdOverflow;
 
@@ -28,7 +29,7 @@ impl StreamId {
 
     /// Parse the stream ID
     #[inline]
-    pub fn parse(buf: &[u8]) -> (StreamId, bool) {
+    pub(crate) fn parse(buf: &[u8]) -> (StreamId, bool) {
         let unpacked = BigEndian::read_u32(buf);
         let flag = unpacked & STREAM_ID_MASK == STREAM_ID_MASK;
 
@@ -65,7 +66,7 @@ impl StreamId {
     /// Returns the next stream ID initiated by the same peer as this stream
     /// ID, or an error if incrementing this stream ID would overflow the
     /// maximum.
-    pub fn next_id(&self) -> Result<StreamId, StreamIdOverflow> {
+    pub(crate) fn next_id(&self) -> Result<StreamId, StreamIdOverflow> {
         let next = self.0 + 2;
         if next > StreamId::MAX.0 {
             Err(StreamIdOverflow)
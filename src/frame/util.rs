@@ -1,7 +1,7 @@
 use std::fmt;
 
 use super::Error;
-use bytes::Bytes;
+use bytes::{BufMut, Bytes};
 
 /// Strip padding from the given payload.
 ///
@@ -38,6 +38,13 @@ pub fn strip_padding(payload: &mut Bytes) -> Result<u8, Error> {
     Ok(pad_len as u8)
 }
 
+/// Write `pad_len` bytes of padding.
+pub(super) fn put_padding<B: BufMut>(dst: &mut B, pad_len: u8) {
+    const PADDING: [u8; 255] = [0; 255];
+
+    dst.put_slice(&PADDING[..pad_len as usize]);
+}
+
 pub(super) fn debug_flags<'a, 'f: 'a>(fmt: &'a mut fmt::Formatter<'f>, bits: u8) -> DebugFlags<'a, 'f> {
     let result = write!(fmt, "({:#x}", bits);
     DebugFlags {
@@ -0,0 +1,133 @@
+use http::header::{self, HeaderMap, HeaderName, HeaderValue};
+
+/// How strictly the header fields of messages are validated.
+///
+/// HTTP/2.0 sets rules that HTTP/1.1 doesn't: header names are lowercase,
+/// connection-specific header fields are not allowed, and requests carry
+/// their target in pseudo header fields. Peers that translate HTTP/1.1
+/// messages do not always follow these rules.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum HeaderValidation {
+    /// Messages that break the rules are malformed, and requests must also
+    /// name a single target.
+    ///
+    /// On top of the `Standard` rules, received requests are rejected with a
+    /// `PROTOCOL_ERROR` when they are missing the `:path` pseudo header
+    /// field, or when their `host` header doesn't match their `:authority`.
+    /// A port left out of either one matches the default port of the
+    /// `:scheme`.
+    Strict,
+
+    /// Messages that break the rules are malformed. This is the default.
+    ///
+    /// Received messages are rejected with a `PROTOCOL_ERROR` when they have
+    /// uppercase header names, connection-specific header fields, a `te`
+    /// header other than `trailers`, or pseudo header fields after regular
+    /// ones. Received requests are also rejected when they are missing the
+    /// `:scheme` pseudo header field.
+    ///
+    /// Sending a message with connection-specific header fields is an error.
+    Standard,
+
+    /// Messages that break the rules are fixed where possible.
+    ///
+    /// Received header names are lowercased, and connection-specific header
+    /// fields are stripped from both received and sent messages. Pseudo
+    /// header fields may follow regular ones, and received requests may
+    /// leave out `:scheme` and `:path`. Without a `:scheme`, the `:authority`
+    /// of a request is moved to the `host` header.
+    Lenient,
+}
+
+// ===== impl HeaderValidation =====
+
+impl HeaderValidation {
+    /// Returns true if requests must also name a single target.
+    pub fn is_strict(&self) -> bool {
+        *self == HeaderValidation::Strict
+    }
+
+    /// Returns true if messages that break the rules are fixed where
+    /// possible.
+    pub fn is_lenient(&self) -> bool {
+        *self == HeaderValidation::Lenient
+    }
+}
+
+impl Default for HeaderValidation {
+    fn default() -> Self {
+        HeaderValidation::Standard
+    }
+}
+
+/// Returns true if the header field is connection-specific, and so not
+/// allowed in HTTP/2.0 messages.
+///
+/// See [RFC 7540 §8.1.2.2](http://httpwg.org/specs/rfc7540.html#ConnectionSpecific).
+pub(crate) fn is_connection_specific(name: &HeaderName, value: &HeaderValue) -> bool {
+    match *name {
+        header::CONNECTION |
+        header::TRANSFER_ENCODING |
+        header::UPGRADE => true,
+        header::TE => value != "trailers",
+        _ => name == "keep-alive" || name == "proxy-connection",
+    }
+}
+
+/// Removes the connection-specific header fields.
+pub(crate) fn strip_connection_specific(fields: &mut HeaderMap) {
+    fields.remove(header::CONNECTION);
+    fields.remove(header::TRANSFER_ENCODING);
+    fields.remove(header::UPGRADE);
+    fields.remove("keep-alive");
+    fields.remove("proxy-connection");
+
+    let trailers = fields.get_all(header::TE).iter().any(|te| te == "trailers");
+
+    if fields.remove(header::TE).is_some() && trailers {
+        fields.insert(header::TE, HeaderValue::from_static("trailers"));
+    }
+}
+
+/// Returns true if the `host` header of a request names the same target as
+/// its `:authority`, ignoring case. A missing port stands for the default
+/// port of `scheme`.
+pub(crate) fn host_matches_authority(host: &HeaderValue, authority: &str, scheme: Option<&str>) -> bool {
+    let host = match host.to_str() {
+        Ok(host) => host,
+        Err(_) => return false,
+    };
+
+    let default_port = match scheme {
+        Some("http") => Some("80"),
+        Some("https") => Some("443"),
+        _ => None,
+    };
+
+    let (host, host_port) = split_port(host);
+    let (authority, authority_port) = split_port(authority);
+
+    host.eq_ignore_ascii_case(authority) &&
+        host_port.or(default_port) == authority_port.or(default_port)
+}
+
+/// Splits an authority into its host and its port, if it has a non-empty
+/// one.
+fn split_port(authority: &str) -> (&str, Option<&str>) {
+    match authority.rfind(':') {
+        // The colons of an IPv6 address are enclosed in brackets.
+        Some(i) if !authority[i..].contains(']') => {
+            let port = &authority[i + 1..];
+            (&authority[..i], if port.is_empty() { None } else { Some(port) })
+        },
+        _ => (authority, None),
+    }
+}
+
+/// Returns the connection-specific header field found in `fields`, if any.
+pub(crate) fn find_connection_specific(fields: &HeaderMap) -> Option<&HeaderName> {
+    fields
+        .iter()
+        .find(|&(name, value)| is_connection_specific(name, value))
+        .map(|(name, _)| name)
+}
@@ -4,6 +4,10 @@ use bytes::{BufMut};
 
 const SIZE_INCREMENT_MASK: u32 = 1 << 31;
 
+/// WINDOW_UPDATE frame
+///
+/// Increments the flow control window of a stream, or of the connection when
+/// sent on stream 0.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct WindowUpdate {
     stream_id: StreamId,
@@ -11,6 +15,7 @@ pub struct WindowUpdate {
 }
 
 impl WindowUpdate {
+    /// Creates a new WINDOW_UPDATE frame.
     pub fn new(stream_id: StreamId, size_increment: u32) -> WindowUpdate {
         WindowUpdate {
             stream_id,
@@ -18,16 +23,18 @@ impl WindowUpdate {
         }
     }
 
+    /// Returns the stream identifier that this frame is associated with.
     pub fn stream_id(&self) -> StreamId {
         self.stream_id
     }
 
+    /// Returns the number of bytes the window is incremented by.
     pub fn size_increment(&self) -> u32 {
         self.size_increment
     }
 
     /// Builds a `WindowUpdate` frame from a raw frame.
-    pub fn load(head: Head, payload: &[u8]) -> Result<WindowUpdate, Error> {
+    pub(crate) fn load(head: Head, payload: &[u8]) -> Result<WindowUpdate, Error> {
         debug_assert_eq!(head.kind(), ::frame::Kind::WindowUpdate);
         if payload.len() != 4 {
             return Err(Error::BadFrameSize);
@@ -47,7 +54,7 @@ impl WindowUpdate {
         })
     }
 
-    pub fn encode<B: BufMut>(&self, dst: &mut B) {
+    pub(crate) fn encode<B: BufMut>(&self, dst: &mut B) {
         trace!("encoding WINDOW_UPDATE; id={:?}", self.stream_id);
         let head = Head::new(Kind::WindowUpdate, 0, self.stream_id);
         head.encode(4, dst);
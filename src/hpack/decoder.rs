@@ -1,18 +1,21 @@
-use super::{huffman, Header};
+use super::{huffman, BytesStr, Header, Stats};
 use frame;
 
 use bytes::{Buf, Bytes, BytesMut};
 use http::header;
 use http::method::{self, Method};
 use http::status::{self, StatusCode};
-use string::String;
 
-use std::cmp;
+use std::{cmp, error, fmt};
 use std::collections::VecDeque;
 use std::io::Cursor;
 use std::str::Utf8Error;
 
-/// Decodes headers using HPACK
+/// Decodes header blocks using HPACK.
+///
+/// The decoder holds the dynamic table of the header blocks it decodes, so a
+/// single decoder must decode all the header blocks sent in one direction of
+/// a connection, in order.
 #[derive(Debug)]
 pub struct Decoder {
     // Protocol indicated that the max table size will update
@@ -20,28 +23,49 @@ pub struct Decoder {
     last_max_update: usize,
     table: Table,
     buffer: BytesMut,
+    stats: Stats,
+    uppercase_names: bool,
 }
 
 /// Represents all errors that can be encountered while performing the decoding
 /// of an HPACK header set.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum DecoderError {
+    /// The first byte of a header field representation is invalid.
     InvalidRepresentation,
+    /// An integer is encoded with an invalid prefix.
     InvalidIntegerPrefix,
+    /// A header field refers to an entry that is in neither table.
     InvalidTableIndex,
+    /// A Huffman encoded string is invalid.
     InvalidHuffmanCode,
+    /// A header field name or value is invalid.
     InvalidUtf8,
+    /// The value of the `:status` pseudo header is invalid.
     InvalidStatusCode,
+    /// A pseudo header is unknown.
     InvalidPseudoheader,
+    /// A dynamic table size update exceeds the max size, or is not at the
+    /// start of the header block.
     InvalidMaxDynamicSize,
+    /// An integer is too large.
     IntegerOverflow,
+    /// The header block ends in the middle of a header field.
     NeedMore(NeedMore),
 }
 
+/// Where a header block ended in the middle of a header field.
+///
+/// The undecoded bytes are left in the source buffer, so decoding can resume
+/// once the rest of the header block, such as the next CONTINUATION frame, is
+/// appended to it.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum NeedMore {
+    /// The header block ended before a string.
     UnexpectedEndOfStream,
+    /// The header block ended in the middle of an integer.
     IntegerUnderflow,
+    /// The header block ended in the middle of a string.
     StringUnderflow,
 }
 
@@ -136,28 +160,47 @@ enum Representation {
     SizeUpdate,
 }
 
+/// A decoded string literal.
+#[derive(Debug)]
+enum Literal {
+    /// The string as is, from `start` to `end` in the header field.
+    Raw(usize, usize),
+
+    /// The Huffman decoded string, and its encoded length.
+    Decoded(Bytes, usize),
+}
+
 #[derive(Debug)]
 struct Table {
     entries: VecDeque<Header>,
     size: usize,
     max_size: usize,
+    evictions: u64,
 }
 
 // ===== impl Decoder =====
 
 impl Decoder {
-    /// Creates a new `Decoder` with all settings set to default values.
+    /// Creates a new `Decoder` with a dynamic table of at most `size` bytes.
+    ///
+    /// `size` is the value of the `SETTINGS_HEADER_TABLE_SIZE` setting sent to
+    /// the encoder, 4,096 by default.
     pub fn new(size: usize) -> Decoder {
         Decoder {
             max_size_update: None,
             last_max_update: size,
             table: Table::new(size),
             buffer: BytesMut::with_capacity(4096),
+            stats: Stats::default(),
+            uppercase_names: false,
         }
     }
 
-    /// Queues a potential size update
-    #[allow(dead_code)]
+    /// Queues a change of the max size of the dynamic table.
+    ///
+    /// This is to be called when a new `SETTINGS_HEADER_TABLE_SIZE` setting is
+    /// sent to the encoder. The encoder may then resize its table up to `size`
+    /// at the start of the next header block.
     pub fn queue_size_update(&mut self, size: usize) {
         let size = match self.max_size_update {
             Some(v) => cmp::max(v, size),
@@ -167,8 +210,49 @@ impl Decoder {
         self.max_size_update = Some(size);
     }
 
-    /// Decodes the headers found in the given buffer.
-    pub fn decode<F>(&mut self, src: &mut Cursor<&mut BytesMut>, mut f: F) -> Result<(), DecoderError>
+    /// Returns true if the last call to `decode` lowercased a header name
+    /// with uppercase characters.
+    ///
+    /// HTTP/2.0 requires header names to be lowercase, but peers translating
+    /// HTTP/1.1 messages sometimes send them as is. Such names are always
+    /// lowercased, so that decoding the rest of the header block keeps the
+    /// dynamic table in sync; it is up to the caller to reject the message.
+    pub fn has_uppercase_names(&self) -> bool {
+        self.uppercase_names
+    }
+
+    /// Returns the compression statistics of the header blocks decoded so
+    /// far.
+    pub fn stats(&self) -> Stats {
+        let table = &self.table;
+        self.stats.with_table(table.size, table.entries.len(), table.evictions)
+    }
+
+    /// Decodes the headers found in the given buffer, calling `f` with each
+    /// one of them in order.
+    ///
+    /// Decoded bytes are removed from the buffer. If the buffer ends in the
+    /// middle of a header field, `DecoderError::NeedMore` is returned and the
+    /// bytes of that field are left in the buffer, so that decoding can
+    /// resume once the rest of the header block is appended to it.
+    ///
+    /// Values of header fields that the encoder sent as never indexed are
+    /// marked as sensitive.
+    pub fn decode<F>(&mut self, src: &mut Cursor<&mut BytesMut>, f: F) -> Result<(), DecoderError>
+    where
+        F: FnMut(Header),
+    {
+        let len = src.get_ref().len();
+        self.uppercase_names = false;
+        let res = self.decode_fields(src, f);
+
+        // Only the consumed bytes are removed from the buffer.
+        self.stats.record_encoded(len - src.get_ref().len());
+
+        res
+    }
+
+    fn decode_fields<F>(&mut self, src: &mut Cursor<&mut BytesMut>, mut f: F) -> Result<(), DecoderError>
     where
         F: FnMut(Header),
     {
@@ -192,6 +276,7 @@ impl Decoder {
                     can_resize = false;
                     let entry = self.decode_indexed(src)?;
                     consume(src);
+                    self.stats.record_header(entry.size() - 32);
                     f(entry);
                 },
                 LiteralWithIndexing => {
@@ -199,27 +284,32 @@ impl Decoder {
                     can_resize = false;
                     let entry = self.decode_literal(src, true)?;
 
-                    // Insert the header into the table
-                    self.table.insert(entry.clone());
-                    consume(src);
+                    // Insert a copy of the header into the table, so that
+                    // the table doesn't keep the whole header block alive.
+                    self.table.insert(entry.copy_storage());
 
+                    self.stats.record_header(entry.size() - 32);
                     f(entry);
                 },
                 LiteralWithoutIndexing => {
                     trace!("    LiteralWithoutIndexing; rem={:?}", src.remaining());
                     can_resize = false;
                     let entry = self.decode_literal(src, false)?;
-                    consume(src);
+                    self.stats.record_header(entry.size() - 32);
                     f(entry);
                 },
                 LiteralNeverIndexed => {
                     trace!("    LiteralNeverIndexed; rem={:?}", src.remaining());
                     can_resize = false;
-                    let entry = self.decode_literal(src, false)?;
-                    consume(src);
+                    let mut entry = self.decode_literal(src, false)?;
 
-                    // TODO: Track that this should never be indexed
+                    // Keep track of the field never being indexed, so that it
+                    // is never indexed if encoded again.
+                    if let Header::Field { ref mut value, .. } = entry {
+                        value.set_sensitive(true);
+                    }
 
+                    self.stats.record_header(entry.size() - 32);
                     f(entry);
                 },
                 SizeUpdate => {
@@ -256,11 +346,21 @@ impl Decoder {
         Ok(())
     }
 
-    fn decode_indexed(&self, buf: &mut Cursor<&mut BytesMut>) -> Result<Header, DecoderError> {
+    fn decode_indexed(&mut self, buf: &mut Cursor<&mut BytesMut>) -> Result<Header, DecoderError> {
         let index = decode_int(buf, 7)?;
-        self.table.get(index)
+        let entry = self.table.get(index)?;
+
+        if index > 61 {
+            self.stats.record_hit();
+        }
+
+        Ok(entry)
     }
 
+    /// Decodes a literal header field, and consumes it.
+    ///
+    /// Nothing is consumed unless the whole field is in the buffer, so that
+    /// decoding can resume from the start of the field.
     fn decode_literal(
         &mut self,
         buf: &mut Cursor<&mut BytesMut>,
@@ -277,16 +377,35 @@ impl Decoder {
             let name = self.decode_string(buf)?;
             let value = self.decode_string(buf)?;
 
-            Header::new(name, value)
+            let field = consume(buf);
+            self.record_literal(&name);
+            self.record_literal(&value);
+
+            let mut name = name.into_bytes(&field);
+
+            if name.iter().any(u8::is_ascii_uppercase) {
+                self.uppercase_names = true;
+                name = name.to_ascii_lowercase().into();
+            }
+
+            Header::new(name, value.into_bytes(&field))
         } else {
             let e = self.table.get(table_idx)?;
             let value = self.decode_string(buf)?;
 
-            e.name().into_entry(value)
+            let field = consume(buf);
+            self.record_literal(&value);
+            e.name().into_entry(value.into_bytes(&field))
+        }
+    }
+
+    fn record_literal(&mut self, literal: &Literal) {
+        if let Literal::Decoded(ref decoded, len) = *literal {
+            self.stats.record_huffman(decoded.len(), len);
         }
     }
 
-    fn decode_string(&mut self, buf: &mut Cursor<&mut BytesMut>) -> Result<Bytes, DecoderError> {
+    fn decode_string(&mut self, buf: &mut Cursor<&mut BytesMut>) -> Result<Literal, DecoderError> {
         const HUFF_FLAG: u8 = 0b10000000;
 
         // The first bit in the first byte contains the huffman encoded flag.
@@ -310,14 +429,17 @@ impl Decoder {
         if huff {
             let ret = {
                 let raw = &buf.bytes()[..len];
-                huffman::decode(raw, &mut self.buffer).map(Into::into)
+                huffman::decode(raw, &mut self.buffer)
             };
 
             buf.advance(len);
-            return ret;
+            return ret.map(|decoded| Literal::Decoded(decoded.freeze(), len));
         }
 
-        Ok(take(buf, len))
+        let start = buf.position() as usize;
+        buf.advance(len);
+
+        Ok(Literal::Raw(start, start + len))
     }
 }
 
@@ -423,19 +545,28 @@ fn peek_u8<B: Buf>(buf: &mut B) -> Option<u8> {
     }
 }
 
-fn take(buf: &mut Cursor<&mut BytesMut>, n: usize) -> Bytes {
+/// Removes the decoded bytes from the buffer, returning them.
+fn consume(buf: &mut Cursor<&mut BytesMut>) -> Bytes {
+    // remove bytes from the internal BytesMut when they have been successfully
+    // decoded. This is a more permanent cursor position, which will be
+    // used to resume if decoding was only partial.
     let pos = buf.position() as usize;
-    let mut head = buf.get_mut().split_to(pos + n);
+    let head = buf.get_mut().split_to(pos);
     buf.set_position(0);
-    head.split_to(pos);
     head.freeze()
 }
 
-fn consume(buf: &mut Cursor<&mut BytesMut>) {
-    // remove bytes from the internal BytesMut when they have been successfully
-    // decoded. This is a more permanent cursor position, which will be
-    // used to resume if decoding was only partial.
-    take(buf, 0);
+// ===== impl Literal =====
+
+impl Literal {
+    /// Returns the bytes of the string, given the bytes of the header field
+    /// it is in.
+    fn into_bytes(self, field: &Bytes) -> Bytes {
+        match self {
+            Literal::Raw(start, end) => field.slice(start, end),
+            Literal::Decoded(bytes, _) => bytes,
+        }
+    }
 }
 
 // ===== impl Table =====
@@ -446,6 +577,7 @@ impl Table {
             entries: VecDeque::new(),
             size: 0,
             max_size: max_size,
+            evictions: 0,
         }
     }
 
@@ -478,7 +610,7 @@ impl Table {
     }
 
     fn insert(&mut self, entry: Header) {
-        let len = entry.len();
+        let len = entry.size();
 
         self.reserve(len);
 
@@ -500,7 +632,8 @@ impl Table {
         while self.size + size > self.max_size {
             match self.entries.pop_back() {
                 Some(last) => {
-                    self.size -= last.len();
+                    self.size -= last.size();
+                    self.evictions += 1;
                 }
                 None => return,
             }
@@ -519,16 +652,48 @@ impl Table {
                     },
                 };
 
-                self.size -= last.len();
+                self.size -= last.size();
             }
 
             self.entries.pop_back();
+            self.evictions += 1;
         }
     }
 }
 
 // ===== impl DecoderError =====
 
+impl DecoderError {
+    fn as_str(&self) -> &'static str {
+        use self::DecoderError::*;
+
+        match *self {
+            InvalidRepresentation => "invalid header field representation",
+            InvalidIntegerPrefix => "invalid integer prefix",
+            InvalidTableIndex => "invalid table index",
+            InvalidHuffmanCode => "invalid Huffman code",
+            InvalidUtf8 => "invalid header field",
+            InvalidStatusCode => "invalid status code",
+            InvalidPseudoheader => "invalid pseudo header",
+            InvalidMaxDynamicSize => "invalid dynamic table size update",
+            IntegerOverflow => "integer overflow",
+            NeedMore(_) => "incomplete header block",
+        }
+    }
+}
+
+impl error::Error for DecoderError {
+    fn description(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for DecoderError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(self.as_str())
+    }
+}
+
 impl From<Utf8Error> for DecoderError {
     fn from(_: Utf8Error) -> DecoderError {
         // TODO: Better error?
@@ -543,6 +708,13 @@ impl From<header::InvalidHeaderValue> for DecoderError {
     }
 }
 
+impl From<header::InvalidHeaderValueBytes> for DecoderError {
+    fn from(_: header::InvalidHeaderValueBytes) -> DecoderError {
+        // TODO: Better error?
+        DecoderError::InvalidUtf8
+    }
+}
+
 impl From<header::InvalidHeaderName> for DecoderError {
     fn from(_: header::InvalidHeaderName) -> DecoderError {
         // TODO: Better error
@@ -571,17 +743,17 @@ impl From<DecoderError> for frame::Error {
 }
 
 /// Get an entry from the static table
-pub fn get_static(idx: usize) -> Header {
+pub(crate) fn get_static(idx: usize) -> Header {
     use http::header::HeaderValue;
 
     match idx {
-        1 => Header::Authority(from_static("")),
+        1 => Header::Authority(BytesStr::from_static("")),
         2 => Header::Method(Method::GET),
         3 => Header::Method(Method::POST),
-        4 => Header::Path(from_static("/")),
-        5 => Header::Path(from_static("/index.html")),
-        6 => Header::Scheme(from_static("http")),
-        7 => Header::Scheme(from_static("https")),
+        4 => Header::Path(BytesStr::from_static("/")),
+        5 => Header::Path(BytesStr::from_static("/index.html")),
+        6 => Header::Scheme(BytesStr::from_static("http")),
+        7 => Header::Scheme(BytesStr::from_static("https")),
         8 => Header::Status(StatusCode::OK),
         9 => Header::Status(StatusCode::NO_CONTENT),
         10 => Header::Status(StatusCode::PARTIAL_CONTENT),
@@ -781,9 +953,6 @@ pub fn get_static(idx: usize) -> Header {
     }
 }
 
-fn from_static(s: &'static str) -> String<Bytes> {
-    unsafe { String::from_utf8_unchecked(Bytes::from_static(s.as_bytes())) }
-}
 
 #[cfg(test)]
 mod test {
@@ -843,6 +1012,33 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_table_does_not_share_header_block() {
+        let mut de = Decoder::default();
+
+        // A literal with indexing, neither the name nor the value is Huffman
+        // encoded, and the value is too long to be stored inline.
+        let value = [b'a'; 64];
+        let mut buf = vec![0b01000000, 5];
+        buf.extend(b"x-foo");
+        buf.push(value.len() as u8);
+        buf.extend(&value[..]);
+
+        let mut buf = BytesMut::from(buf);
+        let mut res = vec![];
+
+        de.decode(&mut Cursor::new(&mut buf), |h| res.push(h)).unwrap();
+
+        // The decoded header shares the header block
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].value_slice(), &value[..]);
+        res.clear();
+
+        // Only the consumed buffer refers to the storage now
+        assert_eq!(de.table.entries.len(), 1);
+        assert!(buf.freeze().try_mut().is_ok());
+    }
+
     fn huff_encode(src: &[u8]) -> BytesMut {
         let mut buf = BytesMut::new();
         huffman::encode(src, &mut buf).unwrap();
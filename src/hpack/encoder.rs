@@ -1,29 +1,58 @@
-use super::{huffman, Header};
+use super::{huffman, DefaultIndexPolicy, Header, IndexPolicy, Name, Stats};
 use super::table::{Index, Table};
 
 use bytes::{BufMut, BytesMut};
-use http::header::{HeaderName, HeaderValue};
-
+use http::header::{HeaderName, HeaderValue, COOKIE};
+
+use std::{error, fmt};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Encodes header blocks using HPACK.
+///
+/// The encoder holds the dynamic table of the header blocks it encodes, so a
+/// single encoder must encode all the header blocks sent in one direction of
+/// a connection, in order.
+///
+/// Which header fields are added to the dynamic table is decided by an
+/// `IndexPolicy`. Header fields whose value is marked as sensitive are never
+/// indexed.
 #[derive(Debug)]
 pub struct Encoder {
     table: Table,
     size_update: Option<SizeUpdate>,
+    policy: Arc<dyn IndexPolicy>,
+    crumble_cookies: bool,
+    /// Set when the last field yielded by the iterator is a cookie.
+    is_cookie: bool,
+    /// Cookie pairs left to encode.
+    crumbs: VecDeque<HeaderValue>,
+    stats: Stats,
 }
 
+/// The outcome of encoding a header list.
 #[derive(Debug)]
 pub enum Encode {
+    /// The whole header list was encoded.
     Full,
+    /// The destination buffer is full. Encoding is resumed by passing the
+    /// state to the next call to `encode`, along with the same iterator.
     Partial(EncodeState),
 }
 
+/// The header field that did not fit in the destination buffer.
 #[derive(Debug)]
 pub struct EncodeState {
-    index: Index,
+    /// `None` when not even the dynamic table size update fit, in which case
+    /// no header field was encoded yet.
+    index: Option<Index>,
     value: Option<HeaderValue>,
 }
 
+/// Errors that can be encountered while encoding a header field.
 #[derive(Debug, PartialEq, Eq)]
 pub enum EncoderError {
+    /// The destination buffer is full.
     BufferOverflow,
 }
 
@@ -34,17 +63,54 @@ enum SizeUpdate {
 }
 
 impl Encoder {
+    /// Creates a new `Encoder` with a dynamic table of at most `max_size`
+    /// bytes, with room for `capacity` entries.
+    ///
+    /// `max_size` may not be more than the value of the
+    /// `SETTINGS_HEADER_TABLE_SIZE` setting received from the decoder, 4,096
+    /// by default.
     pub fn new(max_size: usize, capacity: usize) -> Encoder {
         Encoder {
             table: Table::new(max_size, capacity),
             size_update: None,
+            policy: Arc::new(DefaultIndexPolicy::new()),
+            crumble_cookies: false,
+            is_cookie: false,
+            crumbs: VecDeque::new(),
+            stats: Stats::default(),
         }
     }
 
+    /// Sets the policy deciding which header fields are indexed.
+    ///
+    /// Defaults to `DefaultIndexPolicy`.
+    pub fn set_index_policy(&mut self, policy: Arc<dyn IndexPolicy>) {
+        self.policy = policy;
+    }
+
+    /// Sets whether `cookie` headers are split into one field per cookie
+    /// pair, as allowed by [RFC 7540 §8.1.2.5].
+    ///
+    /// Each cookie pair is then indexed on its own, as decided by
+    /// `IndexPolicy::cookie_pair_indexing`, so the cookies that don't change
+    /// are sent as a single index once they are in the dynamic table.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// [RFC 7540 §8.1.2.5]: http://httpwg.org/specs/rfc7540.html#CompressCookie
+    pub fn set_crumble_cookies(&mut self, enabled: bool) {
+        self.crumble_cookies = enabled;
+    }
+
+    /// Returns the compression statistics of the header blocks encoded so
+    /// far.
+    pub fn stats(&self) -> Stats {
+        self.stats.with_table(self.table.size(), self.table.len(), self.table.evictions())
+    }
+
     /// Queues a max size update.
     ///
     /// The next call to `encode` will include a dynamic size update frame.
-    #[allow(dead_code)]
     pub fn update_max_size(&mut self, val: usize) {
         match self.size_update {
             Some(SizeUpdate::One(old)) => if val > old {
@@ -72,6 +138,16 @@ impl Encoder {
     }
 
     /// Encode a set of headers into the provide buffer
+    ///
+    /// Header fields are encoded for as long as they fit in the remaining
+    /// capacity of `dst`, which is never grown. If they don't all fit,
+    /// `Encode::Partial` is returned, and encoding is resumed by calling
+    /// `encode` again with the returned state and the same iterator, once
+    /// there is room in `dst`, such as when starting a CONTINUATION frame.
+    /// This includes the dynamic table size update queued by
+    /// `update_max_size`, which is encoded first.
+    ///
+    /// A field whose name is `None` has the same name as the field before it.
     pub fn encode<I>(
         &mut self,
         resume: Option<EncodeState>,
@@ -81,51 +157,76 @@ impl Encoder {
     where
         I: Iterator<Item = Header<Option<HeaderName>>>,
     {
+        if resume.is_none() {
+            // A new header list, forget the cookie pairs of the last one
+            self.crumbs.clear();
+            self.is_cookie = false;
+        }
+
         let len = dst.len();
 
-        if let Err(e) = self.encode_size_updates(dst) {
-            if e == EncoderError::BufferOverflow {
-                dst.truncate(len);
-            }
+        if self.encode_size_updates(dst).is_err() {
+            dst.truncate(len);
 
-            unreachable!("encode_size_updates errored");
+            return Encode::Partial(resume.unwrap_or(EncodeState {
+                index: None,
+                value: None,
+            }));
         }
 
+        self.stats.record_encoded(dst.len() - len);
+
         let mut last_index = None;
 
-        if let Some(resume) = resume {
+        if let Some(EncodeState { index: Some(index), value }) = resume {
             let len = dst.len();
+            let stats = self.stats;
 
-            let res = match resume.value {
-                Some(ref value) => self.encode_header_without_name(&resume.index, value, dst),
-                None => self.encode_header(&resume.index, dst),
+            let res = match value {
+                Some(ref value) => self.encode_header_without_name(&index, value, dst),
+                None => self.encode_header(&index, dst),
             };
 
             if res.is_err() {
                 dst.truncate(len);
-                return Encode::Partial(resume);
+                self.stats = stats;
+                return Encode::Partial(EncodeState {
+                    index: Some(index),
+                    value,
+                });
             }
-            last_index = Some(resume.index);
+
+            self.record(&index, value.as_ref(), dst.len() - len);
+            last_index = Some(index);
         }
 
-        for header in headers {
+        while let Some(header) = self.next_header(headers) {
             let len = dst.len();
+            let stats = self.stats;
 
             match header.reify() {
                 // The header has an associated name. In which case, try to
                 // index it in the table.
                 Ok(header) => {
-                    let index = self.table.index(header);
+                    let indexing = match header.name() {
+                        Name::Field(name) if self.crumble_cookies && *name == COOKIE => {
+                            self.policy.cookie_pair_indexing(header.value_slice())
+                        },
+                        name => self.policy.indexing(name, header.value_slice()),
+                    };
+                    let index = self.table.index(header, indexing);
                     let res = self.encode_header(&index, dst);
 
                     if res.is_err() {
                         dst.truncate(len);
+                        self.stats = stats;
                         return Encode::Partial(EncodeState {
-                            index: index,
+                            index: Some(index),
                             value: None,
                         });
                     }
 
+                    self.record(&index, None, dst.len() - len);
                     last_index = Some(index);
                 },
                 // The header does not have an associated name. This means that
@@ -143,11 +244,14 @@ impl Encoder {
 
                     if res.is_err() {
                         dst.truncate(len);
+                        self.stats = stats;
                         return Encode::Partial(EncodeState {
-                            index: last_index.unwrap(), // checked just above
+                            index: Some(last_index.unwrap()), // checked just above
                             value: Some(value),
                         });
                     }
+
+                    self.record(last_index.as_ref().unwrap(), Some(&value), dst.len() - len);
                 },
             };
         }
@@ -155,21 +259,112 @@ impl Encoder {
         Encode::Full
     }
 
+    /// Returns the next header to encode, splitting cookies into their pairs
+    /// if enabled.
+    fn next_header<I>(&mut self, headers: &mut I) -> Option<Header<Option<HeaderName>>>
+    where
+        I: Iterator<Item = Header<Option<HeaderName>>>,
+    {
+        if let Some(crumb) = self.crumbs.pop_front() {
+            return Some(Header::Field {
+                name: Some(COOKIE),
+                value: crumb,
+            });
+        }
+
+        let header = headers.next()?;
+
+        if !self.crumble_cookies {
+            return Some(header);
+        }
+
+        let value = match header {
+            Header::Field { name: Some(name), value } => {
+                self.is_cookie = name == COOKIE;
+
+                if !self.is_cookie {
+                    return Some(Header::Field { name: Some(name), value });
+                }
+
+                value
+            },
+            Header::Field { name: None, value } => {
+                if !self.is_cookie {
+                    return Some(Header::Field { name: None, value });
+                }
+
+                value
+            },
+            header => {
+                self.is_cookie = false;
+                return Some(header);
+            },
+        };
+
+        for crumb in value.as_bytes().split(|&b| b == b';') {
+            let start = crumb.iter().position(|&b| b != b' ').unwrap_or(crumb.len());
+            let crumb = &crumb[start..];
+
+            if crumb.is_empty() {
+                continue;
+            }
+
+            let mut crumb = HeaderValue::from_bytes(crumb)
+                .expect("cookie pair is a valid header value");
+            crumb.set_sensitive(value.is_sensitive());
+
+            self.crumbs.push_back(crumb);
+        }
+
+        let value = self.crumbs.pop_front().unwrap_or(value);
+
+        Some(Header::Field {
+            name: Some(COOKIE),
+            value,
+        })
+    }
+
+    /// Counts a header field that was just encoded, given its index and its
+    /// value if it has no name of its own.
+    fn record(&mut self, index: &Index, value: Option<&HeaderValue>, encoded: usize) {
+        let len = {
+            let header = self.table.resolve(index);
+
+            match value {
+                Some(value) => header.name().as_slice().len() + value.len(),
+                None => header.size() - 32,
+            }
+        };
+
+        self.stats.record_header(len);
+        self.stats.record_encoded(encoded);
+
+        match (index, value) {
+            (&Index::Indexed(idx, _), None) if idx > 61 => self.stats.record_hit(),
+            _ => {},
+        }
+    }
+
+    /// Encodes the queued size update, if any. The table is only resized
+    /// once the update is encoded, so that it is kept queued if it doesn't
+    /// fit in `dst`.
     fn encode_size_updates(&mut self, dst: &mut BytesMut) -> Result<(), EncoderError> {
-        match self.size_update.take() {
+        match self.size_update {
             Some(SizeUpdate::One(val)) => {
-                self.table.resize(val);
                 encode_size_update(val, dst)?;
+                self.table.resize(val);
             },
             Some(SizeUpdate::Two(min, max)) => {
-                self.table.resize(min);
-                self.table.resize(max);
                 encode_size_update(min, dst)?;
                 encode_size_update(max, dst)?;
+                self.table.resize(min);
+                self.table.resize(max);
             },
             None => {},
         }
 
+        self.size_update = None;
+
         Ok(())
     }
 
@@ -180,11 +375,20 @@ impl Encoder {
             },
             Index::Name(idx, _) => {
                 let header = self.table.resolve(&index);
+                let huffman = self.policy.huffman(header.name(), header.value_slice());
 
-                encode_not_indexed(idx, header.value_slice(), header.is_sensitive(), dst)?;
+                encode_not_indexed(
+                    idx,
+                    header.value_slice(),
+                    header.is_sensitive(),
+                    huffman,
+                    &mut self.stats,
+                    dst,
+                )?;
             },
             Index::Inserted(_) => {
                 let header = self.table.resolve(&index);
+                let huffman = self.policy.huffman(header.name(), header.value_slice());
 
                 assert!(!header.is_sensitive());
 
@@ -194,24 +398,28 @@ impl Encoder {
 
                 dst.put_u8(0b01000000);
 
-                encode_str(header.name().as_slice(), dst)?;
-                encode_str(header.value_slice(), dst)?;
+                encode_str(header.name().as_slice(), huffman, &mut self.stats, dst)?;
+                encode_str(header.value_slice(), huffman, &mut self.stats, dst)?;
             },
             Index::InsertedValue(idx, _) => {
                 let header = self.table.resolve(&index);
+                let huffman = self.policy.huffman(header.name(), header.value_slice());
 
                 assert!(!header.is_sensitive());
 
                 encode_int(idx, 6, 0b01000000, dst)?;
-                encode_str(header.value_slice(), dst)?;
+                encode_str(header.value_slice(), huffman, &mut self.stats, dst)?;
             },
             Index::NotIndexed(_) => {
                 let header = self.table.resolve(&index);
+                let huffman = self.policy.huffman(header.name(), header.value_slice());
 
                 encode_not_indexed2(
                     header.name().as_slice(),
                     header.value_slice(),
                     header.is_sensitive(),
+                    huffman,
+                    &mut self.stats,
                     dst,
                 )?;
             },
@@ -232,16 +440,30 @@ impl Encoder {
             Index::Inserted(..) |
             Index::InsertedValue(..) => {
                 let idx = self.table.resolve_idx(last);
+                let huffman = {
+                    let last = self.table.resolve(last);
+                    self.policy.huffman(last.name(), value.as_ref())
+                };
 
-                encode_not_indexed(idx, value.as_ref(), value.is_sensitive(), dst)?;
+                encode_not_indexed(
+                    idx,
+                    value.as_ref(),
+                    value.is_sensitive(),
+                    huffman,
+                    &mut self.stats,
+                    dst,
+                )?;
             },
             Index::NotIndexed(_) => {
                 let last = self.table.resolve(last);
+                let huffman = self.policy.huffman(last.name(), value.as_ref());
 
                 encode_not_indexed2(
                     last.name().as_slice(),
                     value.as_ref(),
                     value.is_sensitive(),
+                    huffman,
+                    &mut self.stats,
                     dst,
                 )?;
             },
@@ -257,6 +479,24 @@ impl Default for Encoder {
     }
 }
 
+// ===== impl EncoderError =====
+
+impl error::Error for EncoderError {
+    fn description(&self) -> &str {
+        match *self {
+            EncoderError::BufferOverflow => "buffer overflow",
+        }
+    }
+}
+
+impl fmt::Display for EncoderError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EncoderError::BufferOverflow => fmt.write_str("buffer overflow"),
+        }
+    }
+}
+
 fn encode_size_update<B: BufMut>(val: usize, dst: &mut B) -> Result<(), EncoderError> {
     encode_int(val, 5, 0b00100000, dst)
 }
@@ -265,6 +505,8 @@ fn encode_not_indexed(
     name: usize,
     value: &[u8],
     sensitive: bool,
+    huffman: bool,
+    stats: &mut Stats,
     dst: &mut BytesMut,
 ) -> Result<(), EncoderError> {
     if sensitive {
@@ -273,7 +515,7 @@ fn encode_not_indexed(
         encode_int(name, 4, 0, dst)?;
     }
 
-    encode_str(value, dst)?;
+    encode_str(value, huffman, stats, dst)?;
     Ok(())
 }
 
@@ -281,6 +523,8 @@ fn encode_not_indexed2(
     name: &[u8],
     value: &[u8],
     sensitive: bool,
+    huffman: bool,
+    stats: &mut Stats,
     dst: &mut BytesMut,
 ) -> Result<(), EncoderError> {
     if !dst.has_remaining_mut() {
@@ -293,19 +537,33 @@ fn encode_not_indexed2(
         dst.put_u8(0);
     }
 
-    encode_str(name, dst)?;
-    encode_str(value, dst)?;
+    encode_str(name, huffman, stats, dst)?;
+    encode_str(value, huffman, stats, dst)?;
     Ok(())
 }
 
-fn encode_str(val: &[u8], dst: &mut BytesMut) -> Result<(), EncoderError> {
+fn encode_str(
+    val: &[u8],
+    huffman: bool,
+    stats: &mut Stats,
+    dst: &mut BytesMut,
+) -> Result<(), EncoderError> {
     use std::io::Cursor;
 
     if !dst.has_remaining_mut() {
         return Err(EncoderError::BufferOverflow);
     }
 
-    if val.len() != 0 {
+    if !huffman {
+        // Write the string as is
+        encode_int(val.len(), 7, 0, dst)?;
+
+        if dst.remaining_mut() < val.len() {
+            return Err(EncoderError::BufferOverflow);
+        }
+
+        dst.put_slice(val);
+    } else if val.len() != 0 {
         let idx = dst.len();
 
         // Push a placeholder byte for the length header
@@ -316,6 +574,8 @@ fn encode_str(val: &[u8], dst: &mut BytesMut) -> Result<(), EncoderError> {
 
         let huff_len = dst.len() - (idx + 1);
 
+        stats.record_huffman(val.len(), huff_len);
+
         if encode_int_one_byte(huff_len, 7) {
             // Write the string head
             dst[idx] = 0x80 | huff_len as u8;
@@ -413,7 +673,8 @@ fn encode_int_one_byte(value: usize, prefix_bits: usize) -> bool {
 #[cfg(test)]
 mod test {
     use super::*;
-    use hpack::Header;
+    use hpack::{Decoder, Header, Name, NoIndexPolicy};
+    use std::io::Cursor;
     use http::*;
 
     #[test]
@@ -623,6 +884,191 @@ mod test {
         assert_eq!(6, res.len());
     }
 
+    #[test]
+    fn test_policy_no_index_header() {
+        let policy = DefaultIndexPolicy::new().no_index("x-request-id".parse().unwrap());
+
+        let mut encoder = Encoder::default();
+        encoder.set_index_policy(Arc::new(policy));
+
+        let res = encode(&mut encoder, vec![header("x-request-id", "1234")]);
+
+        assert_eq!(0, res[0]);
+        assert_eq!(0, encoder.table.len());
+
+        // Other headers are still indexed
+        encode(&mut encoder, vec![header("x-hello", "world")]);
+        assert_eq!(1, encoder.table.len());
+    }
+
+    #[test]
+    fn test_policy_never_index_header() {
+        let policy = DefaultIndexPolicy::new().never_index("x-api-key".parse().unwrap());
+
+        let mut encoder = Encoder::default();
+        encoder.set_index_policy(Arc::new(policy));
+
+        let res = encode(&mut encoder, vec![header("x-api-key", "1234")]);
+
+        assert_eq!(0b10000, res[0]);
+        assert_eq!(0, encoder.table.len());
+    }
+
+    #[test]
+    fn test_no_index_policy() {
+        let mut encoder = Encoder::default();
+        encoder.set_index_policy(Arc::new(NoIndexPolicy));
+
+        // Fully indexed by the static table
+        let res = encode(&mut encoder, vec![method("GET")]);
+        assert_eq!(*res, [0x80 | 2]);
+
+        // Name pulled from the static table
+        let res = encode(&mut encoder, vec![method("PATCH")]);
+        assert_eq!(2, res[0]);
+        assert_eq!(0, encoder.table.len());
+    }
+
+    #[test]
+    fn test_policy_without_huffman() {
+        let mut encoder = Encoder::default();
+        encoder.set_index_policy(Arc::new(DefaultIndexPolicy::new().huffman(false)));
+
+        let res = encode(&mut encoder, vec![header("foo", "bar")]);
+
+        assert_eq!(&[0b01000000, 3, b'f', b'o', b'o', 3, b'b', b'a', b'r'], &res[..]);
+    }
+
+    #[test]
+    fn test_crumble_cookies() {
+        let mut encoder = Encoder::default();
+        encoder.set_crumble_cookies(true);
+
+        let mut value = HeaderValue::from_static("a=1; b=2");
+        value.set_sensitive(true);
+
+        let res = encode(&mut encoder, vec![
+            header("cookie", "session=1234; theme=dark"),
+            Header::Field { name: None, value: value },
+            header("x-foo", "bar"),
+        ]);
+
+        let mut decoder = Decoder::default();
+        let mut decoded = vec![];
+
+        decoder.decode(&mut Cursor::new(&mut res.clone()), |h| decoded.push(h)).unwrap();
+
+        let crumbs: Vec<_> = decoded.iter()
+            .filter(|h| h.name() == Name::Field(&header::COOKIE))
+            .map(|h| (h.value_slice().to_vec(), h.is_sensitive()))
+            .collect();
+
+        assert_eq!(crumbs, [
+            (b"session=1234".to_vec(), false),
+            (b"theme=dark".to_vec(), false),
+            (b"a=1".to_vec(), true),
+            (b"b=2".to_vec(), true),
+        ]);
+        assert_eq!(decoded.len(), 5);
+
+        // The pairs that aren't sensitive are indexed
+        assert_eq!(3, encoder.table.len());
+
+        let res = encode(&mut encoder, vec![header("cookie", "session=1234; theme=dark")]);
+        assert_eq!(*res, [0x80 | 64, 0x80 | 63]);
+    }
+
+    #[test]
+    fn test_cookies_not_indexed_unless_crumbled() {
+        let mut encoder = Encoder::default();
+
+        encode(&mut encoder, vec![header("cookie", "session=1234")]);
+        assert_eq!(0, encoder.table.len());
+
+        // The policy can keep the pairs out of the table too
+        let policy = DefaultIndexPolicy::new().no_index(header::COOKIE);
+
+        let mut encoder = Encoder::default();
+        encoder.set_index_policy(Arc::new(policy));
+        encoder.set_crumble_cookies(true);
+
+        encode(&mut encoder, vec![header("cookie", "session=1234; theme=dark")]);
+        assert_eq!(0, encoder.table.len());
+    }
+
+    #[test]
+    fn test_crumbs_dropped_with_header_list() {
+        let mut encoder = Encoder::default();
+        encoder.set_crumble_cookies(true);
+
+        // Only the first pair fits
+        let mut dst = BytesMut::from(Vec::with_capacity(12));
+        let mut input = vec![header("cookie", "session=1234; theme=dark; lang=en")].into_iter();
+
+        match encoder.encode(None, &mut input, &mut dst) {
+            Encode::Partial(_) => {},
+            unexpected => panic!("encode returned unexpected: {:?}", unexpected),
+        }
+
+        // The header list is given up on, the next one has no cookie
+        let res = encode(&mut encoder, vec![header("x-foo", "bar")]);
+
+        let mut decoder = Decoder::default();
+        let mut decoded = vec![];
+
+        decoder.decode(&mut Cursor::new(&mut dst), |_| {}).unwrap();
+        decoder.decode(&mut Cursor::new(&mut res.clone()), |h| decoded.push(h)).unwrap();
+
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].name(), Name::Field(&"x-foo".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_stats_at_resume() {
+        let mut encoder = Encoder::default();
+        let mut dst = BytesMut::from(Vec::with_capacity(15));
+
+        let mut input = vec![
+            header("hello", "world"),
+            Header::Field {
+                name: None,
+                value: HeaderValue::from_bytes(b"zomg").unwrap(),
+            },
+        ].into_iter();
+
+        let resume = match encoder.encode(None, &mut input, &mut dst) {
+            Encode::Partial(r) => r,
+            _ => panic!(),
+        };
+
+        // The field that didn't fit is not counted
+        let stats = encoder.stats();
+        assert_eq!(1, stats.headers());
+        assert_eq!(10, stats.header_bytes());
+        assert_eq!(11, stats.encoded_bytes());
+        assert_eq!(2, stats.huffman_savings());
+        assert_eq!(1, stats.table_len());
+        assert_eq!(42, stats.table_size());
+
+        dst.clear();
+
+        match encoder.encode(Some(resume), &mut input, &mut dst) {
+            Encode::Full => {},
+            unexpected => panic!("resume returned unexpected: {:?}", unexpected),
+        }
+
+        let stats = encoder.stats();
+        assert_eq!(2, stats.headers());
+        assert_eq!(19, stats.header_bytes());
+        assert_eq!(11 + dst.len() as u64, stats.encoded_bytes());
+        assert_eq!(3, stats.huffman_savings());
+        assert_eq!(0, stats.dynamic_table_hits());
+
+        // The indexed field is found whole in the dynamic table
+        encode(&mut encoder, vec![header("hello", "world")]);
+        assert_eq!(1, encoder.stats().dynamic_table_hits());
+    }
+
     #[test]
     fn test_encoding_headers_with_same_name() {
         let mut encoder = Encoder::default();
@@ -722,6 +1168,38 @@ mod test {
         assert_eq!(Some(SizeUpdate::One(50)), encoder.size_update);
     }
 
+    #[test]
+    fn test_size_update_overflow() {
+        let mut encoder = Encoder::default();
+        encoder.update_max_size(100);
+
+        // The size update takes two bytes
+        let mut dst = BytesMut::from(Vec::with_capacity(1));
+        let mut input = vec![header("foo", "bar")].into_iter();
+
+        let resume = match encoder.encode(None, &mut input, &mut dst) {
+            Encode::Partial(r) => r,
+            unexpected => panic!("encode returned unexpected: {:?}", unexpected),
+        };
+
+        // Nothing is encoded, and the update is still queued
+        assert!(dst.is_empty());
+        assert_eq!(Some(SizeUpdate::One(100)), encoder.size_update);
+        assert_eq!(4096, encoder.table.max_size());
+
+        let mut dst = BytesMut::with_capacity(64);
+
+        match encoder.encode(Some(resume), &mut input, &mut dst) {
+            Encode::Full => {},
+            unexpected => panic!("resume returned unexpected: {:?}", unexpected),
+        }
+
+        assert_eq!(&[0b00111111, 100 - 31], &dst[..2]);
+        assert!(encoder.size_update.is_none());
+        assert_eq!(100, encoder.table.max_size());
+        assert_eq!(1, encoder.table.len());
+    }
+
     #[test]
     fn test_resizing_table() {
         let mut encoder = Encoder::default();
@@ -5,26 +5,55 @@ use http::{Method, StatusCode};
 use http::header::{HeaderName, HeaderValue};
 use string::{String, TryFrom};
 
+use std::{fmt, ops, str};
+
 /// HTTP/2.0 Header
+///
+/// The encoder takes headers whose field name is optional, a `None` name
+/// repeating the name of the field before it.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Header<T = HeaderName> {
-    Field { name: T, value: HeaderValue },
+    /// A regular header field.
+    Field {
+        /// The field name.
+        name: T,
+        /// The field value, never indexed if it is sensitive.
+        value: HeaderValue,
+    },
     // TODO: Change these types to `http::uri` types.
-    Authority(String<Bytes>),
+    /// The `:authority` pseudo header.
+    Authority(BytesStr),
+    /// The `:method` pseudo header.
     Method(Method),
-    Scheme(String<Bytes>),
-    Path(String<Bytes>),
+    /// The `:scheme` pseudo header.
+    Scheme(BytesStr),
+    /// The `:path` pseudo header.
+    Path(BytesStr),
+    /// The `:status` pseudo header.
     Status(StatusCode),
 }
 
+/// A UTF-8 string held in `Bytes`, the value of the `:authority`, `:scheme`
+/// and `:path` pseudo headers.
+///
+/// Cloning it does not copy the string. It derefs to `str`.
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct BytesStr(String<Bytes>);
+
 /// The header field name
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum Name<'a> {
+    /// The name of a regular header field.
     Field(&'a HeaderName),
+    /// `:authority`
     Authority,
+    /// `:method`
     Method,
+    /// `:scheme`
     Scheme,
+    /// `:path`
     Path,
+    /// `:status`
     Status,
 }
 
@@ -34,7 +63,7 @@ pub fn len(name: &HeaderName, value: &HeaderValue) -> usize {
 }
 
 impl Header<Option<HeaderName>> {
-    pub fn reify(self) -> Result<Header, HeaderValue> {
+    pub(crate) fn reify(self) -> Result<Header, HeaderValue> {
         use self::Header::*;
 
         Ok(match self {
@@ -59,6 +88,8 @@ impl Header<Option<HeaderName>> {
 }
 
 impl Header {
+    /// Creates a header from a field name and value, which may be a pseudo
+    /// header.
     pub fn new(name: Bytes, value: Bytes) -> Result<Header, DecoderError> {
         if name.len() == 0 {
             return Err(DecoderError::NeedMore(NeedMore::UnexpectedEndOfStream));
@@ -66,7 +97,7 @@ impl Header {
         if name[0] == b':' {
             match &name[1..] {
                 b"authority" => {
-                    let value = String::try_from(value)?;
+                    let value = BytesStr::from_bytes(value)?;
                     Ok(Header::Authority(value))
                 },
                 b"method" => {
@@ -74,11 +105,11 @@ impl Header {
                     Ok(Header::Method(method))
                 },
                 b"scheme" => {
-                    let value = String::try_from(value)?;
+                    let value = BytesStr::from_bytes(value)?;
                     Ok(Header::Scheme(value))
                 },
                 b"path" => {
-                    let value = String::try_from(value)?;
+                    let value = BytesStr::from_bytes(value)?;
                     Ok(Header::Path(value))
                 },
                 b"status" => {
@@ -90,7 +121,7 @@ impl Header {
         } else {
             // HTTP/2 requires lower case header names
             let name = HeaderName::from_lowercase(&name)?;
-            let value = HeaderValue::from_bytes(&value)?;
+            let value = HeaderValue::from_shared(value)?;
 
             Ok(Header::Field {
                 name: name,
@@ -99,7 +130,9 @@ impl Header {
         }
     }
 
-    pub fn len(&self) -> usize {
+    /// Returns the size of the header as counted against the dynamic table
+    /// size: the length of its name and value plus 32 bytes.
+    pub fn size(&self) -> usize {
         match *self {
             Header::Field {
                 ref name,
@@ -127,6 +160,7 @@ impl Header {
         }
     }
 
+    /// Returns the header value
     pub fn value_slice(&self) -> &[u8] {
         match *self {
             Header::Field {
@@ -140,7 +174,7 @@ impl Header {
         }
     }
 
-    pub fn value_eq(&self, other: &Header) -> bool {
+    pub(crate) fn value_eq(&self, other: &Header) -> bool {
         match *self {
             Header::Field {
                 ref value, ..
@@ -176,6 +210,7 @@ impl Header {
         }
     }
 
+    /// Returns true if the header is never to be indexed.
     pub fn is_sensitive(&self) -> bool {
         match *self {
             Header::Field {
@@ -186,28 +221,31 @@ impl Header {
         }
     }
 
-    pub fn skip_value_index(&self) -> bool {
-        use http::header;
-
+    /// Returns a copy of the header whose value has storage of its own,
+    /// rather than sharing the buffer it was decoded from.
+    pub(crate) fn copy_storage(&self) -> Header {
         match *self {
             Header::Field {
-                ref name, ..
-            } => match *name {
-                header::AGE |
-                header::AUTHORIZATION |
-                header::CONTENT_LENGTH |
-                header::ETAG |
-                header::IF_MODIFIED_SINCE |
-                header::IF_NONE_MATCH |
-                header::LOCATION |
-                header::COOKIE |
-                header::SET_COOKIE => true,
-                _ => false,
+                ref name,
+                ref value,
+            } => {
+                let mut copy = HeaderValue::from_bytes(value.as_bytes())
+                    .expect("header value is valid");
+                copy.set_sensitive(value.is_sensitive());
+
+                Header::Field {
+                    name: name.clone(),
+                    value: copy,
+                }
             },
-            Header::Path(..) => true,
-            _ => false,
+            Header::Authority(ref v) => Header::Authority(v.copy_storage()),
+            Header::Method(ref v) => Header::Method(v.clone()),
+            Header::Scheme(ref v) => Header::Scheme(v.copy_storage()),
+            Header::Path(ref v) => Header::Path(v.copy_storage()),
+            Header::Status(v) => Header::Status(v),
         }
     }
+
 }
 
 // Mostly for tests
@@ -231,16 +269,16 @@ impl From<Header> for Header<Option<HeaderName>> {
 }
 
 impl<'a> Name<'a> {
-    pub fn into_entry(self, value: Bytes) -> Result<Header, DecoderError> {
+    pub(crate) fn into_entry(self, value: Bytes) -> Result<Header, DecoderError> {
         match self {
             Name::Field(name) => Ok(Header::Field {
                 name: name.clone(),
-                value: HeaderValue::from_bytes(&*value)?,
+                value: HeaderValue::from_shared(value)?,
             }),
-            Name::Authority => Ok(Header::Authority(String::try_from(value)?)),
+            Name::Authority => Ok(Header::Authority(BytesStr::from_bytes(value)?)),
             Name::Method => Ok(Header::Method(Method::from_bytes(&*value)?)),
-            Name::Scheme => Ok(Header::Scheme(String::try_from(value)?)),
-            Name::Path => Ok(Header::Path(String::try_from(value)?)),
+            Name::Scheme => Ok(Header::Scheme(BytesStr::from_bytes(value)?)),
+            Name::Path => Ok(Header::Path(BytesStr::from_bytes(value)?)),
             Name::Status => {
                 match StatusCode::from_bytes(&value) {
                     Ok(status) => Ok(Header::Status(status)),
@@ -251,6 +289,7 @@ impl<'a> Name<'a> {
         }
     }
 
+    /// Returns the header name as bytes
     pub fn as_slice(&self) -> &[u8] {
         match *self {
             Name::Field(ref name) => name.as_ref(),
@@ -262,3 +301,55 @@ impl<'a> Name<'a> {
         }
     }
 }
+
+// ===== impl BytesStr =====
+
+impl BytesStr {
+    /// Creates a `BytesStr` from a static string, without copying it.
+    pub fn from_static(src: &'static str) -> BytesStr {
+        BytesStr(unsafe { String::from_utf8_unchecked(Bytes::from_static(src.as_bytes())) })
+    }
+
+    /// Creates a `BytesStr` from bytes, without copying them, if they are
+    /// valid UTF-8.
+    pub fn from_bytes(src: Bytes) -> Result<BytesStr, str::Utf8Error> {
+        String::try_from(src).map(BytesStr)
+    }
+
+    /// Returns the string as a `&str`.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn copy_storage(&self) -> BytesStr {
+        BytesStr(unsafe { String::from_utf8_unchecked(Bytes::from(self.as_str())) })
+    }
+
+    pub(crate) fn from_string(src: String<Bytes>) -> BytesStr {
+        BytesStr(src)
+    }
+
+    pub(crate) fn into_string(self) -> String<Bytes> {
+        self.0
+    }
+}
+
+impl ops::Deref for BytesStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<[u8]> for BytesStr {
+    fn as_ref(&self) -> &[u8] {
+        self.as_str().as_bytes()
+    }
+}
+
+impl fmt::Debug for BytesStr {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), fmt)
+    }
+}
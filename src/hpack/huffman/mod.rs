@@ -1,116 +1,167 @@
 mod table;
 
-use self::table::{DECODE_TABLE, ENCODE_TABLE};
+use self::table::{DECODE_CODES, DECODE_FAST, DECODE_FAST_BITS, DECODE_SYMBOLS, ENCODE_TABLE};
 use hpack::{DecoderError, EncoderError};
 
 use bytes::{BufMut, BytesMut};
+use byteorder::{BigEndian, ByteOrder};
 
-// Constructed in the generated `table.rs` file
-struct Decoder {
-    state: usize,
-    maybe_eos: bool,
-}
+use std::cmp;
 
-// These flags must match the ones in genhuff.rs
+/// Longest code, that of EOS
+const MAX_BITS: usize = 30;
 
-const MAYBE_EOS: u8 = 1;
-const DECODED: u8 = 2;
-const ERROR: u8 = 4;
+const EOS: u16 = 256;
 
 pub fn decode(src: &[u8], buf: &mut BytesMut) -> Result<BytesMut, DecoderError> {
-    let mut decoder = Decoder::new();
+    // Bits left to decode, starting from the most significant bit
+    let mut bits: u64 = 0;
+    let mut nbits = 0;
+    let mut pos = 0;
 
     // Max compression ratio is >= 0.5
     buf.reserve(src.len() << 1);
 
-    for b in src {
-        if let Some(b) = decoder.decode4(b >> 4)? {
-            buf.put_u8(b);
+    loop {
+        // Refill whole bytes
+        if src.len() - pos >= 8 {
+            let n = (63 - nbits) >> 3;
+            bits |= BigEndian::read_u64(&src[pos..]) >> nbits;
+            nbits += n << 3;
+            pos += n;
+
+            // Clear the bits that were read past the last whole byte
+            bits &= !(!0 >> nbits);
+        } else {
+            while nbits <= 56 && pos < src.len() {
+                bits |= (src[pos] as u64) << (56 - nbits);
+                nbits += 8;
+                pos += 1;
+            }
+
+            if nbits == 0 {
+                break;
+            }
         }
 
-        if let Some(b) = decoder.decode4(b & 0xf)? {
-            buf.put_u8(b);
+        // As long as there are enough bits for the longest code, symbols are
+        // decoded without checking for the end of the input.
+        let min = if pos == src.len() { 0 } else { MAX_BITS };
+
+        while nbits > min {
+            // Most symbols, ASCII letters and digits included, are decoded
+            // with a single lookup.
+            let (byte, len) = DECODE_FAST[(bits >> (64 - DECODE_FAST_BITS)) as usize];
+            let len = len as usize;
+
+            if len != 0 && len <= nbits {
+                buf.put_u8(byte);
+                bits <<= len;
+                nbits -= len;
+                continue;
+            }
+
+            match decode_long(bits, nbits) {
+                Some((EOS, _)) => {
+                    // EOS in the string is a decoding error
+                    return Err(DecoderError::InvalidHuffmanCode);
+                },
+                Some((sym, len)) => {
+                    buf.put_u8(sym as u8);
+                    bits <<= len;
+                    nbits -= len;
+                },
+                None => {
+                    // The input ends in the middle of a code, which must be
+                    // the padding.
+                    debug_assert!(pos == src.len());
+                    return finish(bits, nbits, buf);
+                },
+            }
+        }
+
+        if pos == src.len() {
+            break;
         }
     }
 
-    if !decoder.is_final() {
+    finish(bits, nbits, buf)
+}
+
+fn finish(bits: u64, nbits: usize, buf: &mut BytesMut) -> Result<BytesMut, DecoderError> {
+    // What is left must be padding, which is fewer than 8 bits of the EOS
+    // code, all ones.
+    if nbits >= 8 || bits != !(!0 >> nbits) {
         return Err(DecoderError::InvalidHuffmanCode);
     }
 
     Ok(buf.take())
 }
 
-// TODO: return error when there is not enough room to encode the value
-pub fn encode<B: BufMut>(src: &[u8], dst: &mut B) -> Result<(), EncoderError> {
-    let mut bits: u64 = 0;
-    let mut bits_left = 40;
-    let mut rem = dst.remaining_mut();
-
-    for &b in src {
-        let (nbits, code) = ENCODE_TABLE[b as usize];
-
-        bits |= code << (bits_left - nbits);
-        bits_left -= nbits;
-
-        while bits_left <= 32 {
-            if rem == 0 {
-                return Err(EncoderError::BufferOverflow);
-            }
-
-            dst.put_u8((bits >> 32) as u8);
-
-            bits <<= 8;
-            bits_left += 8;
-            rem -= 1;
+/// Decodes a symbol whose code is longer than `DECODE_FAST_BITS`, returning
+/// the symbol and the length of its code.
+fn decode_long(bits: u64, nbits: usize) -> Option<(u16, usize)> {
+    let codes = DECODE_CODES.iter()
+        .enumerate()
+        .take(cmp::min(nbits, MAX_BITS) + 1)
+        .skip(DECODE_FAST_BITS + 1);
+
+    for (len, &(first, count, offset)) in codes {
+        let code = (bits >> (64 - len)) as u32;
+
+        // The codes of a given length are consecutive
+        if code >= first && code - first < count as u32 {
+            let sym = DECODE_SYMBOLS[offset as usize + (code - first) as usize];
+            return Some((sym, len));
         }
     }
 
-    if bits_left != 40 {
-        if rem == 0 {
-            return Err(EncoderError::BufferOverflow);
-        }
+    None
+}
 
-        // This writes the EOS token
-        bits |= (1 << bits_left) - 1;
-        dst.put_u8((bits >> 32) as u8);
-    }
+pub fn encode<B: BufMut>(src: &[u8], dst: &mut B) -> Result<(), EncoderError> {
+    // Bits to write, starting from the least significant bit
+    let mut bits: u64 = 0;
+    let mut nbits = 0;
 
-    Ok(())
-}
+    for &b in src {
+        let (len, code) = ENCODE_TABLE[b as usize];
 
-impl Decoder {
-    fn new() -> Decoder {
-        Decoder {
-            state: 0,
-            maybe_eos: false,
+        if nbits + len <= 64 {
+            bits = bits << len | code;
+            nbits += len;
+            continue;
         }
-    }
 
-    // Decodes 4 bits
-    fn decode4(&mut self, input: u8) -> Result<Option<u8>, DecoderError> {
-        // (next-state, byte, flags)
-        let (next, byte, flags) = DECODE_TABLE[self.state][input as usize];
+        // Write the full 64 bits at once
+        let rem = 64 - nbits;
+        bits = bits << rem | code >> (len - rem);
 
-        if flags & ERROR == ERROR {
-            // Data followed the EOS marker
-            return Err(DecoderError::InvalidHuffmanCode);
+        if dst.remaining_mut() < 8 {
+            return Err(EncoderError::BufferOverflow);
         }
 
-        let mut ret = None;
+        dst.put_u64_be(bits);
 
-        if flags & DECODED == DECODED {
-            ret = Some(byte);
-        }
+        nbits = len - rem;
+        bits = code & ((1 << nbits) - 1);
+    }
 
-        self.state = next;
-        self.maybe_eos = flags & MAYBE_EOS == MAYBE_EOS;
+    // Pad the last byte with the most significant bits of EOS
+    let pad = (8 - nbits % 8) % 8;
+    bits = bits << pad | ((1 << pad) - 1);
+    nbits += pad;
 
-        Ok(ret)
+    if dst.remaining_mut() < nbits / 8 {
+        return Err(EncoderError::BufferOverflow);
     }
 
-    fn is_final(&self) -> bool {
-        self.state == 0 || self.maybe_eos
+    while nbits > 0 {
+        nbits -= 8;
+        dst.put_u8((bits >> nbits) as u8);
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -210,4 +261,38 @@ mod test {
             assert_eq!(&decoded[..], &s[..]);
         }
     }
+
+    #[test]
+    fn encode_decode_all_bytes() {
+        let src: Vec<u8> = (0..256).map(|b| b as u8).chain((0..256).rev().map(|b| b as u8)).collect();
+        let mut dst = Vec::new();
+
+        encode(&src, &mut dst).unwrap();
+
+        assert_eq!(&decode(&dst).unwrap()[..], &src[..]);
+    }
+
+    #[test]
+    fn encode_buffer_overflow() {
+        let src = [b'a'; 100];
+        let mut dst = BytesMut::with_capacity(32);
+
+        // 63 bytes once encoded
+        let res = encode(&src, &mut dst);
+        assert_eq!(res, Err(EncoderError::BufferOverflow));
+    }
+
+    #[test]
+    fn decode_invalid_padding() {
+        // Padding that is not all ones
+        assert_eq!(decode(&[0b00111110]), Err(DecoderError::InvalidHuffmanCode));
+
+        // Padding longer than 7 bits
+        assert_eq!(decode(&[0b00111111, 0xff]), Err(DecoderError::InvalidHuffmanCode));
+    }
+
+    #[test]
+    fn decode_eos() {
+        assert_eq!(decode(&[0xff, 0xff, 0xff, 0xff]), Err(DecoderError::InvalidHuffmanCode));
+    }
 }
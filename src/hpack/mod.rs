@@ -1,7 +1,62 @@
+//! HPACK header compression.
+//!
+//! This module provides the [HPACK] encoder and decoder used by the HTTP/2.0
+//! connections, for use on their own. An `Encoder` turns a list of headers
+//! into a header block, and a `Decoder` turns a header block back into the
+//! headers. Each one holds a dynamic table, so the header blocks of one
+//! direction of a connection must all go through the same encoder and
+//! decoder, in order.
+//!
+//! Header values marked as sensitive with `HeaderValue::set_sensitive` are
+//! never indexed, and are decoded as sensitive.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate bytes;
+//! # extern crate h2;
+//! # extern crate http;
+//! use bytes::BytesMut;
+//! use h2::hpack::{Decoder, Encode, Encoder, Header};
+//! use http::Method;
+//! use http::header::{HeaderName, HeaderValue};
+//! use std::io::Cursor;
+//!
+//! # pub fn main() {
+//! let headers = vec![
+//!     Header::Method(Method::GET),
+//!     Header::Field {
+//!         name: HeaderName::from_static("accept"),
+//!         value: HeaderValue::from_static("*/*"),
+//!     },
+//! ];
+//!
+//! let mut encoder = Encoder::default();
+//! let mut buf = BytesMut::with_capacity(1024);
+//! let mut iter = headers.clone().into_iter().map(Into::into);
+//!
+//! match encoder.encode(None, &mut iter, &mut buf) {
+//!     Encode::Full => {},
+//!     Encode::Partial(_) => panic!("buffer too small"),
+//! }
+//!
+//! let mut decoder = Decoder::default();
+//! let mut decoded = vec![];
+//!
+//! decoder.decode(&mut Cursor::new(&mut buf), |header| decoded.push(header)).unwrap();
+//!
+//! assert_eq!(decoded, headers);
+//! # }
+//! ```
+//!
+//! [HPACK]: https://tools.ietf.org/html/rfc7541
+
 mod encoder;
 mod decoder;
 pub(crate) mod header;
 mod huffman;
+mod policy;
+mod stats;
 mod table;
 
 #[cfg(test)]
@@ -9,4 +64,6 @@ mod test;
 
 pub use self::decoder::{Decoder, DecoderError, NeedMore};
 pub use self::encoder::{Encode, EncodeState, Encoder, EncoderError};
-pub use self::header::Header;
+pub use self::header::{BytesStr, Header, Name};
+pub use self::policy::{DefaultIndexPolicy, IndexPolicy, Indexing, NoIndexPolicy};
+pub use self::stats::Stats;
@@ -0,0 +1,189 @@
+use super::Name;
+
+use http::header::{self, HeaderName};
+
+use std::fmt;
+
+/// Decides how each header field is represented when encoded.
+///
+/// The policy is consulted for every header field, except for the values
+/// that follow the first one of a multi-valued field, which are never
+/// indexed. Whatever the policy says, fields found whole in the static table
+/// are referred to by index, values marked as sensitive are never indexed,
+/// and fields that would take up more than three quarters of the dynamic
+/// table are not indexed.
+pub trait IndexPolicy: fmt::Debug + Send + Sync + 'static {
+    /// Returns whether the header field is added to the dynamic table.
+    fn indexing(&self, name: Name, value: &[u8]) -> Indexing;
+
+    /// Returns whether a single cookie pair is added to the dynamic table,
+    /// when the encoder splits `cookie` headers into their pairs.
+    ///
+    /// Defaults to the `indexing` of a `cookie` field with the pair as value.
+    fn cookie_pair_indexing(&self, value: &[u8]) -> Indexing {
+        self.indexing(Name::Field(&header::COOKIE), value)
+    }
+
+    /// Returns true if the literal name and value of the header field are
+    /// Huffman encoded.
+    ///
+    /// Defaults to `true`.
+    fn huffman(&self, name: Name, value: &[u8]) -> bool {
+        let _ = (name, value);
+        true
+    }
+}
+
+/// How a header field is represented in a header block.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Indexing {
+    /// The field is added to the dynamic table, so that later header blocks
+    /// can refer to it.
+    Index,
+
+    /// The field is sent as a literal, without being added to the dynamic
+    /// table. Only the static table is referred to, for the name or the
+    /// whole field.
+    NoIndex,
+
+    /// The field is sent as a literal that intermediaries must not index
+    /// either.
+    ///
+    /// This only applies to regular header fields. Pseudo headers are sent
+    /// without indexing instead.
+    NeverIndex,
+}
+
+/// The default `IndexPolicy`.
+///
+/// Header fields are indexed, except for the ones whose values seldom repeat:
+/// `:path`, `age`, `authorization`, `content-length`, `etag`,
+/// `if-modified-since`, `if-none-match`, `location`, `cookie` and
+/// `set-cookie`. When cookies are split into their pairs, each pair is
+/// indexed though, unless `cookie` is added to `no_index` or `never_index`.
+/// More header names can be kept out of the dynamic table, such as the ones
+/// carrying request IDs or trace contexts.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate h2;
+/// # extern crate http;
+/// use h2::hpack::DefaultIndexPolicy;
+/// use http::header::HeaderName;
+///
+/// # pub fn main() {
+/// let policy = DefaultIndexPolicy::new()
+///     .no_index(HeaderName::from_static("x-request-id"))
+///     .never_index(HeaderName::from_static("x-api-key"));
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DefaultIndexPolicy {
+    no_index: Vec<HeaderName>,
+    never_index: Vec<HeaderName>,
+    huffman: bool,
+}
+
+/// An `IndexPolicy` that never adds header fields to the dynamic table.
+///
+/// Header fields are still compressed with the static table and Huffman
+/// encoding, but the encoder keeps no state between header blocks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoIndexPolicy;
+
+// ===== impl DefaultIndexPolicy =====
+
+impl DefaultIndexPolicy {
+    /// Returns the default policy.
+    pub fn new() -> Self {
+        DefaultIndexPolicy {
+            no_index: vec![],
+            never_index: vec![],
+            huffman: true,
+        }
+    }
+
+    /// Sends the fields named `name` without indexing them.
+    pub fn no_index(mut self, name: HeaderName) -> Self {
+        self.no_index.push(name);
+        self
+    }
+
+    /// Sends the fields named `name` as never indexed, as if their values
+    /// were marked as sensitive.
+    pub fn never_index(mut self, name: HeaderName) -> Self {
+        self.never_index.push(name);
+        self
+    }
+
+    /// Sets whether header names and values are Huffman encoded.
+    ///
+    /// Defaults to `true`.
+    pub fn huffman(mut self, enabled: bool) -> Self {
+        self.huffman = enabled;
+        self
+    }
+}
+
+impl IndexPolicy for DefaultIndexPolicy {
+    fn indexing(&self, name: Name, _: &[u8]) -> Indexing {
+        // Don't index certain headers. This logic is borrowed from nghttp2.
+        let name = match name {
+            Name::Field(name) => name,
+            Name::Path => return Indexing::NoIndex,
+            _ => return Indexing::Index,
+        };
+
+        if self.never_index.contains(name) {
+            return Indexing::NeverIndex;
+        }
+
+        if self.no_index.contains(name) {
+            return Indexing::NoIndex;
+        }
+
+        match *name {
+            header::AGE |
+            header::AUTHORIZATION |
+            header::CONTENT_LENGTH |
+            header::ETAG |
+            header::IF_MODIFIED_SINCE |
+            header::IF_NONE_MATCH |
+            header::LOCATION |
+            header::COOKIE |
+            header::SET_COOKIE => Indexing::NoIndex,
+            _ => Indexing::Index,
+        }
+    }
+
+    fn cookie_pair_indexing(&self, _: &[u8]) -> Indexing {
+        // Single cookie pairs are worth indexing, whole cookie strings are
+        // not.
+        if self.never_index.contains(&header::COOKIE) {
+            Indexing::NeverIndex
+        } else if self.no_index.contains(&header::COOKIE) {
+            Indexing::NoIndex
+        } else {
+            Indexing::Index
+        }
+    }
+
+    fn huffman(&self, _: Name, _: &[u8]) -> bool {
+        self.huffman
+    }
+}
+
+impl Default for DefaultIndexPolicy {
+    fn default() -> Self {
+        DefaultIndexPolicy::new()
+    }
+}
+
+// ===== impl NoIndexPolicy =====
+
+impl IndexPolicy for NoIndexPolicy {
+    fn indexing(&self, _: Name, _: &[u8]) -> Indexing {
+        Indexing::NoIndex
+    }
+}
@@ -0,0 +1,93 @@
+/// Compression statistics of an `Encoder` or a `Decoder`.
+///
+/// The counters add up over all the header blocks encoded or decoded so far,
+/// while the dynamic table figures are taken when the statistics are
+/// returned. Comparing `header_bytes` to `encoded_bytes` gives the overall
+/// compression ratio.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    headers: u64,
+    header_bytes: u64,
+    encoded_bytes: u64,
+    dynamic_table_hits: u64,
+    evictions: u64,
+    huffman_decoded: u64,
+    huffman_encoded: u64,
+    table_size: usize,
+    table_len: usize,
+}
+
+impl Stats {
+    /// Returns the number of header fields.
+    pub fn headers(&self) -> u64 {
+        self.headers
+    }
+
+    /// Returns the length of the names and values of the header fields,
+    /// before compression.
+    pub fn header_bytes(&self) -> u64 {
+        self.header_bytes
+    }
+
+    /// Returns the length of the header blocks, including dynamic table size
+    /// updates.
+    pub fn encoded_bytes(&self) -> u64 {
+        self.encoded_bytes
+    }
+
+    /// Returns the number of header fields represented by a single index into
+    /// the dynamic table.
+    pub fn dynamic_table_hits(&self) -> u64 {
+        self.dynamic_table_hits
+    }
+
+    /// Returns the number of entries evicted from the dynamic table.
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Returns the number of bytes saved by Huffman encoding string literals.
+    ///
+    /// This is negative if Huffman encoding made the strings longer, which
+    /// happens with binary data.
+    pub fn huffman_savings(&self) -> i64 {
+        self.huffman_decoded as i64 - self.huffman_encoded as i64
+    }
+
+    /// Returns the current size of the dynamic table, as counted against its
+    /// max size: the length of the names and values plus 32 bytes per entry.
+    pub fn table_size(&self) -> usize {
+        self.table_size
+    }
+
+    /// Returns the current number of entries in the dynamic table.
+    pub fn table_len(&self) -> usize {
+        self.table_len
+    }
+
+    pub(super) fn record_header(&mut self, len: usize) {
+        self.headers += 1;
+        self.header_bytes += len as u64;
+    }
+
+    pub(super) fn record_encoded(&mut self, len: usize) {
+        self.encoded_bytes += len as u64;
+    }
+
+    pub(super) fn record_hit(&mut self) {
+        self.dynamic_table_hits += 1;
+    }
+
+    pub(super) fn record_huffman(&mut self, decoded: usize, encoded: usize) {
+        self.huffman_decoded += decoded as u64;
+        self.huffman_encoded += encoded as u64;
+    }
+
+    /// Returns the statistics, along with the state of the dynamic table.
+    pub(super) fn with_table(mut self, size: usize, len: usize, evictions: u64) -> Stats {
+        self.table_size = size;
+        self.table_len = len;
+        self.evictions = evictions;
+        self
+    }
+}
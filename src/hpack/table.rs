@@ -1,4 +1,4 @@
-use super::Header;
+use super::{Header, Indexing};
 
 use fnv::FnvHasher;
 use http::header;
@@ -18,6 +18,8 @@ pub struct Table {
     // Size is in bytes
     size: usize,
     max_size: usize,
+    // Number of headers evicted so far
+    evictions: u64,
 }
 
 #[derive(Debug)]
@@ -81,6 +83,7 @@ impl Table {
                 inserted: 0,
                 size: 0,
                 max_size: max_size,
+                evictions: 0,
             }
         } else {
             let capacity = cmp::max(to_raw_capacity(capacity).next_power_of_two(), 8);
@@ -92,6 +95,7 @@ impl Table {
                 inserted: 0,
                 size: 0,
                 max_size: max_size,
+                evictions: 0,
             }
         }
     }
@@ -105,6 +109,21 @@ impl Table {
         self.max_size
     }
 
+    /// Returns the number of headers in the table
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the table size
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of headers evicted from the table so far
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
     /// Gets the header stored in the table
     pub fn resolve<'a>(&'a self, index: &'a Index) -> &'a Header {
         use self::Index::*;
@@ -131,20 +150,20 @@ impl Table {
     }
 
     /// Index the header in the HPACK table.
-    pub fn index(&mut self, header: Header) -> Index {
+    pub fn index(&mut self, mut header: Header, indexing: Indexing) -> Index {
         // Check the static table
         let statik = index_static(&header);
 
-        // Don't index certain headers. This logic is borrowed from nghttp2.
-        if header.skip_value_index() {
-            // Right now, if this is true, the header name is always in the
-            // static table. At some point in the future, this might not be true
-            // and this logic will need to be updated.
-            debug_assert!(
-                statik.is_some(),
-                "skip_value_index requires a static name",
-            );
-            return Index::new(statik, header);
+        match indexing {
+            Indexing::Index => {},
+            Indexing::NoIndex => return Index::new(statik, header),
+            Indexing::NeverIndex => {
+                if let Header::Field { ref mut value, .. } = header {
+                    value.set_sensitive(true);
+                }
+
+                return Index::new(statik, header);
+            },
         }
 
         // If the header is already indexed by the static table, return that
@@ -153,7 +172,7 @@ impl Table {
         }
 
         // Don't index large headers
-        if header.len() * 4 > self.max_size * 3 {
+        if header.size() * 4 > self.max_size * 3 {
             return Index::new(statik, header);
         }
 
@@ -163,7 +182,7 @@ impl Table {
     fn index_dynamic(&mut self, header: Header, statik: Option<(usize, bool)>) -> Index {
         debug_assert!(self.assert_valid_state("one"));
 
-        if header.len() + self.size < self.max_size || !header.is_sensitive() {
+        if header.size() + self.size < self.max_size || !header.is_sensitive() {
             // Only grow internal storage if needed
             self.reserve_one();
         }
@@ -237,7 +256,7 @@ impl Table {
                 return Index::Name(real_idx + DYN_OFFSET, header);
             }
 
-            self.update_size(header.len(), Some(index));
+            self.update_size(header.size(), Some(index));
 
             // Insert the new header
             self.insert(header, hash);
@@ -283,7 +302,7 @@ impl Table {
 
         // Passing in `usize::MAX` for prev_idx since there is no previous
         // header in this case.
-        if self.update_size(header.len(), None) {
+        if self.update_size(header.size(), None) {
             while dist != 0 {
                 let back = probe.wrapping_sub(1) & self.mask;
 
@@ -394,7 +413,8 @@ impl Table {
         let mut probe = desired_pos(self.mask, slot.hash);
 
         // Update the size
-        self.size -= slot.header.len();
+        self.size -= slot.header.size();
+        self.evictions += 1;
 
         debug_assert_eq!(
             self.indices
@@ -624,19 +644,6 @@ impl Table {
     }
 }
 
-#[cfg(test)]
-impl Table {
-    /// Returns the number of headers in the table
-    pub fn len(&self) -> usize {
-        self.slots.len()
-    }
-
-    /// Returns the table size
-    pub fn size(&self) -> usize {
-        self.size
-    }
-}
-
 impl Index {
     fn new(v: Option<(usize, bool)>, e: Header) -> Index {
         match v {
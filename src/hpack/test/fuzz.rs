@@ -3,7 +3,7 @@ extern crate env_logger;
 extern crate quickcheck;
 extern crate rand;
 
-use hpack::{Decoder, Encode, Encoder, Header};
+use hpack::{BytesStr, Decoder, Encode, Encoder, Header};
 
 use http::header::{HeaderName, HeaderValue};
 
@@ -399,7 +399,6 @@ fn gen_string(g: &mut StdRng, min: usize, max: usize) -> String {
     String::from_utf8(bytes).unwrap()
 }
 
-fn to_shared(src: String) -> ::string::String<Bytes> {
-    let b: Bytes = src.into();
-    unsafe { ::string::String::from_utf8_unchecked(b) }
+fn to_shared(src: String) -> BytesStr {
+    BytesStr::from_bytes(src.into()).unwrap()
 }
@@ -104,6 +104,7 @@ extern crate bytes;
 extern crate fnv;
 
 extern crate byteorder;
+extern crate iovec;
 extern crate slab;
 
 #[macro_use]
@@ -111,6 +112,10 @@ extern crate log;
 extern crate string;
 extern crate indexmap;
 
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+
 macro_rules! proto_err {
     (conn: $($msg:tt)+) => {
         debug!("connection error PROTOCOL_ERROR -- {};", format_args!($($msg)+))
@@ -121,24 +126,20 @@ macro_rules! proto_err {
 }
 
 mod error;
-#[cfg_attr(feature = "unstable", allow(missing_docs))]
 mod codec;
-mod hpack;
+pub mod hpack;
 mod proto;
-
-#[cfg(not(feature = "unstable"))]
-mod frame;
-
-#[cfg(feature = "unstable")]
-#[allow(missing_docs)]
 pub mod frame;
 
 pub mod client;
 pub mod server;
+pub mod session;
+mod observer;
 mod share;
 
 pub use error::{Error, Reason};
-pub use share::{SendStream, StreamId, RecvStream, ReleaseCapacity, PingPong, Ping, Pong};
-
-#[cfg(feature = "unstable")]
-pub use codec::{Codec, RecvError, SendError, UserError};
+pub use frame::{HeaderValidation, Padding};
+pub use observer::{FrameObserver, ObservedFrame, ConnectionEvent, Redaction};
+pub use share::{SendStream, StreamId, RecvStream, ReleaseCapacity, PingPong, Ping, Pong, GoAway,
+                RemoteSettings, ExtensionFrame, ExtensionFrames};
+pub use codec::{Codec, FlushPolicy, RecvError, SendError, UserError};
@@ -0,0 +1,272 @@
+use frame::{self, Frame, Pseudo, Reason};
+use share::StreamId;
+
+use byteorder::{BigEndian, ByteOrder};
+use http::HeaderMap;
+use http::header::{AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, SET_COOKIE};
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Observes the frames exchanged on a connection, and its events.
+///
+/// An observer is registered with [`client::Builder::frame_observer`] or
+/// [`server::Builder::frame_observer`]. It is called synchronously, by the
+/// task driving the connection, so it should not block. All methods have a
+/// default implementation that does nothing.
+///
+/// [`client::Builder::frame_observer`]: client/struct.Builder.html#method.frame_observer
+/// [`server::Builder::frame_observer`]: server/struct.Builder.html#method.frame_observer
+pub trait FrameObserver: Send + Sync + 'static {
+    /// Called for every frame read from the peer, once it is decoded.
+    fn recv_frame(&self, frame: &ObservedFrame) {
+        let _ = frame;
+    }
+
+    /// Called for every frame written to the send buffer.
+    fn send_frame(&self, frame: &ObservedFrame) {
+        let _ = frame;
+    }
+
+    /// Called on connection level events.
+    fn event(&self, event: &ConnectionEvent) {
+        let _ = event;
+    }
+
+    /// Returns which header values are passed on to the observer.
+    ///
+    /// Defaults to `Redaction::Sensitive`.
+    fn redaction(&self) -> Redaction {
+        Redaction::Sensitive
+    }
+}
+
+/// Which header values are passed on to a `FrameObserver`.
+///
+/// Header names are always passed on. Redacted values are replaced with
+/// `"[redacted]"`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Redaction {
+    /// No value is redacted.
+    None,
+
+    /// The values of headers that carry credentials are redacted. These are
+    /// `authorization`, `proxy-authorization`, `cookie` and `set-cookie`, as
+    /// well as any value marked as sensitive.
+    Sensitive,
+
+    /// All values are redacted, pseudo headers included.
+    All,
+}
+
+/// A frame, as seen by a `FrameObserver`.
+///
+/// Frames are reported as they are on the wire, so a header block that is
+/// split across CONTINUATION frames is reported as several frames. Its
+/// headers are attached to the HEADERS or PUSH_PROMISE frame when sending,
+/// and to the frame that ends the block when receiving.
+#[derive(Debug, Clone)]
+pub struct ObservedFrame {
+    kind: u8,
+    flags: u8,
+    stream_id: StreamId,
+    payload_len: usize,
+    headers: Option<Vec<(String, String)>>,
+}
+
+/// A connection level event, as seen by a `FrameObserver`.
+#[derive(Debug, Clone)]
+pub enum ConnectionEvent {
+    /// The settings sent to the peer were acknowledged, and are now in
+    /// effect.
+    LocalSettingsApplied,
+
+    /// The settings received from the peer were acknowledged, and are now in
+    /// effect.
+    RemoteSettingsApplied,
+
+    /// A GOAWAY frame was sent to the peer.
+    GoAwaySent {
+        /// The last stream ID that was, or may be, processed.
+        last_stream_id: StreamId,
+        /// The reason for closing the connection.
+        reason: Reason,
+    },
+
+    /// A GOAWAY frame was received from the peer.
+    GoAwayReceived {
+        /// The last stream ID that the peer processed, or may process.
+        last_stream_id: StreamId,
+        /// The reason for closing the connection.
+        reason: Reason,
+    },
+}
+
+/// The observer of a connection, if any.
+#[derive(Clone, Default)]
+pub(crate) struct Observer(Option<Arc<dyn FrameObserver>>);
+
+/// The direction of an observed frame.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Direction {
+    Recv,
+    Send,
+}
+
+const REDACTED: &str = "[redacted]";
+
+// ===== impl ObservedFrame =====
+
+impl ObservedFrame {
+    /// Returns the frame type, as defined in [Section 11.2] of the HTTP/2.0
+    /// spec, or by an extension.
+    ///
+    /// [Section 11.2]: http://httpwg.org/specs/rfc7540.html#iana-frames
+    pub fn kind(&self) -> u8 {
+        self.kind
+    }
+
+    /// Returns the frame flags.
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// Returns the ID of the stream the frame is sent on.
+    pub fn stream_id(&self) -> StreamId {
+        self.stream_id.clone()
+    }
+
+    /// Returns the length of the frame payload, padding included.
+    pub fn payload_len(&self) -> usize {
+        self.payload_len
+    }
+
+    /// Returns the decoded header block, pseudo headers first, for the frames
+    /// that carry one.
+    pub fn headers(&self) -> Option<&[(String, String)]> {
+        self.headers.as_ref().map(|h| &h[..])
+    }
+}
+
+// ===== impl Observer =====
+
+impl Observer {
+    pub fn new<O: FrameObserver>(observer: O) -> Self {
+        Observer(Some(Arc::new(observer)))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Returns the header block of `frame`, if the frame has one and the
+    /// observer is enabled.
+    pub fn header_block<T>(&self, frame: &Frame<T>) -> Option<Vec<(String, String)>> {
+        let observer = match self.0 {
+            Some(ref observer) => observer,
+            None => return None,
+        };
+
+        let (pseudo, fields) = match *frame {
+            Frame::Headers(ref v) => (v.pseudo(), v.fields()),
+            Frame::PushPromise(ref v) => (v.pseudo(), v.fields()),
+            _ => return None,
+        };
+
+        Some(header_list(observer.redaction(), pseudo, fields))
+    }
+
+    /// Reports a frame, given the bytes of its head.
+    pub fn frame(&self, direction: Direction, head: &[u8], headers: Option<Vec<(String, String)>>) {
+        let observer = match self.0 {
+            Some(ref observer) => observer,
+            None => return,
+        };
+
+        let frame = ObservedFrame {
+            kind: head[3],
+            flags: head[4],
+            stream_id: StreamId::from_internal(frame::Head::parse(head).stream_id()),
+            payload_len: BigEndian::read_uint(&head[..3], 3) as usize,
+            headers,
+        };
+
+        match direction {
+            Direction::Recv => observer.recv_frame(&frame),
+            Direction::Send => observer.send_frame(&frame),
+        }
+    }
+
+    pub fn event(&self, event: ConnectionEvent) {
+        if let Some(ref observer) = self.0 {
+            observer.event(&event);
+        }
+    }
+}
+
+impl fmt::Debug for Observer {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("Observer")
+            .field(&self.is_enabled())
+            .finish()
+    }
+}
+
+fn header_list(redaction: Redaction, pseudo: &Pseudo, fields: &HeaderMap) -> Vec<(String, String)> {
+    let mut list = Vec::with_capacity(fields.len() + 4);
+
+    {
+        let mut pseudo_header = |name: &str, value: &str| {
+            let value = match redaction {
+                Redaction::All => REDACTED,
+                _ => value,
+            };
+
+            list.push((name.to_string(), value.to_string()));
+        };
+
+        if let Some(ref method) = pseudo.method {
+            pseudo_header(":method", method.as_str());
+        }
+
+        if let Some(ref scheme) = pseudo.scheme {
+            pseudo_header(":scheme", scheme);
+        }
+
+        if let Some(ref authority) = pseudo.authority {
+            pseudo_header(":authority", authority);
+        }
+
+        if let Some(ref path) = pseudo.path {
+            pseudo_header(":path", path);
+        }
+
+        if let Some(ref status) = pseudo.status {
+            pseudo_header(":status", status.as_str());
+        }
+    }
+
+    for (name, value) in fields {
+        let is_redacted = match redaction {
+            Redaction::None => false,
+            Redaction::Sensitive => {
+                value.is_sensitive() ||
+                    name == AUTHORIZATION ||
+                    name == PROXY_AUTHORIZATION ||
+                    name == COOKIE ||
+                    name == SET_COOKIE
+            },
+            Redaction::All => true,
+        };
+
+        let value = if is_redacted {
+            REDACTED.to_string()
+        } else {
+            String::from_utf8_lossy(value.as_bytes()).into_owned()
+        };
+
+        list.push((name.as_str().to_string(), value));
+    }
+
+    list
+}
@@ -1,8 +1,9 @@
-use {client, frame, proto, server};
-use codec::RecvError;
+use {client, frame, hpack, proto, server};
+use codec::{FlushPolicy, RecvError};
 use frame::{Reason, StreamId};
 
 use frame::DEFAULT_INITIAL_WINDOW_SIZE;
+use observer::ConnectionEvent;
 use proto::*;
 
 use bytes::{Bytes, IntoBuf};
@@ -28,6 +29,9 @@ where
     /// graceful shutdown.
     error: Option<Reason>,
 
+    /// The last GOAWAY frame received from the peer.
+    received_go_away: Option<frame::GoAway>,
+
     /// Read / write frame values
     codec: Codec<T, Prioritized<B::Buf>>,
 
@@ -37,6 +41,9 @@ where
     /// Ping/pong handler
     ping_pong: PingPong,
 
+    /// Extension frames handler
+    extensions: Extensions,
+
     /// Connection settings
     settings: Settings,
 
@@ -50,9 +57,13 @@ where
 #[derive(Debug, Clone)]
 pub(crate) struct Config {
     pub next_stream_id: StreamId,
+    pub max_stream_id: StreamId,
     pub initial_max_send_streams: usize,
     pub reset_stream_duration: Duration,
     pub reset_stream_max: usize,
+    pub padding: frame::Padding,
+    pub coalesce_data: bool,
+    pub header_validation: frame::HeaderValidation,
     pub settings: frame::Settings,
 }
 
@@ -84,6 +95,7 @@ where
                 .unwrap_or(DEFAULT_INITIAL_WINDOW_SIZE),
             initial_max_send_streams: config.initial_max_send_streams,
             local_next_stream_id: config.next_stream_id,
+            local_max_stream_id: config.max_stream_id,
             local_push_enabled: config.settings.is_push_enabled(),
             local_reset_duration: config.reset_stream_duration,
             local_reset_max: config.reset_stream_max,
@@ -91,13 +103,18 @@ where
             remote_max_initiated: config.settings
                 .max_concurrent_streams()
                 .map(|max| max as usize),
+            local_padding: config.padding,
+            local_coalesce_data: config.coalesce_data,
+            header_validation: config.header_validation,
         });
         Connection {
             state: State::Open,
             error: None,
+            received_go_away: None,
             codec: codec,
             go_away: GoAway::new(),
             ping_pong: PingPong::new(),
+            extensions: Extensions::new(),
             settings: Settings::new(),
             streams: streams,
             _phantom: PhantomData,
@@ -108,6 +125,24 @@ where
         self.streams.set_target_connection_window_size(size);
     }
 
+    pub fn set_flush_policy(&mut self, policy: FlushPolicy) {
+        self.codec.set_flush_policy(policy);
+    }
+
+    /// Requests the buffered frames to be flushed the next time the
+    /// connection has nothing more to write.
+    pub fn request_flush(&mut self) {
+        self.streams.request_flush();
+    }
+
+    pub fn hpack_encoder_stats(&self) -> hpack::Stats {
+        self.codec.hpack_encoder_stats()
+    }
+
+    pub fn hpack_decoder_stats(&self) -> hpack::Stats {
+        self.codec.hpack_decoder_stats()
+    }
+
     /// Returns `Ready` when the connection is ready to receive a frame.
     ///
     /// Returns `RecvError` as this may raise errors that are caused by delayed
@@ -116,6 +151,7 @@ where
         // The order of these calls don't really matter too much
         try_ready!(self.ping_pong.send_pending_pong(&mut self.codec));
         try_ready!(self.ping_pong.send_pending_ping(&mut self.codec));
+        try_ready!(self.extensions.send_pending_extensions(&mut self.codec));
         try_ready!(
             self.settings
                 .send_pending_ack(&mut self.codec, &mut self.streams)
@@ -133,9 +169,8 @@ where
         self.go_away.send_pending_go_away(&mut self.codec)
     }
 
-    fn go_away(&mut self, id: StreamId, e: Reason) {
-        let frame = frame::GoAway::new(id, e);
-        self.streams.send_go_away(id);
+    fn go_away(&mut self, frame: frame::GoAway) {
+        self.streams.send_go_away(frame.last_stream_id());
         self.go_away.go_away(frame);
     }
 
@@ -155,28 +190,41 @@ where
     }
 
     fn take_error(&mut self, ours: Reason) -> Poll<(), proto::Error> {
-        let reason = if let Some(theirs) = self.error.take() {
-            match (ours, theirs) {
-                // If either side reported an error, return that
-                // to the user.
-                (Reason::NO_ERROR, err) | (err, Reason::NO_ERROR) => err,
-                // If both sides reported an error, give their
-                // error back to th user. We assume our error
-                // was a consequence of their error, and less
-                // important.
-                (_, theirs) => theirs,
+        if let Some(theirs) = self.error.take() {
+            // If the peer reported an error, give their error back to the
+            // user, along with any debug data. If we reported an error as
+            // well, we assume ours was a consequence of theirs, and less
+            // important.
+            if theirs != Reason::NO_ERROR {
+                let frame = self.received_go_away
+                    .clone()
+                    .unwrap_or_else(|| frame::GoAway::new(StreamId::zero(), theirs));
+
+                return Err(proto::Error::GoAway(frame, theirs));
             }
-        } else {
-            ours
-        };
+        }
 
-        if reason == Reason::NO_ERROR {
+        if ours == Reason::NO_ERROR {
             Ok(().into())
         } else {
-            Err(proto::Error::Proto(reason))
+            Err(proto::Error::Proto(ours))
         }
     }
 
+    /// Returns the settings received from the peer so far.
+    pub fn remote_settings(&self) -> Option<frame::Settings> {
+        self.streams.remote_settings()
+    }
+
+    pub fn poll_remote_settings(&self, version: &mut usize) -> Poll<frame::Settings, ::Error> {
+        self.streams.poll_remote_settings(version)
+    }
+
+    /// Returns the last GOAWAY frame received from the peer, if any.
+    pub fn received_go_away(&self) -> Option<&frame::GoAway> {
+        self.received_go_away.as_ref()
+    }
+
     /// Closes the connection by transitioning to a GOAWAY state
     /// iff there are no streams or references
     pub fn maybe_close_connection_if_no_streams(&mut self) {
@@ -191,6 +239,15 @@ where
         self.ping_pong.take_user_pings()
     }
 
+    pub(crate) fn take_user_extensions(&mut self) -> Option<UserExtensions> {
+        self.extensions.take_user_extensions()
+    }
+
+    /// Queue an extension frame to be sent.
+    pub fn send_extension_frame(&mut self, frame: frame::Extension) -> Result<(), ::Error> {
+        self.extensions.send_extension_frame(frame)
+    }
+
     /// Advances the internal state of the connection.
     pub fn poll(&mut self) -> Poll<(), proto::Error> {
         use codec::RecvError::*;
@@ -212,7 +269,12 @@ where
 
                             if self.error.is_some() || self.go_away.should_close_on_idle() {
                                 if !self.streams.has_streams() {
-                                    self.go_away_now(Reason::NO_ERROR);
+                                    // Repeat the reason of a graceful GOAWAY
+                                    // we've sent, if any, so it isn't sent twice.
+                                    let reason = self.go_away
+                                        .going_away_reason()
+                                        .unwrap_or(Reason::NO_ERROR);
+                                    self.go_away_now(reason);
                                     continue;
                                 }
                             }
@@ -302,8 +364,12 @@ where
                         return Err(RecvError::Connection(reason));
                     }
                 }
-                // Only NO_ERROR should be waiting for idle
-                debug_assert_eq!(reason, Reason::NO_ERROR, "graceful GOAWAY should be NO_ERROR");
+                // Otherwise, this was a graceful GOAWAY and the connection
+                // keeps running until idle.
+                debug_assert!(
+                    reason == Reason::NO_ERROR || self.go_away.is_user_initiated(),
+                    "graceful GOAWAY should be NO_ERROR"
+                );
             }
             try_ready!(self.poll_ready());
 
@@ -326,16 +392,22 @@ where
                 },
                 Some(Settings(frame)) => {
                     trace!("recv SETTINGS; frame={:?}", frame);
-                    self.settings.recv_settings(frame);
+                    self.settings.recv_settings(frame, self.codec.observer());
                 },
                 Some(GoAway(frame)) => {
                     trace!("recv GOAWAY; frame={:?}", frame);
+                    self.codec.observer().event(ConnectionEvent::GoAwayReceived {
+                        last_stream_id: ::StreamId::from_internal(frame.last_stream_id()),
+                        reason: frame.reason(),
+                    });
+
                     // This should prevent starting new streams,
                     // but should allow continuing to process current streams
                     // until they are all EOS. Once they are, State should
                     // transition to GoAway.
                     self.streams.recv_go_away(&frame)?;
                     self.error = Some(frame.reason());
+                    self.received_go_away = Some(frame);
                 },
                 Some(Ping(frame)) => {
                     trace!("recv PING; frame={:?}", frame);
@@ -347,7 +419,8 @@ where
                         );
 
                         let last_processed_id = self.streams.last_processed_id();
-                        self.go_away(last_processed_id, Reason::NO_ERROR);
+                        let frame = self.go_away.lower_last_stream_id(last_processed_id);
+                        self.go_away(frame);
                     }
                 },
                 Some(WindowUpdate(frame)) => {
@@ -358,6 +431,10 @@ where
                     trace!("recv PRIORITY; frame={:?}", frame);
                     // TODO: handle
                 },
+                Some(Extension(frame)) => {
+                    trace!("recv EXTENSION; frame={:?}", frame);
+                    self.extensions.recv_extension_frame(frame);
+                },
                 None => {
                     trace!("codec closed");
                     self.streams.recv_eof(false)
@@ -393,7 +470,7 @@ where
     }
 
     // Graceful shutdown only makes sense for server peers.
-    pub fn go_away_gracefully(&mut self) {
+    pub fn go_away_gracefully(&mut self, reason: Reason, debug_data: Bytes) {
         if self.go_away.is_going_away() {
             // No reason to start a new one.
             return;
@@ -410,7 +487,9 @@ where
         // > send another GOAWAY frame with an updated last stream identifier.
         // > This ensures that a connection can be cleanly shut down without
         // > losing requests.
-        self.go_away(StreamId::MAX, Reason::NO_ERROR);
+        let frame = frame::GoAway::with_debug_data(StreamId::MAX, reason, debug_data);
+        self.streams.send_go_away(frame.last_stream_id());
+        self.go_away.go_away_gracefully_from_user(frame);
 
         // We take the advice of waiting 1 RTT literally, and wait
         // for a pong before proceeding.
@@ -1,5 +1,5 @@
 use codec::{RecvError, SendError};
-use frame::Reason;
+use frame::{self, Reason};
 
 use std::io;
 
@@ -7,6 +7,11 @@ use std::io;
 #[derive(Debug)]
 pub enum Error {
     Proto(Reason),
+    /// The peer sent a GOAWAY frame.
+    GoAway(frame::GoAway, Reason),
+    /// The peer did not process the stream: it either refused the stream, or
+    /// sent a GOAWAY frame that leaves the stream out.
+    Unprocessed(Option<frame::GoAway>, Reason),
     Io(io::Error),
 }
 
@@ -17,6 +22,10 @@ impl Error {
     pub(super) fn shallow_clone(&self) -> Error {
         match *self {
             Error::Proto(reason) => Error::Proto(reason),
+            Error::GoAway(ref frame, reason) => Error::GoAway(frame.clone(), reason),
+            Error::Unprocessed(ref frame, reason) => {
+                Error::Unprocessed(frame.clone(), reason)
+            },
             Error::Io(ref io) => Error::Io(io::Error::from(io.kind())),
         }
     }
@@ -37,7 +46,9 @@ impl From<io::Error> for Error {
 impl From<Error> for RecvError {
     fn from(src: Error) -> RecvError {
         match src {
-            Error::Proto(reason) => RecvError::Connection(reason),
+            Error::Proto(reason) |
+            Error::GoAway(_, reason) |
+            Error::Unprocessed(_, reason) => RecvError::Connection(reason),
             Error::Io(e) => RecvError::Io(e),
         }
     }
@@ -46,7 +57,9 @@ impl From<Error> for RecvError {
 impl From<Error> for SendError {
     fn from(src: Error) -> SendError {
         match src {
-            Error::Proto(reason) => SendError::Connection(reason),
+            Error::Proto(reason) |
+            Error::GoAway(_, reason) |
+            Error::Unprocessed(_, reason) => SendError::Connection(reason),
             Error::Io(e) => SendError::Io(e),
         }
     }
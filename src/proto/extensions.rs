@@ -0,0 +1,198 @@
+use codec::{Codec, UserError};
+use frame::{self, Extension};
+
+use bytes::Buf;
+use futures::{task, Async, Poll};
+use futures::task::Task;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex};
+use tokio_io::AsyncWrite;
+
+/// Maximum number of received extension frames waiting to be polled by the
+/// user. Frames received once the queue is full are dropped.
+const MAX_PENDING_RECV: usize = 128;
+
+/// Sends and receives extension frames on behalf of the user.
+#[derive(Debug)]
+pub(crate) struct Extensions {
+    inner: Arc<Mutex<Inner>>,
+    taken: bool,
+}
+
+/// The user's handle to the connection's extension frames.
+#[derive(Debug)]
+pub(crate) struct UserExtensions(Arc<Mutex<Inner>>);
+
+#[derive(Debug)]
+struct Inner {
+    /// Frames waiting to be written.
+    pending_send: VecDeque<Extension>,
+    /// Frames received and waiting to be polled by the user.
+    pending_recv: VecDeque<Extension>,
+    /// Task to wake up the main `Connection`.
+    send_task: Option<Task>,
+    /// Task to wake up `share::ExtensionFrames::poll`.
+    recv_task: Option<Task>,
+    /// True while the user holds a `UserExtensions` handle. Received frames
+    /// are discarded otherwise.
+    is_user_interested: bool,
+    /// True once the connection is closed.
+    is_closed: bool,
+}
+
+// ===== impl Extensions =====
+
+impl Extensions {
+    pub(crate) fn new() -> Self {
+        Extensions {
+            inner: Arc::new(Mutex::new(Inner {
+                pending_send: VecDeque::new(),
+                pending_recv: VecDeque::new(),
+                send_task: None,
+                recv_task: None,
+                is_user_interested: false,
+                is_closed: false,
+            })),
+            taken: false,
+        }
+    }
+
+    /// Can only be called once. If called a second time, returns `None`.
+    pub(crate) fn take_user_extensions(&mut self) -> Option<UserExtensions> {
+        if self.taken {
+            return None;
+        }
+
+        self.taken = true;
+        self.inner.lock().unwrap().is_user_interested = true;
+        Some(UserExtensions(self.inner.clone()))
+    }
+
+    /// Queue a frame to be sent.
+    pub(crate) fn send_extension_frame(&mut self, frame: Extension) -> Result<(), ::Error> {
+        ensure_payload_size(&frame)?;
+        self.inner.lock().unwrap().pending_send.push_back(frame);
+        Ok(())
+    }
+
+    /// Process a received extension frame.
+    pub(crate) fn recv_extension_frame(&mut self, frame: Extension) {
+        let mut inner = self.inner.lock().unwrap();
+
+        if !inner.is_user_interested {
+            trace!("ignoring extension frame; kind={}", frame.kind());
+            return;
+        }
+
+        if inner.pending_recv.len() >= MAX_PENDING_RECV {
+            trace!("dropping extension frame, receive queue full; kind={}", frame.kind());
+            return;
+        }
+
+        inner.pending_recv.push_back(frame);
+
+        if let Some(task) = inner.recv_task.take() {
+            task.notify();
+        }
+    }
+
+    /// Send any pending extension frames.
+    pub(crate) fn send_pending_extensions<T, B>(&mut self, dst: &mut Codec<T, B>) -> Poll<(), io::Error>
+    where
+        T: AsyncWrite,
+        B: Buf,
+    {
+        let mut inner = self.inner.lock().unwrap();
+
+        while !inner.pending_send.is_empty() {
+            if !dst.poll_ready()?.is_ready() {
+                return Ok(Async::NotReady);
+            }
+
+            let frame = inner.pending_send.pop_front().unwrap();
+
+            dst.buffer(frame.into())
+                .expect("invalid extension frame");
+        }
+
+        if inner.is_user_interested {
+            inner.send_task = Some(task::current());
+        }
+
+        Ok(Async::Ready(()))
+    }
+}
+
+impl Drop for Extensions {
+    fn drop(&mut self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.is_closed = true;
+
+            if let Some(task) = inner.recv_task.take() {
+                task.notify();
+            }
+        }
+    }
+}
+
+// ===== impl UserExtensions =====
+
+impl UserExtensions {
+    pub(crate) fn send_extension_frame(&self, frame: Extension) -> Result<(), ::Error> {
+        ensure_payload_size(&frame)?;
+
+        let mut inner = self.0.lock().unwrap();
+
+        if inner.is_closed {
+            return Err(broken_pipe().into());
+        }
+
+        inner.pending_send.push_back(frame);
+
+        if let Some(task) = inner.send_task.take() {
+            task.notify();
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn poll_extension_frame(&self) -> Poll<Option<Extension>, ::Error> {
+        let mut inner = self.0.lock().unwrap();
+
+        if let Some(frame) = inner.pending_recv.pop_front() {
+            return Ok(Async::Ready(Some(frame)));
+        }
+
+        if inner.is_closed {
+            return Ok(Async::Ready(None));
+        }
+
+        inner.recv_task = Some(task::current());
+        Ok(Async::NotReady)
+    }
+}
+
+impl Drop for UserExtensions {
+    fn drop(&mut self) {
+        if let Ok(mut inner) = self.0.lock() {
+            inner.is_user_interested = false;
+            inner.pending_recv.clear();
+        }
+    }
+}
+
+/// Extension frames are never split, so their payload must fit in a single
+/// frame. The maximum frame size can't be set lower than its initial value,
+/// so a payload of that size is accepted by all peers.
+fn ensure_payload_size(frame: &Extension) -> Result<(), UserError> {
+    if frame.payload().len() > frame::DEFAULT_MAX_FRAME_SIZE as usize {
+        return Err(UserError::PayloadTooBig);
+    }
+
+    Ok(())
+}
+
+fn broken_pipe() -> io::Error {
+    io::ErrorKind::BrokenPipe.into()
+}
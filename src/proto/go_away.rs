@@ -1,7 +1,8 @@
 use codec::Codec;
 use frame::{self, Reason, StreamId};
+use observer::ConnectionEvent;
 
-use bytes::Buf;
+use bytes::{Buf, Bytes};
 use futures::{Async, Poll};
 use std::io;
 use tokio_io::AsyncWrite;
@@ -26,10 +27,6 @@ pub(super) struct GoAway {
 /// were a `frame::GoAway`, it might appear like we eventually wanted to
 /// serialize it. We **only** want to be able to look up these fields at a
 /// later time.
-///
-/// (Technically, `frame::GoAway` should gain an opaque_debug_data field as
-/// well, and we wouldn't want to save that here to accidentally dump in logs,
-/// or waste struct space.)
 #[derive(Debug)]
 struct GoingAway {
     /// Stores the highest stream ID of a GOAWAY that has been sent.
@@ -39,6 +36,10 @@ struct GoingAway {
 
     /// Records the error code of any GOAWAY frame sent.
     reason: Reason,
+
+    /// The opaque debug data of the GOAWAY frame sent, kept so that a
+    /// graceful shutdown can repeat it in the final GOAWAY.
+    debug_data: Bytes,
 }
 
 impl GoAway {
@@ -68,6 +69,7 @@ impl GoAway {
         self.going_away = Some(GoingAway {
             last_processed_id: f.last_stream_id(),
             reason: f.reason(),
+            debug_data: f.debug_data().clone(),
         });
         self.pending = Some(f);
     }
@@ -89,6 +91,27 @@ impl GoAway {
         self.go_away_now(f);
     }
 
+    /// Like `go_away_from_user`, but the connection is expected to continue
+    /// to run until idle.
+    pub fn go_away_gracefully_from_user(&mut self, f: frame::GoAway) {
+        self.is_user_initiated = true;
+        self.go_away(f);
+    }
+
+    /// Returns a GOAWAY frame lowering the last stream ID of the GOAWAY
+    /// we've already sent, keeping its reason and debug data.
+    pub fn lower_last_stream_id(&self, last_processed_id: StreamId) -> frame::GoAway {
+        let going_away = self.going_away
+            .as_ref()
+            .expect("no GOAWAY has been sent");
+
+        frame::GoAway::with_debug_data(
+            last_processed_id,
+            going_away.reason,
+            going_away.debug_data.clone(),
+        )
+    }
+
     /// Return if a GOAWAY has ever been scheduled.
     pub fn is_going_away(&self) -> bool {
         self.going_away.is_some()
@@ -133,6 +156,12 @@ impl GoAway {
             }
 
             let reason = frame.reason();
+
+            dst.observer().event(ConnectionEvent::GoAwaySent {
+                last_stream_id: ::StreamId::from_internal(frame.last_stream_id()),
+                reason,
+            });
+
             dst.buffer(frame.into())
                 .ok()
                 .expect("invalid GOAWAY frame");
@@ -1,5 +1,6 @@
 mod connection;
 mod error;
+mod extensions;
 mod go_away;
 mod peer;
 mod ping_pong;
@@ -8,6 +9,7 @@ mod streams;
 
 pub(crate) use self::connection::{Config, Connection};
 pub(crate) use self::error::Error;
+pub(crate) use self::extensions::UserExtensions;
 pub(crate) use self::peer::{Peer, Dyn as DynPeer};
 pub(crate) use self::ping_pong::UserPings;
 pub(crate) use self::streams::{StreamRef, OpaqueStreamRef, Streams};
@@ -15,6 +17,7 @@ pub(crate) use self::streams::{PollReset, Prioritized, Open};
 
 use codec::Codec;
 
+use self::extensions::Extensions;
 use self::go_away::GoAway;
 use self::ping_pong::PingPong;
 use self::settings::Settings;
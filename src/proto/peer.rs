@@ -1,6 +1,6 @@
 use codec::RecvError;
 use error::Reason;
-use frame::{Pseudo, StreamId};
+use frame::{HeaderValidation, Pseudo, StreamId};
 use proto::Open;
 
 use http::{HeaderMap, Request, Response};
@@ -17,7 +17,7 @@ pub(crate) trait Peer {
     fn is_server() -> bool;
 
     fn convert_poll_message(
-        pseudo: Pseudo, fields: HeaderMap, stream_id: StreamId
+        pseudo: Pseudo, fields: HeaderMap, stream_id: StreamId, validation: HeaderValidation
     ) -> Result<Self::Poll, RecvError>;
 
     fn is_local_init(id: StreamId) -> bool {
@@ -54,13 +54,13 @@ impl Dyn {
     }
 
     pub fn convert_poll_message(
-        &self, pseudo: Pseudo, fields: HeaderMap, stream_id: StreamId
+        &self, pseudo: Pseudo, fields: HeaderMap, stream_id: StreamId, validation: HeaderValidation
     ) -> Result<PollMessage, RecvError> {
         if self.is_server() {
-            ::server::Peer::convert_poll_message(pseudo, fields, stream_id)
+            ::server::Peer::convert_poll_message(pseudo, fields, stream_id, validation)
                 .map(PollMessage::Server)
         } else {
-            ::client::Peer::convert_poll_message(pseudo, fields, stream_id)
+            ::client::Peer::convert_poll_message(pseudo, fields, stream_id, validation)
                 .map(PollMessage::Client)
         }
     }
@@ -1,5 +1,6 @@
 use codec::RecvError;
 use frame;
+use observer::{ConnectionEvent, Observer};
 use proto::*;
 
 #[derive(Debug)]
@@ -17,10 +18,11 @@ impl Settings {
         }
     }
 
-    pub fn recv_settings(&mut self, frame: frame::Settings) {
+    pub fn recv_settings(&mut self, frame: frame::Settings, observer: &Observer) {
         if frame.is_ack() {
             debug!("received remote settings ack");
-        // TODO: handle acks
+            // TODO: handle acks
+            observer.event(ConnectionEvent::LocalSettingsApplied);
         } else {
             assert!(self.pending.is_none());
             self.pending = Some(frame);
@@ -61,6 +63,8 @@ impl Settings {
             }
 
             streams.apply_remote_settings(settings)?;
+
+            dst.observer().event(ConnectionEvent::RemoteSettingsApplied);
         }
 
         self.pending = None;
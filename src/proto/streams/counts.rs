@@ -20,6 +20,12 @@ pub(super) struct Counts {
     /// Current number of locally initiated streams
     num_recv_streams: usize,
 
+    /// Current number of locally initiated streams waiting to be opened
+    num_pending_open: usize,
+
+    /// Tasks waiting for locally initiated streams to become available
+    send_stream_tasks: Vec<Task>,
+
     /// Maximum number of pending locally reset streams
     max_reset_streams: usize,
 
@@ -36,6 +42,8 @@ impl Counts {
             num_send_streams: 0,
             max_recv_streams: config.remote_max_initiated.unwrap_or(usize::MAX),
             num_recv_streams: 0,
+            num_pending_open: 0,
+            send_stream_tasks: Vec::new(),
             max_reset_streams: config.local_reset_max,
             num_reset_streams: 0,
         }
@@ -88,6 +96,41 @@ impl Counts {
         stream.is_counted = true;
     }
 
+    /// Returns the number of locally initiated streams that may be opened
+    /// without waiting, taking into account streams already waiting to be
+    /// opened.
+    pub fn available_send_streams(&self) -> usize {
+        self.max_send_streams
+            .saturating_sub(self.num_send_streams + self.num_pending_open)
+    }
+
+    /// Increments the number of streams waiting to be opened.
+    pub fn inc_num_pending_open(&mut self) {
+        self.num_pending_open += 1;
+    }
+
+    /// Decrements the number of streams waiting to be opened.
+    pub fn dec_num_pending_open(&mut self) {
+        assert!(self.num_pending_open > 0);
+        self.num_pending_open -= 1;
+    }
+
+    /// Registers the current task to be notified once more locally initiated
+    /// streams may be opened.
+    pub fn wait_send_streams(&mut self) {
+        if !self.send_stream_tasks.iter().any(|task| task.will_notify_current()) {
+            self.send_stream_tasks.push(task::current());
+        }
+    }
+
+    /// Notifies all tasks waiting for locally initiated streams to become
+    /// available.
+    pub fn notify_send_streams(&mut self) {
+        for task in self.send_stream_tasks.drain(..) {
+            task.notify();
+        }
+    }
+
     /// Returns true if the number of pending reset streams can be incremented.
     pub fn can_inc_num_reset_streams(&self) -> bool {
         self.max_reset_streams > self.num_reset_streams
@@ -107,6 +150,7 @@ impl Counts {
     pub fn apply_remote_settings(&mut self, settings: &frame::Settings) {
         if let Some(val) = settings.max_concurrent_streams() {
             self.max_send_streams = val as usize;
+            self.notify_send_streams();
         }
     }
 
@@ -172,6 +216,7 @@ impl Counts {
             assert!(self.num_send_streams > 0);
             self.num_send_streams -= 1;
             stream.is_counted = false;
+            self.notify_send_streams();
         } else {
             assert!(self.num_recv_streams > 0);
             self.num_recv_streams -= 1;
@@ -134,7 +134,8 @@ impl FlowControl {
     /// Decrement the window size.
     ///
     /// This is called after receiving a SETTINGS frame with a lower
-    /// INITIAL_WINDOW_SIZE value.
+    /// INITIAL_WINDOW_SIZE value, and when sending padding, which uses window
+    /// without consuming any assigned capacity.
     pub fn dec_window(&mut self, sz: WindowSize) {
         trace!(
             "dec_window; sz={}; window={}, available={}",
@@ -24,7 +24,7 @@ use self::state::State;
 use self::store::Store;
 use self::stream::Stream;
 
-use frame::{StreamId, StreamIdOverflow};
+use frame::{HeaderValidation, Padding, StreamId, StreamIdOverflow};
 use proto::*;
 
 use bytes::Bytes;
@@ -44,6 +44,9 @@ pub struct Config {
     /// The stream ID to start the next local stream with
     pub local_next_stream_id: StreamId,
 
+    /// The largest stream ID that may be used for a local stream
+    pub local_max_stream_id: StreamId,
+
     /// If the local peer is willing to receive push promises
     pub local_push_enabled: bool,
 
@@ -58,4 +61,13 @@ pub struct Config {
 
     /// Maximum number of remote initiated streams
     pub remote_max_initiated: Option<usize>,
+
+    /// Padding of frames sent on streams that don't override it
+    pub local_padding: Padding,
+
+    /// If small DATA frames queued on a stream are merged when sent
+    pub local_coalesce_data: bool,
+
+    /// How strictly the header fields of messages are validated
+    pub header_validation: HeaderValidation,
 }
@@ -1,14 +1,16 @@
 use super::*;
 use super::store::Resolve;
 
-use frame::{Reason, StreamId};
+use frame::{Padding, Reason, StreamId};
 
 use codec::UserError;
 use codec::UserError::*;
 
 use bytes::buf::Take;
+use iovec::IoVec;
 
-use std::{cmp, fmt, mem};
+use std::{cmp, fmt};
+use std::collections::VecDeque;
 use std::io;
 
 /// # Warning
@@ -49,16 +51,28 @@ pub(super) struct Prioritize {
     /// Stream ID of the last stream opened.
     last_opened_id: StreamId,
 
-    /// What `DATA` frame is currently being sent in the codec.
-    in_flight_data_frame: InFlightData,
+    /// The `DATA` frames currently being sent in the codec, in the order they
+    /// were buffered.
+    in_flight_data_frames: VecDeque<InFlightData>,
+
+    /// Padding of frames sent on streams that don't override it
+    padding: Padding,
+
+    /// If DATA frames queued on a stream are merged when popped
+    coalesce_data: bool,
 }
 
 #[derive(Debug, Eq, PartialEq)]
 enum InFlightData {
-    /// There is no `DATA` frame in flight.
-    Nothing,
     /// There is a `DATA` frame in flight belonging to the given stream.
-    DataFrame(store::Key),
+    ///
+    /// A partial frame only holds the start of the buffer it was popped from.
+    /// The rest of the buffer is pushed back to the stream once the frame is
+    /// written, so nothing else may be sent on the stream until then.
+    DataFrame {
+        stream: store::Key,
+        is_partial: bool,
+    },
     /// There was a `DATA` frame, but the stream's queue was since cleared.
     Drop,
 }
@@ -67,6 +81,9 @@ pub(crate) struct Prioritized<B> {
     // The buffer
     inner: Take<B>,
 
+    // The buffers of the chunks merged into the frame, sent after `inner`
+    coalesced: VecDeque<B>,
+
     end_of_stream: bool,
 
     // The stream that this is associated with
@@ -93,7 +110,9 @@ impl Prioritize {
             pending_open: store::Queue::new(),
             flow: flow,
             last_opened_id: StreamId::ZERO,
-            in_flight_data_frame: InFlightData::Nothing,
+            in_flight_data_frames: VecDeque::new(),
+            padding: config.local_padding,
+            coalesce_data: config.local_coalesce_data,
         }
     }
 
@@ -509,9 +528,11 @@ impl Prioritize {
                 Some(frame) => {
                     trace!("writing frame={:?}", frame);
 
-                    debug_assert_eq!(self.in_flight_data_frame, InFlightData::Nothing);
                     if let Frame::Data(ref frame) = frame {
-                        self.in_flight_data_frame = InFlightData::DataFrame(frame.payload().stream);
+                        self.in_flight_data_frames.push_back(InFlightData::DataFrame {
+                            stream: frame.payload().stream,
+                            is_partial: frame.payload().is_partial(),
+                        });
                     }
                     dst.buffer(frame).ok().expect("invalid frame");
 
@@ -522,8 +543,9 @@ impl Prioritize {
                     self.reclaim_frame(buffer, store, dst);
                 },
                 None => {
-                    // Try to flush the codec.
-                    try_ready!(dst.flush());
+                    // Try to flush the codec, as far as the flush policy
+                    // allows.
+                    try_ready!(dst.flush_idle());
 
                     // This might release a data frame...
                     if !self.reclaim_frame(buffer, store, dst) {
@@ -537,9 +559,9 @@ impl Prioritize {
         }
     }
 
-    /// Tries to reclaim a pending data frame from the codec.
+    /// Tries to reclaim the data frames that the codec has written.
     ///
-    /// Returns true if a frame was reclaimed.
+    /// Returns true if a frame was pushed back to its stream.
     ///
     /// When a data frame is written to the codec, it may not be written in its
     /// entirety (large chunks are split up into potentially many data frames).
@@ -555,8 +577,10 @@ impl Prioritize {
     {
         trace!("try reclaim frame");
 
-        // First check if there are any data chunks to take back
-        if let Some(frame) = dst.take_last_data_frame() {
+        let mut reclaimed = false;
+
+        // Check if there are any data chunks to take back
+        while let Some(frame) = dst.take_written_data_frame() {
             trace!(
                 "  -> reclaimed; frame={:?}; sz={}",
                 frame,
@@ -566,14 +590,14 @@ impl Prioritize {
             let mut eos = false;
             let key = frame.payload().stream;
 
-            match mem::replace(&mut self.in_flight_data_frame, InFlightData::Nothing) {
-                InFlightData::Nothing => panic!("wasn't expecting a frame to reclaim"),
-                InFlightData::Drop => {
+            match self.in_flight_data_frames.pop_front() {
+                None => panic!("wasn't expecting a frame to reclaim"),
+                Some(InFlightData::Drop) => {
                     trace!("not reclaiming frame for cancelled stream");
-                    return false;
+                    continue;
                 }
-                InFlightData::DataFrame(k) => {
-                    debug_assert_eq!(k, key);
+                Some(InFlightData::DataFrame { stream, .. }) => {
+                    debug_assert_eq!(stream, key);
                 }
             }
 
@@ -590,13 +614,26 @@ impl Prioritize {
                     frame.set_end_stream(true);
                 }
 
+                // Padding was sent with the part of the frame that has been
+                // written, the rest is padded again when it is next popped.
+                frame.unset_padding();
+
                 self.push_back_frame(frame.into(), buffer, &mut stream);
 
-                return true;
+                reclaimed = true;
             }
         }
 
-        false
+        reclaimed
+    }
+
+    /// Returns true if part of the stream's buffered data is being written,
+    /// and the rest of it is yet to be pushed back to the stream.
+    fn is_reclaim_pending(&self, key: store::Key) -> bool {
+        self.in_flight_data_frames.iter().any(|f| *f == InFlightData::DataFrame {
+            stream: key,
+            is_partial: true,
+        })
     }
 
     /// Push the frame to the front of the stream's deque, scheduling the
@@ -626,10 +663,15 @@ impl Prioritize {
 
         stream.buffered_send_data = 0;
         stream.requested_send_capacity = 0;
-        if let InFlightData::DataFrame(key) = self.in_flight_data_frame {
-            if stream.key() == key {
+        for in_flight in self.in_flight_data_frames.iter_mut() {
+            let is_stream = match *in_flight {
+                InFlightData::DataFrame { stream: key, .. } => stream.key() == key,
+                InFlightData::Drop => false,
+            };
+
+            if is_stream {
                 // This stream could get cleaned up now - don't allow the buffered frame to get reclaimed.
-                self.in_flight_data_frame = InFlightData::Drop;
+                *in_flight = InFlightData::Drop;
             }
         }
     }
@@ -643,6 +685,7 @@ impl Prioritize {
 
     pub fn clear_pending_open(&mut self, store: &mut Store, counts: &mut Counts) {
         while let Some(stream) = self.pending_open.pop(store) {
+            counts.dec_num_pending_open();
             let is_pending_reset = stream.is_pending_reset_expiration();
             counts.transition_after(stream, is_pending_reset);
         }
@@ -676,6 +719,14 @@ impl Prioritize {
                     trace!(" --> stream={:?}; is_pending_reset={:?};",
                         stream.id, is_pending_reset);
 
+                    // The stream's next frames can only be sent after the
+                    // data in flight. The stream is scheduled again when that
+                    // data is pushed back to it.
+                    if self.is_reclaim_pending(stream.key()) {
+                        trace!(" --> data in flight; stream={:?}", stream.id);
+                        continue;
+                    }
+
                     let frame = match stream.pending_send.pop_front(buffer) {
                         Some(Frame::Data(mut frame)) => {
                             // Get the amount of capacity remaining for stream's
@@ -724,7 +775,46 @@ impl Prioritize {
                             let len = cmp::min(sz, max_len);
 
                             // Only send up to the stream's window capacity
-                            let len = cmp::min(len, stream_capacity.as_size() as usize) as WindowSize;
+                            let mut len = cmp::min(len, stream_capacity.as_size() as usize) as WindowSize;
+
+                            // Merge the chunks queued after this one, as long
+                            // as they fit whole in the frame.
+                            let mut coalesced = VecDeque::new();
+
+                            if self.coalesce_data && len as usize == sz && !frame.is_end_stream() {
+                                let max = cmp::min(max_len, stream_capacity.as_size() as usize);
+
+                                let next_len = |frame: &Frame<B>| match *frame {
+                                    Frame::Data(ref next) => Some(next.payload().remaining()),
+                                    _ => None,
+                                };
+
+                                while let Some(next_sz) = stream.pending_send.peek_front(buffer).and_then(next_len) {
+                                    if len as usize + next_sz > max {
+                                        break;
+                                    }
+
+                                    let next = match stream.pending_send.pop_front(buffer) {
+                                        Some(Frame::Data(next)) => next,
+                                        _ => unreachable!(),
+                                    };
+
+                                    trace!(" --> coalescing data frame; sz={}; eos={:?}",
+                                        next_sz, next.is_end_stream());
+
+                                    len += next_sz as WindowSize;
+
+                                    if next.is_end_stream() {
+                                        frame.set_end_stream(true);
+                                    }
+
+                                    coalesced.push_back(next.into_payload());
+
+                                    if frame.is_end_stream() {
+                                        break;
+                                    }
+                                }
+                            }
 
                             // There *must* be be enough connection level
                             // capacity at this point.
@@ -749,6 +839,32 @@ impl Prioritize {
                             trace!(" -- updating connection flow --");
                             self.flow.send_data(len);
 
+                            // Padding counts against flow control, but may
+                            // only use window that is neither assigned to the
+                            // stream nor to any other stream, so that it never
+                            // holds data back.
+                            let padding = stream.send_padding.unwrap_or(self.padding);
+                            let pad_len = if padding.is_none() {
+                                None
+                            } else {
+                                let unassigned = cmp::min(
+                                    stream.send_flow.window_size()
+                                        .saturating_sub(stream.send_flow.available().as_size()),
+                                    self.flow.available().as_size(),
+                                );
+                                let max = cmp::min(unassigned as usize, max_len - len as usize);
+
+                                padding.pad_len(len as usize, max)
+                            };
+
+                            if let Some(pad_len) = pad_len {
+                                let sz = pad_len as WindowSize + 1;
+
+                                trace!(" -- padding data frame; pad_len={} --", pad_len);
+                                stream.send_flow.dec_window(sz);
+                                self.flow.send_data(sz);
+                            }
+
                             // Wrap the frame's data payload to ensure that the
                             // correct amount of data gets written.
 
@@ -759,20 +875,41 @@ impl Prioritize {
                                 frame.set_end_stream(false);
                             }
 
-                            Frame::Data(frame.map(|buf| {
+                            let mut frame = frame.map(|buf| {
                                 Prioritized {
                                     inner: buf.take(len),
+                                    coalesced,
                                     end_of_stream: eos,
                                     stream: stream.key(),
                                 }
-                            }))
+                            });
+
+                            if let Some(pad_len) = pad_len {
+                                frame.set_padding(pad_len);
+                            }
+
+                            Frame::Data(frame)
+                        },
+                        Some(frame) => {
+                            let mut frame = frame.map(|_|
+                                unreachable!(
+                                    "Frame::map closure will only be called \
+                                     on DATA frames."
+                                 )
+                            );
+
+                            // Header blocks are not flow controlled, so their
+                            // padding is left entirely to the codec.
+                            let padding = stream.send_padding.unwrap_or(self.padding);
+
+                            match frame {
+                                Frame::Headers(ref mut frame) => frame.set_padding(padding),
+                                Frame::PushPromise(ref mut frame) => frame.set_padding(padding),
+                                _ => {},
+                            }
+
+                            frame
                         },
-                        Some(frame) => frame.map(|_|
-                            unreachable!(
-                                "Frame::map closure will only be called \
-                                 on DATA frames."
-                             )
-                        ),
                         None => {
                             if let Some(reason) = stream.state.get_scheduled_reset() {
                                 stream.state.set_reset(reason);
@@ -825,6 +962,7 @@ impl Prioritize {
             if let Some(mut stream) = self.pending_open.pop(store) {
                 trace!("schedule_pending_open; stream={:?}", stream.id);
 
+                counts.dec_num_pending_open();
                 counts.inc_num_send_streams(&mut stream);
                 self.pending_send.push(&mut stream);
                 stream.notify_send();
@@ -837,20 +975,91 @@ impl Prioritize {
 
 // ===== impl Prioritized =====
 
+impl<B: Buf> Prioritized<B> {
+    /// Returns true if the frame only holds the start of the stream's buffer.
+    fn is_partial(&self) -> bool {
+        self.inner.get_ref().remaining() > self.inner.limit()
+    }
+}
+
 impl<B> Buf for Prioritized<B>
 where
     B: Buf,
 {
     fn remaining(&self) -> usize {
-        self.inner.remaining()
+        self.coalesced.iter()
+            .fold(self.inner.remaining(), |n, buf| n + buf.remaining())
     }
 
     fn bytes(&self) -> &[u8] {
-        self.inner.bytes()
+        if self.inner.has_remaining() {
+            return self.inner.bytes();
+        }
+
+        self.coalesced.iter()
+            .map(|buf| buf.bytes())
+            .find(|bytes| !bytes.is_empty())
+            .unwrap_or(&[])
+    }
+
+    fn bytes_vec<'a>(&'a self, dst: &mut [&'a IoVec]) -> usize {
+        // `Take` only knows of `bytes`, so limit the buffer's own vectors.
+        let mut n = self.inner.get_ref().bytes_vec(dst);
+        let mut rem = self.inner.limit();
+
+        for (i, slot) in dst.iter_mut().enumerate().take(n) {
+            if rem == 0 {
+                n = i;
+                break;
+            }
+
+            let v: &'a IoVec = *slot;
+
+            if v.len() > rem {
+                *slot = v[..rem].into();
+                n = i + 1;
+                break;
+            }
+
+            rem -= v.len();
+        }
+
+        // The merged chunks may only follow once `inner` is fully covered.
+        let mut covered = dst[..n].iter().fold(0, |len, v| len + v.len());
+        let mut expected = self.inner.remaining();
+
+        for buf in &self.coalesced {
+            if covered < expected || n == dst.len() {
+                break;
+            }
+
+            let m = buf.bytes_vec(&mut dst[n..]);
+
+            covered = dst[n..n + m].iter().fold(0, |len, v| len + v.len());
+            expected = buf.remaining();
+            n += m;
+        }
+
+        n
     }
 
     fn advance(&mut self, cnt: usize) {
-        self.inner.advance(cnt)
+        let n = cmp::min(cnt, self.inner.remaining());
+        self.inner.advance(n);
+
+        let mut cnt = cnt - n;
+
+        while cnt > 0 {
+            let buf = self.coalesced.front_mut().expect("advanced past end of buffer");
+            let n = cmp::min(cnt, buf.remaining());
+
+            buf.advance(n);
+            cnt -= n;
+
+            if !buf.has_remaining() {
+                self.coalesced.pop_front();
+            }
+        }
     }
 }
 
@@ -858,6 +1067,7 @@ impl<B: Buf> fmt::Debug for Prioritized<B> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.debug_struct("Prioritized")
             .field("remaining", &self.inner.get_ref().remaining())
+            .field("coalesced", &self.coalesced.len())
             .field("end_of_stream", &self.end_of_stream)
             .field("stream", &self.stream)
             .finish()
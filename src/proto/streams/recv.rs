@@ -1,7 +1,7 @@
 use super::*;
 use {frame, proto};
 use codec::{RecvError, UserError};
-use frame::{Reason, DEFAULT_INITIAL_WINDOW_SIZE};
+use frame::{HeaderValidation, Reason, DEFAULT_INITIAL_WINDOW_SIZE};
 
 use http::{HeaderMap, Response, Request, Method};
 
@@ -54,6 +54,9 @@ pub(super) struct Recv {
 
     /// If push promises are allowed to be recevied.
     is_push_enabled: bool,
+
+    /// How strictly received requests are validated
+    header_validation: HeaderValidation,
 }
 
 #[derive(Debug)]
@@ -107,6 +110,7 @@ impl Recv {
             buffer: Buffer::new(),
             refused: None,
             is_push_enabled: config.local_push_enabled,
+            header_validation: config.header_validation,
         }
     }
 
@@ -222,8 +226,18 @@ impl Recv {
         }
 
         let stream_id = frame.stream_id();
-        let (pseudo, fields) = frame.into_parts();
-        let message = counts.peer().convert_poll_message(pseudo, fields, stream_id)?;
+        let (pseudo, mut fields) = frame.into_parts();
+        join_cookies(&mut fields);
+        let message = counts.peer()
+            .convert_poll_message(pseudo, fields, stream_id, self.header_validation)?;
+
+        // The response to a HEAD request has no body, whatever its
+        // content-length says.
+        if let peer::PollMessage::Server(ref request) = message {
+            if *request.method() == Method::HEAD {
+                stream.send_content_length = super::stream::ContentLength::Head;
+            }
+        }
 
         // Push the frame onto the stream's recv buffer
         stream
@@ -489,7 +503,8 @@ impl Recv {
         frame: frame::Data,
         stream: &mut store::Ptr,
     ) -> Result<(), RecvError> {
-        let sz = frame.payload().len();
+        // Padding counts against flow control too.
+        let sz = frame.flow_controlled_len();
 
         // This should have been enforced at the codec::FramedRead layer, so
         // this is just a sanity check.
@@ -581,6 +596,15 @@ impl Recv {
         // Track the data as in-flight
         stream.in_flight_recv_data += sz;
 
+        // The padding is never handed to the user, who therefore can't
+        // release its capacity, so it is released right away.
+        let padding_len = frame.padding_len() as WindowSize;
+
+        if padding_len > 0 {
+            self.release_capacity(padding_len, stream, &mut None)
+                .expect("padding is in flight");
+        }
+
         let event = Event::Data(frame.into_payload());
 
         // Push the frame onto the recv buffer
@@ -655,8 +679,9 @@ impl Recv {
 
         let promised_id = frame.promised_id();
         use http::header;
-        let (pseudo, fields) = frame.into_parts();
-        let req = ::server::Peer::convert_poll_message(pseudo, fields, promised_id)?;
+        let (pseudo, mut fields) = frame.into_parts();
+        join_cookies(&mut fields);
+        let req = ::server::Peer::convert_poll_message(pseudo, fields, promised_id, self.header_validation)?;
         // The spec has some requirements for promised request headers
         // [https://httpwg.org/specs/rfc7540.html#PushRequests]
 
@@ -1087,7 +1112,47 @@ impl<T> From<RecvError> for RecvHeaderBlockError<T> {
 
 // ===== util =====
 
-fn parse_u64(src: &[u8]) -> Result<u64, ()> {
+/// Joins the values of the `cookie` header into a single value, as split
+/// cookies must be before being handed off to HTTP/1.1 semantics.
+///
+/// See [RFC 7540 §8.1.2.5](http://httpwg.org/specs/rfc7540.html#CompressCookie).
+fn join_cookies(fields: &mut HeaderMap) {
+    use bytes::BytesMut;
+    use http::header::{self, HeaderValue};
+
+    let joined = {
+        let mut values = fields.get_all(header::COOKIE).iter();
+
+        let first = match values.next() {
+            Some(value) => value,
+            None => return,
+        };
+
+        let mut rest = values.peekable();
+
+        if rest.peek().is_none() {
+            return;
+        }
+
+        let mut sensitive = first.is_sensitive();
+        let mut joined = BytesMut::from(first.as_bytes());
+
+        for value in rest {
+            sensitive |= value.is_sensitive();
+            joined.extend_from_slice(b"; ");
+            joined.extend_from_slice(value.as_bytes());
+        }
+
+        let mut joined = HeaderValue::from_shared(joined.freeze())
+            .expect("joined cookies are a valid header value");
+        joined.set_sensitive(sensitive);
+        joined
+    };
+
+    fields.insert(header::COOKIE, joined);
+}
+
+pub(super) fn parse_u64(src: &[u8]) -> Result<u64, ()> {
     if src.len() > 19 {
         // At danger for overflow...
         return Err(());
@@ -1,5 +1,7 @@
 use codec::{RecvError, UserError};
-use frame::{self, Reason};
+use frame::{self, HeaderValidation, Reason};
+use super::recv::parse_u64;
+use super::stream::ContentLength;
 use super::{
     store, Buffer, Codec, Config, Counts, Frame, Prioritize,
     Prioritized, Store, Stream, StreamId, StreamIdOverflow, WindowSize,
@@ -19,11 +21,20 @@ pub(super) struct Send {
     /// Stream identifier to use for next initialized stream.
     next_stream_id: Result<StreamId, StreamIdOverflow>,
 
+    /// The largest stream identifier that may be used for a new stream.
+    max_stream_id: StreamId,
+
     /// Initial window size of locally initiated streams
     init_window_sz: WindowSize,
 
+    /// The largest header list the peer accepts, if it advertised a limit.
+    max_header_list_size: Option<usize>,
+
     /// Prioritization layer
     prioritize: Prioritize,
+
+    /// How strictly the header fields of sent messages are validated.
+    header_validation: HeaderValidation,
 }
 
 /// A value to detect which public API has called `poll_reset`.
@@ -38,8 +49,11 @@ impl Send {
     pub fn new(config: &Config) -> Self {
         Send {
             init_window_sz: config.remote_init_window_sz,
+            max_header_list_size: None,
             next_stream_id: Ok(config.local_next_stream_id),
+            max_stream_id: config.local_max_stream_id,
             prioritize: Prioritize::new(config),
+            header_validation: config.header_validation,
         }
     }
 
@@ -56,7 +70,7 @@ impl Send {
 
     pub fn send_headers<B>(
         &mut self,
-        frame: frame::Headers,
+        mut frame: frame::Headers,
         buffer: &mut Buffer<Frame<B>>,
         stream: &mut store::Ptr,
         counts: &mut Counts,
@@ -69,28 +83,51 @@ impl Send {
         );
 
         // 8.1.2.2. Connection-Specific Header Fields
-        if frame.fields().contains_key(http::header::CONNECTION)
-            || frame.fields().contains_key(http::header::TRANSFER_ENCODING)
-            || frame.fields().contains_key(http::header::UPGRADE)
-            || frame.fields().contains_key("keep-alive")
-            || frame.fields().contains_key("proxy-connection")
-        {
-            debug!("illegal connection-specific headers found");
+        if self.header_validation.is_lenient() {
+            frame::strip_connection_specific(frame.fields_mut());
+        } else if let Some(name) = frame::find_connection_specific(frame.fields()) {
+            debug!("illegal connection-specific header found; name={:?}", name);
             return Err(UserError::MalformedHeaders);
-        } else if let Some(te) = frame.fields().get(http::header::TE) {
-            if te != "trailers" {
-                debug!("illegal connection-specific headers found");
-                return Err(UserError::MalformedHeaders);
-
-            }
         }
 
         if frame.has_too_big_field() {
             return Err(UserError::HeaderTooBig);
         }
 
+        self.ensure_header_list_size(&frame)?;
+
         let end_stream = frame.is_end_stream();
 
+        // The content-length of a 304 response describes the body the
+        // request would have had, not this one.
+        let is_not_modified = frame.pseudo().status == Some(http::StatusCode::NOT_MODIFIED);
+
+        if !stream.send_content_length.is_head() && !is_not_modified {
+            if let Some(content_length) = frame.fields().get(http::header::CONTENT_LENGTH) {
+                let content_length = match parse_u64(content_length.as_bytes()) {
+                    Ok(v) => v,
+                    Err(()) => {
+                        debug!("could not parse content-length; value={:?}", content_length);
+                        return Err(UserError::MalformedHeaders);
+                    },
+                };
+
+                if end_stream && content_length > 0 {
+                    debug!("stream ended before content-length; stream={:?}", stream.id);
+
+                    // A response is sent on a stream the peer already opened,
+                    // which must not be left waiting for one.
+                    if !stream.state.is_idle() {
+                        self.send_reset(Reason::PROTOCOL_ERROR, buffer, stream, counts, task);
+                    }
+
+                    return Err(UserError::ContentLengthIncomplete);
+                }
+
+                stream.send_content_length = ContentLength::Remaining(content_length);
+            }
+        }
+
         // Update the state
         stream.state.send_open(end_stream)?;
 
@@ -98,6 +135,7 @@ impl Send {
             if counts.can_inc_num_send_streams() {
                 counts.inc_num_send_streams(stream);
             } else {
+                counts.inc_num_pending_open();
                 self.prioritize.queue_open(stream);
             }
         }
@@ -198,6 +236,20 @@ impl Send {
     ) -> Result<(), UserError>
         where B: Buf,
     {
+        if stream.state.is_send_streaming() {
+            let sz = frame.payload().remaining();
+
+            if sz > 0 && stream.send_content_length.dec(sz).is_err() {
+                return Err(UserError::ContentLengthExceeded);
+            }
+
+            if frame.is_end_stream() && stream.send_content_length.ensure_zero().is_err() {
+                debug!("stream ended before content-length; stream={:?}", stream.id);
+                self.send_reset(Reason::PROTOCOL_ERROR, buffer, stream, counts, task);
+                return Err(UserError::ContentLengthIncomplete);
+            }
+        }
+
         self.prioritize.send_data(frame, buffer, stream, counts, task)
     }
 
@@ -214,10 +266,18 @@ impl Send {
             return Err(UserError::UnexpectedFrameType);
         }
 
+        if stream.send_content_length.ensure_zero().is_err() {
+            debug!("stream ended before content-length; stream={:?}", stream.id);
+            self.send_reset(Reason::PROTOCOL_ERROR, buffer, stream, counts, task);
+            return Err(UserError::ContentLengthIncomplete);
+        }
+
         if frame.has_too_big_field() {
             return Err(UserError::HeaderTooBig);
         }
 
+        self.ensure_header_list_size(&frame)?;
+
         stream.state.send_close();
 
         trace!("send_trailers -- queuing; frame={:?}", frame);
@@ -346,6 +406,10 @@ impl Send {
         counts: &mut Counts,
         task: &mut Option<Task>,
     ) -> Result<(), RecvError> {
+        if let Some(val) = settings.max_header_list_size() {
+            self.max_header_list_size = Some(val as usize);
+        }
+
         // Applies an update to the remote endpoint's initial window size.
         //
         // Per RFC 7540 §6.9.2:
@@ -443,8 +507,38 @@ impl Send {
         Ok(())
     }
 
+    /// Headers over the peer's limit would only get the stream reset, so
+    /// refuse to send them.
+    fn ensure_header_list_size(&self, frame: &frame::Headers) -> Result<(), UserError> {
+        if let Some(max) = self.max_header_list_size {
+            let size = frame.header_list_size();
+
+            if size > max {
+                debug!("header list too big; size={}; max={}", size, max);
+                return Err(UserError::HeaderListTooBig);
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn ensure_next_stream_id(&self) -> Result<StreamId, UserError> {
-        self.next_stream_id.map_err(|_| UserError::OverflowedStreamId)
+        match self.next_stream_id {
+            Ok(id) if id <= self.max_stream_id => Ok(id),
+            _ => Err(UserError::OverflowedStreamId),
+        }
+    }
+
+    /// Returns the number of streams that may still be opened before running
+    /// out of stream identifiers.
+    pub fn num_remaining_stream_ids(&self) -> usize {
+        match self.next_stream_id {
+            Ok(id) if id <= self.max_stream_id => {
+                let (next, max): (u32, u32) = (id.into(), self.max_stream_id.into());
+                ((max - next) / 2 + 1) as usize
+            },
+            _ => 0,
+        }
     }
 
     pub fn may_have_created_stream(&self, id: StreamId) -> bool {
@@ -2,7 +2,7 @@ use std::io;
 
 use codec::{RecvError, UserError};
 use codec::UserError::*;
-use frame::Reason;
+use frame::{self, Reason};
 use proto::{self, PollReset};
 
 use self::Inner::*;
@@ -53,7 +53,7 @@ pub struct State {
     inner: Inner,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 enum Inner {
     Idle,
     // TODO: these states shouldn't count against concurrency limits:
@@ -71,11 +71,14 @@ enum Peer {
     Streaming,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 enum Cause {
     EndStream,
     Proto(Reason),
     LocallyReset(Reason),
+    /// The peer sent a GOAWAY frame with a last stream ID lower than this
+    /// stream, so the stream was never processed.
+    GoAway(frame::GoAway, Reason),
     Io,
 
     /// This indicates to the connection that a reset frame must be sent out
@@ -172,7 +175,7 @@ impl State {
             } else {
                 HalfClosedLocal(remote)
             },
-            state => {
+            ref state => {
                 // All other transitions result in a protocol error
                 proto_err!(conn: "recv_open: in unexpected state {:?}", state);
                 return Err(RecvError::Connection(Reason::PROTOCOL_ERROR));
@@ -189,7 +192,7 @@ impl State {
                 self.inner = ReservedRemote;
                 Ok(())
             },
-            state => {
+            ref state => {
                 proto_err!(conn: "reserve_remote: in unexpected state {:?}", state);
                 Err(RecvError::Connection(Reason::PROTOCOL_ERROR))
             }
@@ -212,7 +215,7 @@ impl State {
                 self.inner = Closed(Cause::EndStream);
                 Ok(())
             },
-            state => {
+            ref state => {
                 proto_err!(conn: "recv_close: in unexpected state {:?}", state);
                 Err(RecvError::Connection(Reason::PROTOCOL_ERROR))
             }
@@ -243,7 +246,7 @@ impl State {
             // In either of these cases, we want to overwrite the stream's
             // previous state with the received RST_STREAM, so that the queue
             // will be cleared by `Prioritize::pop_frame`.
-            state => {
+            ref state => {
                 trace!(
                     "recv_reset; reason={:?}; state={:?}; queued={:?}",
                     reason, state, queued
@@ -264,6 +267,11 @@ impl State {
                 trace!("recv_err; err={:?}", err);
                 self.inner = Closed(match *err {
                     Proto(reason) => Cause::LocallyReset(reason),
+                    GoAway(ref frame, reason) |
+                    Unprocessed(Some(ref frame), reason) => {
+                        Cause::GoAway(frame.clone(), reason)
+                    },
+                    Unprocessed(None, reason) => Cause::Proto(reason),
                     Io(..) => Cause::Io,
                 });
             },
@@ -273,7 +281,7 @@ impl State {
     pub fn recv_eof(&mut self) {
         match self.inner {
             Closed(..) => {},
-            s => {
+            ref s => {
                 trace!("recv_eof; state={:?}", s);
                 self.inner = Closed(Cause::Io);
             }
@@ -294,7 +302,7 @@ impl State {
                 trace!("send_close: HalfClosedRemote => Closed");
                 self.inner = Closed(Cause::EndStream);
             },
-            state => panic!("send_close: unexpected state {:?}", state),
+            ref state => panic!("send_close: unexpected state {:?}", state),
         }
     }
 
@@ -326,6 +334,7 @@ impl State {
     pub fn is_local_reset(&self) -> bool {
         match self.inner {
             Closed(Cause::LocallyReset(_)) => true,
+            Closed(Cause::GoAway(..)) => true,
             Closed(Cause::Scheduled(..)) => true,
             _ => false,
         }
@@ -407,9 +416,16 @@ impl State {
     pub fn ensure_recv_open(&self) -> Result<bool, proto::Error> {
         // TODO: Is this correct?
         match self.inner {
+            // The peer refused the stream, so it was never processed.
+            Closed(Cause::Proto(Reason::REFUSED_STREAM)) => {
+                Err(proto::Error::Unprocessed(None, Reason::REFUSED_STREAM))
+            },
             Closed(Cause::Proto(reason)) |
             Closed(Cause::LocallyReset(reason)) |
             Closed(Cause::Scheduled(reason)) => Err(proto::Error::Proto(reason)),
+            Closed(Cause::GoAway(ref frame, reason)) => {
+                Err(proto::Error::Unprocessed(Some(frame.clone()), reason))
+            },
             Closed(Cause::Io) => Err(proto::Error::Io(io::ErrorKind::BrokenPipe.into())),
             Closed(Cause::EndStream) |
             HalfClosedRemote(..) => Ok(false),
@@ -422,7 +438,8 @@ impl State {
         match self.inner {
             Closed(Cause::Proto(reason)) |
             Closed(Cause::LocallyReset(reason)) |
-            Closed(Cause::Scheduled(reason)) => Ok(Some(reason)),
+            Closed(Cause::Scheduled(reason)) |
+            Closed(Cause::GoAway(_, reason)) => Ok(Some(reason)),
             Closed(Cause::Io) => Err(proto::Error::Io(io::ErrorKind::BrokenPipe.into()).into()),
             Open { local: Streaming, .. } |
             HalfClosedRemote(Streaming) => match mode {
@@ -68,6 +68,9 @@ pub(super) struct Stream {
     /// Set to true when the stream is pending to be opened
     pub is_pending_open: bool,
 
+    /// Padding of frames sent on this stream, overriding the connection's
+    pub send_padding: Option<Padding>,
+
     // ===== Fields related to receiving =====
     /// Next node in the accept linked list
     pub next_pending_accept: Option<store::Key>,
@@ -103,6 +106,9 @@ pub(super) struct Stream {
 
     /// Validate content-length headers
     pub content_length: ContentLength,
+
+    /// Validate the content-length of sent messages
+    pub send_content_length: ContentLength,
 }
 
 /// State related to validating a stream's content-length
@@ -170,6 +176,7 @@ impl Stream {
             send_capacity_inc: false,
             is_pending_open: false,
             next_open: None,
+            send_padding: None,
 
             // ===== Fields related to receiving =====
             next_pending_accept: None,
@@ -184,6 +191,7 @@ impl Stream {
             recv_task: None,
             pending_push_promises: store::Queue::new(),
             content_length: ContentLength::Omitted,
+            send_content_length: ContentLength::Omitted,
         }
     }
 
@@ -258,24 +266,11 @@ impl Stream {
 
     /// Returns `Err` when the decrement cannot be completed due to overflow.
     pub fn dec_content_length(&mut self, len: usize) -> Result<(), ()> {
-        match self.content_length {
-            ContentLength::Remaining(ref mut rem) => match rem.checked_sub(len as u64) {
-                Some(val) => *rem = val,
-                None => return Err(()),
-            },
-            ContentLength::Head => return Err(()),
-            _ => {},
-        }
-
-        Ok(())
+        self.content_length.dec(len)
     }
 
     pub fn ensure_content_length_zero(&self) -> Result<(), ()> {
-        match self.content_length {
-            ContentLength::Remaining(0) => Ok(()),
-            ContentLength::Remaining(_) => Err(()),
-            _ => Ok(()),
-        }
+        self.content_length.ensure_zero()
     }
 
     pub fn notify_send(&mut self) {
@@ -450,4 +445,26 @@ impl ContentLength {
             _ => false,
         }
     }
+
+    /// Returns `Err` when the decrement cannot be completed due to overflow.
+    pub fn dec(&mut self, len: usize) -> Result<(), ()> {
+        match *self {
+            ContentLength::Remaining(ref mut rem) => match rem.checked_sub(len as u64) {
+                Some(val) => *rem = val,
+                None => return Err(()),
+            },
+            ContentLength::Head => return Err(()),
+            _ => {},
+        }
+
+        Ok(())
+    }
+
+    pub fn ensure_zero(&self) -> Result<(), ()> {
+        match *self {
+            ContentLength::Remaining(0) => Ok(()),
+            ContentLength::Remaining(_) => Err(()),
+            _ => Ok(()),
+        }
+    }
 }
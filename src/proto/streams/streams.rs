@@ -1,6 +1,6 @@
 use {client, proto, server};
 use codec::{Codec, RecvError, SendError, UserError};
-use frame::{self, Frame, Reason};
+use frame::{self, Frame, Padding, Reason};
 use proto::{peer, Peer, Open, WindowSize};
 use super::{Buffer, Config, Counts, Prioritized, Recv, Send, Stream, StreamId};
 use super::recv::RecvHeaderBlockError;
@@ -11,7 +11,7 @@ use futures::{task, Async, Poll};
 use http::{HeaderMap, Request, Response};
 use tokio_io::AsyncWrite;
 
-use std::{fmt, io};
+use std::{cmp, fmt, io};
 use std::sync::{Arc, Mutex};
 
 #[derive(Debug)]
@@ -66,6 +66,9 @@ struct Inner {
 
     /// The number of stream refs to this shared state.
     refs: usize,
+
+    /// The settings received from the remote peer.
+    remote_settings: RemoteSettings,
 }
 
 #[derive(Debug)]
@@ -79,10 +82,30 @@ struct Actions {
     /// Task that calls `poll_complete`.
     task: Option<task::Task>,
 
+    /// Set when a handle requests the buffered frames to be flushed, until
+    /// `poll_complete` passes the request on to the codec.
+    flush_requested: bool,
+
     /// If the connection errors, a copy is kept for any StreamRefs.
     conn_error: Option<proto::Error>,
 }
 
+/// Tracks the settings received from the remote peer, so that they can be
+/// exposed to the user.
+#[derive(Debug, Default)]
+struct RemoteSettings {
+    /// All values received from the peer so far, or `None` until the first
+    /// SETTINGS frame has been applied.
+    settings: Option<frame::Settings>,
+
+    /// Incremented each time a SETTINGS frame is applied, so that handles can
+    /// tell if they have already observed the current values.
+    version: usize,
+
+    /// Tasks waiting for the settings to change.
+    tasks: Vec<task::Task>,
+}
+
 /// Contains the buffer of frames to be written to the wire.
 #[derive(Debug)]
 struct SendBuffer<B> {
@@ -106,10 +129,12 @@ where
                     recv: Recv::new(peer, &config),
                     send: Send::new(&config),
                     task: None,
+                    flush_requested: false,
                     conn_error: None,
                 },
                 store: Store::new(),
                 refs: 1,
+                remote_settings: RemoteSettings::default(),
             })),
             send_buffer: Arc::new(SendBuffer::new()),
             _p: ::std::marker::PhantomData,
@@ -264,7 +289,7 @@ where
                         id,
                     );
 
-                    let sz = frame.payload().len();
+                    let sz = frame.flow_controlled_len();
                     // This should have been enforced at the codec::FramedRead layer, so
                     // this is just a sanity check.
                     assert!(sz <= super::MAX_WINDOW_SIZE as usize);
@@ -287,7 +312,7 @@ where
         let send_buffer = &mut *send_buffer;
 
         me.counts.transition(stream, |counts, stream| {
-            let sz = frame.payload().len();
+            let sz = frame.flow_controlled_len();
             let res = actions.recv.recv_data(frame, stream);
 
             // Any stream error after receiving a DATA frame means
@@ -366,6 +391,8 @@ where
             .unwrap();
 
         actions.conn_error = Some(err.shallow_clone());
+        counts.notify_send_streams();
+        me.remote_settings.notify();
 
         last_processed_id
     }
@@ -380,7 +407,7 @@ where
         let send_buffer = &mut *send_buffer;
 
         let last_stream_id = frame.last_stream_id();
-        let err = frame.reason().into();
+        let err = proto::Error::Unprocessed(Some(frame.clone()), frame.reason());
 
         if last_stream_id > actions.recv.max_stream_id() {
             // The remote endpoint sent a `GOAWAY` frame indicating a stream
@@ -412,6 +439,8 @@ where
             .unwrap();
 
         actions.conn_error = Some(err);
+        counts.notify_send_streams();
+        me.remote_settings.notify();
 
         Ok(())
     }
@@ -585,6 +614,11 @@ where
         let mut send_buffer = self.send_buffer.inner.lock().unwrap();
         let send_buffer = &mut *send_buffer;
 
+        if me.actions.flush_requested {
+            me.actions.flush_requested = false;
+            dst.request_flush();
+        }
+
         // Send WINDOW_UPDATE frames first
         //
         // TODO: It would probably be better to interleave updates w/ data
@@ -615,7 +649,37 @@ where
         me.counts.apply_remote_settings(frame);
 
         me.actions.send.apply_remote_settings(
-            frame, send_buffer, &mut me.store, &mut me.counts, &mut me.actions.task)
+            frame, send_buffer, &mut me.store, &mut me.counts, &mut me.actions.task)?;
+
+        me.remote_settings.apply(frame);
+
+        Ok(())
+    }
+
+    /// Returns the settings received from the peer so far.
+    pub fn remote_settings(&self) -> Option<frame::Settings> {
+        let me = self.inner.lock().unwrap();
+        me.remote_settings.settings.clone()
+    }
+
+    /// Returns `Ready` with the remote settings if they were updated since
+    /// `version`, which is then set to the current version.
+    pub fn poll_remote_settings(&self, version: &mut usize) -> Poll<frame::Settings, ::Error> {
+        let mut me = self.inner.lock().unwrap();
+        let me = &mut *me;
+
+        if me.remote_settings.version != *version {
+            if let Some(ref settings) = me.remote_settings.settings {
+                *version = me.remote_settings.version;
+                return Ok(Async::Ready(settings.clone()));
+            }
+        }
+
+        // The settings won't change anymore once the connection is closed.
+        me.actions.ensure_no_conn_error()?;
+
+        me.remote_settings.register_task();
+        Ok(Async::NotReady)
     }
 
     pub fn send_request(
@@ -728,6 +792,13 @@ where
         me.actions.send_reset(stream, reason, &mut me.counts, send_buffer);
     }
 
+    /// Requests the buffered frames to be flushed, and notifies the
+    /// connection task.
+    pub fn request_flush(&self) {
+        let mut me = self.inner.lock().unwrap();
+        me.actions.request_flush();
+    }
+
     pub fn send_go_away(&mut self, last_processed_id: StreamId) {
         let mut me = self.inner.lock().unwrap();
         let me = &mut *me;
@@ -757,6 +828,36 @@ where
         }
         Ok(().into())
     }
+
+    /// Returns the number of streams that may be opened without waiting.
+    pub fn available_send_streams(&self) -> usize {
+        let me = self.inner.lock().unwrap();
+        cmp::min(
+            me.counts.available_send_streams(),
+            me.actions.send.num_remaining_stream_ids(),
+        )
+    }
+
+    /// Returns `Ready` with the number of streams that may be opened without
+    /// waiting once it is non-zero.
+    pub fn poll_available_send_streams(&mut self) -> Poll<usize, ::Error> {
+        let mut me = self.inner.lock().unwrap();
+        let me = &mut *me;
+
+        me.actions.ensure_no_conn_error()?;
+        me.actions.send.ensure_next_stream_id()?;
+
+        let available = cmp::min(
+            me.counts.available_send_streams(),
+            me.actions.send.num_remaining_stream_ids(),
+        );
+        if available == 0 {
+            me.counts.wait_send_streams();
+            return Ok(Async::NotReady);
+        }
+
+        Ok(available.into())
+    }
 }
 
 impl<B, P> Streams<B, P>
@@ -795,6 +896,8 @@ where
             .expect("recv_eof");
 
         actions.clear_queues(clear_pending_accept, &mut me.store, counts);
+        counts.notify_send_streams();
+        me.remote_settings.notify();
         Ok(())
     }
 
@@ -906,6 +1009,13 @@ impl<B> StreamRef<B> {
         me.actions.send_reset(stream, reason, &mut me.counts, send_buffer);
     }
 
+    /// Requests the buffered frames to be flushed, and notifies the
+    /// connection task.
+    pub fn request_flush(&self) {
+        let mut me = self.opaque.inner.lock().unwrap();
+        me.actions.request_flush();
+    }
+
     pub fn send_response(
         &mut self,
         response: Response<()>,
@@ -958,6 +1068,16 @@ impl<B> StreamRef<B> {
         me.actions.send.reserve_capacity(capacity, &mut stream, &mut me.counts)
     }
 
+    /// Sets the padding of frames sent on the stream
+    pub fn set_padding(&mut self, padding: Padding) {
+        let mut me = self.opaque.inner.lock().unwrap();
+        let me = &mut *me;
+
+        let mut stream = me.store.resolve(self.opaque.key);
+
+        stream.send_padding = Some(padding);
+    }
+
     /// Returns the stream's current send capacity.
     pub fn capacity(&self) -> WindowSize {
         let mut me = self.opaque.inner.lock().unwrap();
@@ -1234,9 +1354,41 @@ impl<B> SendBuffer<B> {
     }
 }
 
+// ===== impl RemoteSettings =====
+
+impl RemoteSettings {
+    fn apply(&mut self, frame: &frame::Settings) {
+        self.settings
+            .get_or_insert_with(frame::Settings::default)
+            .merge(frame);
+        self.version += 1;
+        self.notify();
+    }
+
+    fn register_task(&mut self) {
+        if !self.tasks.iter().any(|task| task.will_notify_current()) {
+            self.tasks.push(task::current());
+        }
+    }
+
+    fn notify(&mut self) {
+        for task in self.tasks.drain(..) {
+            task.notify();
+        }
+    }
+}
+
 // ===== impl Actions =====
 
 impl Actions {
+    fn request_flush(&mut self) {
+        self.flush_requested = true;
+
+        if let Some(task) = self.task.take() {
+            task.notify();
+        }
+    }
+
     fn send_reset<B>(
         &mut self,
         stream: store::Ptr,
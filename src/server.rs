@@ -129,15 +129,20 @@
 //! [`SendStream`]: ../struct.SendStream.html
 //! [`TcpListener`]: https://docs.rs/tokio-core/0.1/tokio_core/net/struct.TcpListener.html
 
-use {SendStream, RecvStream, ReleaseCapacity, PingPong};
-use codec::{Codec, RecvError};
-use frame::{self, Pseudo, Reason, Settings, StreamId};
+use {GoAway, SendStream, RecvStream, ReleaseCapacity, PingPong, RemoteSettings};
+use {ExtensionFrame, ExtensionFrames, FlushPolicy, FrameObserver};
+use codec::{self, Codec, RecvError};
+use frame::{self, HeaderValidation, Padding, Pseudo, Reason, Settings, StreamId};
+use hpack::{self, DefaultIndexPolicy, IndexPolicy};
+use observer::Observer;
 use proto::{self, Config, Prioritized};
 
 use bytes::{Buf, Bytes, IntoBuf};
 use futures::{self, Async, Future, Poll};
-use http::{HeaderMap, Request, Response};
+use http::{HeaderMap, Method, Request, Response};
+use http::header::{self, HeaderValue};
 use std::{convert, fmt, io, mem};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio_io::{AsyncRead, AsyncWrite};
 
@@ -209,6 +214,8 @@ pub struct Handshake<T, B: IntoBuf = Bytes> {
 #[must_use = "streams do nothing unless polled"]
 pub struct Connection<T, B: IntoBuf> {
     connection: proto::Connection<T, Peer, B>,
+    /// Version of the remote settings last returned by `poll_remote_settings`.
+    remote_settings_version: usize,
 }
 
 /// Builds server connections with custom configuration values.
@@ -261,6 +268,34 @@ pub struct Builder {
 
     /// Initial target window size for new connections.
     initial_target_connection_window_size: Option<u32>,
+
+    /// Whether the handshake completes only once the client's SETTINGS frame
+    /// has been received.
+    wait_for_remote_settings: bool,
+
+    /// Padding of frames sent on streams that don't override it.
+    padding: Padding,
+
+    /// Whether small DATA frames queued on a stream are merged when sent.
+    coalesce_data_frames: bool,
+
+    /// When frames buffered on the connection are flushed.
+    flush_policy: FlushPolicy,
+
+    /// Observer of the frames exchanged on the connection.
+    observer: Observer,
+
+    /// Decides which header fields are added to the HPACK dynamic table.
+    index_policy: Arc<dyn IndexPolicy>,
+
+    /// Whether `cookie` headers are split into one field per cookie pair.
+    crumble_cookies: bool,
+
+    /// How strictly the header fields of messages are validated.
+    header_validation: HeaderValidation,
+
+    /// Size of the buffer that the connection is read into.
+    read_buffer_size: usize,
 }
 
 /// Send a response back to the client
@@ -290,6 +325,9 @@ enum Handshaking<T, B: IntoBuf> {
     Flushing(Flush<T, Prioritized<B::Buf>>),
     /// State 2. Connection is waiting for the client preface.
     ReadingPreface(ReadPreface<T, Prioritized<B::Buf>>),
+    /// State 3. Connection is waiting for the client's SETTINGS frame, if
+    /// the `Builder` was configured to.
+    ReadingSettings(Connection<T, B>),
     /// Dummy state for `mem::replace`.
     Empty,
 }
@@ -308,7 +346,7 @@ struct ReadPreface<T, B> {
 #[derive(Debug)]
 pub(crate) struct Peer;
 
-const PREFACE: [u8; 24] = *b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+pub(crate) const PREFACE: [u8; 24] = *b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
 
 /// Creates a new configured HTTP/2.0 server with default configuration
 /// values backed by `io`.
@@ -365,6 +403,12 @@ where
     fn handshake2(io: T, builder: Builder) -> Handshake<T, B> {
         // Create the codec.
         let mut codec = Codec::new(io);
+        codec.set_observer(builder.observer.clone());
+        codec.set_index_policy(builder.index_policy.clone());
+        codec.set_crumble_cookies(builder.crumble_cookies);
+        codec.set_header_validation(builder.header_validation);
+        codec.set_read_buffer_size(builder.read_buffer_size);
+        codec.set_flush_policy(builder.flush_policy);
 
         if let Some(max) = builder.settings.max_frame_size() {
             codec.set_max_recv_frame_size(max as usize);
@@ -379,6 +423,9 @@ where
             .buffer(builder.settings.clone().into())
             .expect("invalid SETTINGS frame");
 
+        // The handshake must not wait on the flush policy.
+        codec.request_flush();
+
         // Create the handshake future.
         let state = Handshaking::from(codec);
 
@@ -407,6 +454,48 @@ where
         self.connection.set_target_window_size(size);
     }
 
+    /// Sets when the frames buffered on the connection are flushed to the
+    /// socket.
+    ///
+    /// Setting `FlushPolicy::Manual` corks the connection until [`flush`] is
+    /// called, or until the policy is changed again. See [`FlushPolicy`] for
+    /// the other policies.
+    ///
+    /// [`flush`]: #method.flush
+    /// [`FlushPolicy`]: ../enum.FlushPolicy.html
+    pub fn set_flush_policy(&mut self, policy: FlushPolicy) {
+        self.connection.set_flush_policy(policy);
+    }
+
+    /// Requests the frames buffered on the connection to be flushed to the
+    /// socket, whatever the flush policy.
+    ///
+    /// The connection task is notified, and the frames are flushed once the
+    /// connection has nothing more to write. [`SendStream::flush`] does the
+    /// same from a stream handle.
+    ///
+    /// [`SendStream::flush`]: ../struct.SendStream.html#method.flush
+    pub fn flush(&mut self) {
+        self.connection.request_flush();
+    }
+
+    /// Returns the HPACK compression statistics of the header blocks sent
+    /// on the connection, in responses, push promises and trailers.
+    ///
+    /// They tell whether tuning the [`index_policy`] or the header table size
+    /// is worth it for this peer.
+    ///
+    /// [`index_policy`]: struct.Builder.html#method.index_policy
+    pub fn hpack_encoder_stats(&self) -> hpack::Stats {
+        self.connection.hpack_encoder_stats()
+    }
+
+    /// Returns the HPACK compression statistics of the header blocks
+    /// received on the connection, in requests and trailers.
+    pub fn hpack_decoder_stats(&self) -> hpack::Stats {
+        self.connection.hpack_decoder_stats()
+    }
+
     /// Returns `Ready` when the underlying connection has closed.
     ///
     /// If any new inbound streams are received during a call to `poll_close`,
@@ -457,7 +546,57 @@ where
     ///
     /// [1]: http://httpwg.org/specs/rfc7540.html#GOAWAY
     pub fn graceful_shutdown(&mut self) {
-        self.connection.go_away_gracefully();
+        self.graceful_shutdown_with(Reason::NO_ERROR, Bytes::new());
+    }
+
+    /// Starts a [graceful shutdown][1] process, sending the given error code
+    /// and opaque debug data in the GOAWAY frames.
+    ///
+    /// This behaves like [`graceful_shutdown`], but lets the client know why
+    /// the connection is going away, e.g. `ENHANCE_YOUR_CALM` when the client
+    /// is being rate limited. The debug data is meant for diagnostics only,
+    /// and is truncated if it does not fit in a single frame.
+    ///
+    /// [1]: http://httpwg.org/specs/rfc7540.html#GOAWAY
+    /// [`graceful_shutdown`]: #method.graceful_shutdown
+    pub fn graceful_shutdown_with(&mut self, reason: Reason, debug_data: Bytes) {
+        self.connection.go_away_gracefully(reason, debug_data);
+    }
+
+    /// Returns the settings advertised by the client.
+    ///
+    /// Returns `None` if the client's initial SETTINGS frame has not been
+    /// received yet. See [`Builder::wait_for_remote_settings`] to only
+    /// complete the handshake once it has.
+    ///
+    /// [`Builder::wait_for_remote_settings`]: struct.Builder.html#method.wait_for_remote_settings
+    pub fn remote_settings(&self) -> Option<RemoteSettings> {
+        self.connection
+            .remote_settings()
+            .map(RemoteSettings::from_internal)
+    }
+
+    /// Returns `Ready` with the settings advertised by the client once they
+    /// have changed since the last time this function returned `Ready`.
+    ///
+    /// The first call returns `Ready` as soon as the client's initial
+    /// SETTINGS frame has been received. When `NotReady` is returned, the
+    /// task will be notified once the client updates its settings. This
+    /// function does not drive the connection; settings are only received
+    /// while the connection is being polled.
+    ///
+    /// An error is returned once the connection is closed or going away, as
+    /// the settings won't change anymore.
+    pub fn poll_remote_settings(&mut self) -> Poll<RemoteSettings, ::Error> {
+        let settings = try_ready!(self.connection.poll_remote_settings(&mut self.remote_settings_version));
+        Ok(RemoteSettings::from_internal(settings).into())
+    }
+
+    /// Returns the last GOAWAY frame received from the client, if any.
+    pub fn received_go_away(&self) -> Option<GoAway> {
+        self.connection
+            .received_go_away()
+            .map(GoAway::from_internal)
     }
 
     /// Takes a `PingPong` instance from the connection.
@@ -470,6 +609,32 @@ where
             .take_user_pings()
             .map(PingPong::new)
     }
+
+    /// Takes an `ExtensionFrames` instance from the connection, used to send
+    /// and receive extension frames.
+    ///
+    /// Until this is called, received extension frames are ignored.
+    ///
+    /// # Note
+    ///
+    /// This may only be called once. Calling multiple times will return `None`.
+    pub fn extension_frames(&mut self) -> Option<ExtensionFrames> {
+        self.connection
+            .take_user_extensions()
+            .map(ExtensionFrames::new)
+    }
+
+    /// Queues an extension frame to be sent to the peer.
+    ///
+    /// The frame is written the next time the connection is polled, after any
+    /// header block being written has been completed. See
+    /// [`ExtensionFrames::send_extension_frame`] to send frames from another
+    /// task.
+    ///
+    /// [`ExtensionFrames::send_extension_frame`]: ../struct.ExtensionFrames.html#method.send_extension_frame
+    pub fn send_extension_frame(&mut self, frame: ExtensionFrame) -> Result<(), ::Error> {
+        self.connection.send_extension_frame(frame.into_internal())
+    }
 }
 
 impl<T, B> futures::Stream for Connection<T, B>
@@ -557,6 +722,15 @@ impl Builder {
             reset_stream_max: proto::DEFAULT_RESET_STREAM_MAX,
             settings: Settings::default(),
             initial_target_connection_window_size: None,
+            wait_for_remote_settings: false,
+            padding: Padding::None,
+            coalesce_data_frames: false,
+            flush_policy: FlushPolicy::Idle,
+            observer: Observer::default(),
+            index_policy: Arc::new(DefaultIndexPolicy::new()),
+            crumble_cookies: false,
+            header_validation: HeaderValidation::default(),
+            read_buffer_size: codec::DEFAULT_READ_BUFFER_SIZE,
         }
     }
 
@@ -708,6 +882,46 @@ impl Builder {
         self
     }
 
+    /// Sets the size of the buffer that the connection is read into.
+    ///
+    /// Received frames are split off this buffer without being copied. The
+    /// buffer grows as needed to hold a whole frame, so this only sets how
+    /// much space is reserved for each read from the socket. A larger value
+    /// means fewer reads, at the cost of memory held per connection.
+    ///
+    /// The default value is 8,192.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `size` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .read_buffer_size(64 * 1024)
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    pub fn read_buffer_size(&mut self, size: usize) -> &mut Self {
+        assert!(size > 0, "read buffer size must be greater than zero");
+        self.read_buffer_size = size;
+        self
+    }
+
     /// Sets the maximum number of concurrent streams.
     ///
     /// The maximum concurrent streams setting only controls the maximum number
@@ -855,6 +1069,389 @@ impl Builder {
         self
     }
 
+    /// Sets whether the handshake waits for the client's initial SETTINGS
+    /// frame before completing.
+    ///
+    /// By default, the handshake completes once the client connection preface
+    /// has been read. When enabled, the handshake also reads from the
+    /// connection until the client's SETTINGS frame has been received and
+    /// applied, so that [`Connection::remote_settings`] returns the client's
+    /// actual values.
+    ///
+    /// Default value: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake, including the client's SETTINGS frame.
+    /// let server_fut = Builder::new()
+    ///     .wait_for_remote_settings(true)
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`Connection::remote_settings`]: struct.Connection.html#method.remote_settings
+    pub fn wait_for_remote_settings(&mut self, enabled: bool) -> &mut Self {
+        self.wait_for_remote_settings = enabled;
+        self
+    }
+
+    /// Advertises a setting that is not defined by the HTTP/2.0 specification.
+    ///
+    /// Protocol extensions define new settings, identified by `id`, which are
+    /// sent to the client in the initial SETTINGS frame along with the other
+    /// settings. They have no effect on the connection itself. If the same
+    /// `id` is set more than once, the last value wins.
+    ///
+    /// The setting is ignored if `id` identifies one of the settings defined by
+    /// the HTTP/2.0 specification, which have their own `Builder` functions,
+    /// or if [`MAX_UNKNOWN_SETTINGS`] other custom settings are already set.
+    ///
+    /// The client's own custom settings are available from
+    /// [`RemoteSettings::custom_settings`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .custom_setting(0xf000, 1)
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`RemoteSettings::custom_settings`]: ../struct.RemoteSettings.html#method.custom_settings
+    /// [`MAX_UNKNOWN_SETTINGS`]: ../frame/constant.MAX_UNKNOWN_SETTINGS.html
+    pub fn custom_setting(&mut self, id: u16, value: u32) -> &mut Self {
+        self.settings.set_unknown_setting(id, value);
+        self
+    }
+
+    /// Sets the padding of the DATA, HEADERS and PUSH_PROMISE frames sent on
+    /// the connection.
+    ///
+    /// Padding hides the actual size of messages from observers of the
+    /// encrypted traffic. See [`Padding`] for the available policies, and for
+    /// how padding interacts with flow control.
+    ///
+    /// The padding of a stream's frames can be changed with
+    /// [`SendResponse::set_padding`] and [`SendStream::set_padding`].
+    ///
+    /// The default value is `Padding::None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// # use h2::Padding;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .padding(Padding::RoundUp(64))
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`Padding`]: ../enum.Padding.html
+    /// [`SendResponse::set_padding`]: struct.SendResponse.html#method.set_padding
+    /// [`SendStream::set_padding`]: ../struct.SendStream.html#method.set_padding
+    pub fn padding(&mut self, padding: Padding) -> &mut Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Enables merging the data chunks queued on a stream into DATA frames.
+    ///
+    /// By default, every call to [`SendStream::send_data`] is sent in DATA
+    /// frames of its own. When enabled, chunks still queued on a stream are
+    /// merged into a single DATA frame when the stream is next written, as
+    /// long as the frame fits in the peer's max frame size and in the
+    /// stream's window. An empty chunk that ends the stream is folded into
+    /// the END_STREAM flag of the frame before it.
+    ///
+    /// This saves the overhead of a frame header per chunk when sending
+    /// many small chunks. Chunks are never split to be merged, and a chunk
+    /// is never held back waiting for more data.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .coalesce_data_frames(true)
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`SendStream::send_data`]: ../struct.SendStream.html#method.send_data
+    pub fn coalesce_data_frames(&mut self, enabled: bool) -> &mut Self {
+        self.coalesce_data_frames = enabled;
+        self
+    }
+
+    /// Sets when the frames buffered on the connection are flushed to the
+    /// socket.
+    ///
+    /// See [`FlushPolicy`] for the available policies. The policy can be
+    /// changed once the connection is established with
+    /// [`Connection::set_flush_policy`].
+    ///
+    /// The default value is `FlushPolicy::Idle`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// # use h2::FlushPolicy;
+    /// #
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .flush_policy(FlushPolicy::Buffered(8 * 1024))
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`FlushPolicy`]: ../enum.FlushPolicy.html
+    /// [`Connection::set_flush_policy`]: struct.Connection.html#method.set_flush_policy
+    pub fn flush_policy(&mut self, policy: FlushPolicy) -> &mut Self {
+        self.flush_policy = policy;
+        self
+    }
+
+    /// Sets an observer of the frames exchanged on the connection, and of its
+    /// events.
+    ///
+    /// The observer sees every frame read from, or written to, the
+    /// connection, with the decoded headers of HEADERS and PUSH_PROMISE
+    /// frames. See [`FrameObserver`] for details, and for how header values
+    /// are redacted.
+    ///
+    /// By default, there is no observer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// use h2::{FrameObserver, ObservedFrame};
+    ///
+    /// struct Logger;
+    ///
+    /// impl FrameObserver for Logger {
+    ///     fn recv_frame(&self, frame: &ObservedFrame) {
+    ///         println!("recv {:?}", frame);
+    ///     }
+    ///
+    ///     fn send_frame(&self, frame: &ObservedFrame) {
+    ///         println!("send {:?}", frame);
+    ///     }
+    /// }
+    ///
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .frame_observer(Logger)
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`FrameObserver`]: ../trait.FrameObserver.html
+    pub fn frame_observer<O: FrameObserver>(&mut self, observer: O) -> &mut Self {
+        self.observer = Observer::new(observer);
+        self
+    }
+
+    /// Sets the policy deciding which of the headers sent are added to the
+    /// HPACK dynamic table.
+    ///
+    /// Headers added to the table are sent as a single index the next time
+    /// they are sent, but evict older entries once the table is full. Headers
+    /// whose values rarely repeat, such as request IDs, are best kept out of
+    /// the table. See [`IndexPolicy`] for details.
+    ///
+    /// Defaults to [`DefaultIndexPolicy::new()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate http;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// use h2::hpack::DefaultIndexPolicy;
+    /// use http::header::HeaderName;
+    ///
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// let policy = DefaultIndexPolicy::new()
+    ///     .no_index(HeaderName::from_static("x-request-id"));
+    ///
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .index_policy(policy)
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`IndexPolicy`]: ../hpack/trait.IndexPolicy.html
+    /// [`DefaultIndexPolicy::new()`]: ../hpack/struct.DefaultIndexPolicy.html#method.new
+    pub fn index_policy<P: IndexPolicy>(&mut self, policy: P) -> &mut Self {
+        self.index_policy = Arc::new(policy);
+        self
+    }
+
+    /// Sets whether `cookie` headers are split into one field per cookie
+    /// pair when sent.
+    ///
+    /// [RFC 7540 §8.1.2.5] allows this so that each cookie pair is
+    /// compressed on its own: the cookies that don't change from one response to
+    /// the next are sent as a single index once they are in the HPACK
+    /// dynamic table. Split cookies are always joined again when received.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .crumble_cookies(true)
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [RFC 7540 §8.1.2.5]: http://httpwg.org/specs/rfc7540.html#CompressCookie
+    pub fn crumble_cookies(&mut self, enabled: bool) -> &mut Self {
+        self.crumble_cookies = enabled;
+        self
+    }
+
+    /// Sets how strictly the header fields of sent and received messages are
+    /// validated.
+    ///
+    /// With `HeaderValidation::Standard`, requests that break the HTTP/2.0
+    /// rules on header fields are rejected with a `PROTOCOL_ERROR`, and
+    /// sending a message with connection-specific header fields fails.
+    /// `HeaderValidation::Strict` also rejects requests that are missing
+    /// `:path`, or whose `host` header doesn't match their `:authority`. With
+    /// `HeaderValidation::Lenient`, such messages are fixed where possible,
+    /// which suits peers that translate HTTP/1.1 messages. See
+    /// [`HeaderValidation`] for the details.
+    ///
+    /// Defaults to `HeaderValidation::Standard`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate h2;
+    /// # extern crate tokio_io;
+    /// # use tokio_io::*;
+    /// # use h2::server::*;
+    /// use h2::HeaderValidation;
+    ///
+    /// # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+    /// # -> Handshake<T>
+    /// # {
+    /// // `server_fut` is a future representing the completion of the HTTP/2.0
+    /// // handshake.
+    /// let server_fut = Builder::new()
+    ///     .header_validation(HeaderValidation::Lenient)
+    ///     .handshake(my_io);
+    /// # server_fut
+    /// # }
+    /// #
+    /// # pub fn main() {}
+    /// ```
+    ///
+    /// [`HeaderValidation`]: ../enum.HeaderValidation.html
+    pub fn header_validation(&mut self, validation: HeaderValidation) -> &mut Self {
+        self.header_validation = validation;
+        self
+    }
+
     /// Creates a new configured HTTP/2.0 server backed by `io`.
     ///
     /// It is expected that `io` already be in an appropriate state to commence
@@ -980,6 +1577,20 @@ impl<B: IntoBuf> SendResponse<B> {
         self.inner.send_reset(reason)
     }
 
+    /// Sets the padding of frames sent on this stream.
+    ///
+    /// This overrides the connection's padding, as configured with
+    /// [`Builder::padding`], for all the frames of the response that have not
+    /// been written to the connection yet. Once the response is sent,
+    /// [`SendStream::set_padding`] changes it.
+    ///
+    /// [`Builder::padding`]: struct.Builder.html#method.padding
+    /// [`send_response`]: #method.send_response
+    /// [`SendStream::set_padding`]: ../struct.SendStream.html#method.set_padding
+    pub fn set_padding(&mut self, padding: Padding) {
+        self.inner.set_padding(padding)
+    }
+
     /// Polls to be notified when the client resets this stream.
     ///
     /// If stream is still open, this returns `Ok(Async::NotReady)`, and
@@ -1127,27 +1738,71 @@ impl<T, B: IntoBuf> Future for Handshake<T, B>
             // `if let` block, because we've borrowed `self` mutably in order
             // to poll the state and won't be able to borrow the SETTINGS frame
             // as well until we release the borrow for `poll()`.
+        } else if let ReadingSettings(_) = self.state {
+            return self.poll_remote_settings();
         } else {
             unreachable!("Handshake::poll() state was not advanced completely!")
         };
-        let server = poll?.map(|codec| {
-            let connection = proto::Connection::new(codec, Config {
-                next_stream_id: 2.into(),
-                // Server does not need to locally initiate any streams
-                initial_max_send_streams: 0,
-                reset_stream_duration: self.builder.reset_stream_duration,
-                reset_stream_max: self.builder.reset_stream_max,
-                settings: self.builder.settings.clone(),
-            });
-
-            trace!("Handshake::poll(); connection established!");
-            let mut c = Connection { connection };
-            if let Some(sz) = self.builder.initial_target_connection_window_size {
-                c.set_target_window_size(sz);
-            }
-            c
+        let codec = try_ready!(poll);
+        let connection = proto::Connection::new(codec, Config {
+            next_stream_id: 2.into(),
+            max_stream_id: StreamId::MAX,
+            // Server does not need to locally initiate any streams
+            initial_max_send_streams: 0,
+            reset_stream_duration: self.builder.reset_stream_duration,
+            reset_stream_max: self.builder.reset_stream_max,
+            padding: self.builder.padding,
+            coalesce_data: self.builder.coalesce_data_frames,
+            header_validation: self.builder.header_validation,
+            settings: self.builder.settings.clone(),
         });
-        Ok(server)
+
+        trace!("Handshake::poll(); connection established!");
+        let mut server = Connection {
+            connection,
+            remote_settings_version: 0,
+        };
+        if let Some(sz) = self.builder.initial_target_connection_window_size {
+            server.set_target_window_size(sz);
+        }
+
+        if self.builder.wait_for_remote_settings {
+            self.state = ReadingSettings(server);
+            return self.poll_remote_settings();
+        }
+
+        Ok(server.into())
+    }
+}
+
+impl<T, B> Handshake<T, B>
+    where T: AsyncRead + AsyncWrite,
+          B: IntoBuf,
+{
+    /// Drives the connection until the client's SETTINGS frame is applied.
+    fn poll_remote_settings(&mut self) -> Poll<Connection<T, B>, ::Error> {
+        if let Handshaking::ReadingSettings(ref mut server) = self.state {
+            if server.connection.remote_settings().is_none() {
+                if server.connection.poll()?.is_ready() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "connection closed before receiving SETTINGS",
+                    ).into());
+                }
+
+                if server.connection.remote_settings().is_none() {
+                    return Ok(Async::NotReady);
+                }
+            }
+        }
+
+        match mem::replace(&mut self.state, Handshaking::Empty) {
+            Handshaking::ReadingSettings(server) => {
+                trace!("Handshake::poll(); received SETTINGS");
+                Ok(server.into())
+            },
+            _ => unreachable!("Handshake::poll_remote_settings() called in wrong state"),
+        }
     }
 }
 
@@ -1205,7 +1860,7 @@ impl proto::Peer for Peer {
     }
 
     fn convert_poll_message(
-        pseudo: Pseudo, fields: HeaderMap, stream_id: StreamId
+        pseudo: Pseudo, mut fields: HeaderMap, stream_id: StreamId, validation: HeaderValidation
     ) -> Result<Self::Poll, RecvError> {
         use http::{uri, Version};
 
@@ -1223,6 +1878,8 @@ impl proto::Peer for Peer {
 
         b.version(Version::HTTP_2);
 
+        let is_connect = pseudo.method == Some(Method::CONNECT);
+
         if let Some(method) = pseudo.method {
             b.method(method);
         } else {
@@ -1249,6 +1906,21 @@ impl proto::Peer for Peer {
 
         }
 
+        // The `host` header must not point intermediaries that only look at
+        // it to another target than the :authority.
+        if validation.is_strict() {
+            if let (Some(authority), Some(host)) = (parts.authority.as_ref(), fields.get(header::HOST)) {
+                let scheme = pseudo.scheme.as_ref().map(|scheme| &scheme[..]);
+
+                if !frame::host_matches_authority(host, authority.as_str(), scheme) {
+                    malformed!(
+                        "malformed headers: host ({:?}) does not match authority ({:?})",
+                        host, authority,
+                    );
+                }
+            }
+        }
+
         // A :scheme is always required.
         if let Some(scheme) = pseudo.scheme {
             let maybe_scheme = uri::Scheme::from_shared(scheme.clone().into_inner());
@@ -1262,8 +1934,19 @@ impl proto::Peer for Peer {
             if parts.authority.is_some() {
                 parts.scheme = Some(scheme);
             }
-        } else {
+        } else if !validation.is_lenient() {
             malformed!("malformed headers: missing scheme");
+        } else if pseudo.path.is_some() {
+            // Without a scheme, the URI can't have both an authority and a
+            // path, so the authority is kept in the `host` header instead.
+            if let Some(authority) = parts.authority.take() {
+                if !fields.contains_key(header::HOST) {
+                    let host = HeaderValue::from_str(authority.as_str()).or_else(|why| malformed!(
+                        "malformed headers: malformed authority ({:?}): {}", authority, why,
+                    ))?;
+                    fields.insert(header::HOST, host);
+                }
+            }
         }
 
         if let Some(path) = pseudo.path {
@@ -1276,6 +1959,8 @@ impl proto::Peer for Peer {
             parts.path_and_query = Some(maybe_path.or_else(|why| malformed!(
                 "malformed headers: malformed path ({:?}): {}", path, why,
             ))?);
+        } else if validation.is_strict() && !is_connect {
+            malformed!("malformed headers: missing path");
         }
 
         b.uri(parts);
@@ -1311,6 +1996,8 @@ where
                 write!(f, "Handshaking::Flushing(_)"),
             Handshaking::ReadingPreface(_) =>
                 write!(f, "Handshaking::ReadingPreface(_)"),
+            Handshaking::ReadingSettings(_) =>
+                write!(f, "Handshaking::ReadingSettings(_)"),
             Handshaking::Empty =>
                 write!(f, "Handshaking::Empty"),
         }
@@ -0,0 +1,581 @@
+//! Capture the frames of a connection, and replay them.
+//!
+//! A [`Session`] holds the frames exchanged on a connection, in both
+//! directions, as seen by one of its endpoints. Sessions are recorded from a
+//! live connection by wrapping its I/O handle in a [`Capture`] before the
+//! handshake, and replayed against a client or server with the in-memory I/O
+//! handle returned by [`Session::replay`].
+//!
+//! With the `serde` feature, sessions can be serialized, for instance to
+//! store an interop failure seen in production as a JSON fixture, and replay
+//! it in a regression test. Frames are stored as hex strings, as in the HPACK
+//! fixtures.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate h2;
+//! # extern crate tokio_io;
+//! # use tokio_io::*;
+//! use h2::client;
+//! use h2::session::{Capture, Side};
+//!
+//! # fn doc<T: AsyncRead + AsyncWrite>(my_io: T)
+//! # -> client::Handshake<Capture<T>>
+//! # {
+//! let io = Capture::new(my_io, Side::Client);
+//! let recording = io.recording();
+//!
+//! let client_fut = client::handshake(io);
+//!
+//! // Later, once the connection is done with, or on an error:
+//! let session = recording.session();
+//!
+//! // Replaying the session feeds the recorded server frames to a new client.
+//! let replay_fut = client::handshake(session.replay());
+//! # client_fut
+//! # }
+//! #
+//! # pub fn main() {}
+//! ```
+//!
+//! [`Session`]: struct.Session.html
+//! [`Capture`]: struct.Capture.html
+//! [`Session::replay`]: struct.Session.html#method.replay
+
+use frame;
+use server::PREFACE;
+
+use byteorder::{BigEndian, ByteOrder};
+use bytes::{Bytes, BytesMut};
+use futures::{task, Async, Poll};
+use futures::task::Task;
+use tokio_io::{AsyncRead, AsyncWrite};
+
+use std::{cmp, fmt, io};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The default maximum number of frames a `Recording` holds.
+pub const DEFAULT_MAX_FRAMES: usize = 10_000;
+
+/// The frames exchanged on a connection.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Session {
+    side: Side,
+    frames: Vec<RecordedFrame>,
+}
+
+/// A frame of a `Session`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RecordedFrame {
+    direction: Direction,
+
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    elapsed: Option<Duration>,
+
+    #[cfg_attr(feature = "serde", serde(with = "hex"))]
+    bytes: Bytes,
+}
+
+/// The endpoint of the connection a `Session` is seen from.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Side {
+    /// The client.
+    Client,
+    /// The server.
+    Server,
+}
+
+/// Whether a frame was received or sent.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Direction {
+    /// The frame was received from the peer.
+    Recv,
+    /// The frame was sent to the peer.
+    Send,
+}
+
+/// An I/O handle that records the frames read and written through it.
+///
+/// The connection preface is not recorded.
+pub struct Capture<T> {
+    io: T,
+    recv: Frames,
+    send: Frames,
+    recording: Recording,
+}
+
+/// A handle to the session recorded by a `Capture` or a `Replay`.
+///
+/// Recording stops once [`DEFAULT_MAX_FRAMES`] frames are recorded, or the
+/// limit set with [`set_max_frames`], or when [`stop`] is called. The frames
+/// recorded until then are kept.
+///
+/// [`DEFAULT_MAX_FRAMES`]: constant.DEFAULT_MAX_FRAMES.html
+/// [`set_max_frames`]: #method.set_max_frames
+/// [`stop`]: #method.stop
+#[derive(Debug, Clone)]
+pub struct Recording {
+    inner: Arc<Mutex<RecordingInner>>,
+}
+
+#[derive(Debug)]
+struct RecordingInner {
+    session: Session,
+    start: Instant,
+    max_frames: usize,
+    is_stopped: bool,
+}
+
+/// An in-memory I/O handle that plays the peer's side of a `Session`.
+///
+/// Received frames are read in order. Each is only read once the
+/// implementation has written at least as many frames as were sent before
+/// it in the session, so that the peer's frames are not read before the ones
+/// they respond to. Once all frames have been read, and the implementation
+/// has written as many frames as the session holds, reading returns EOF.
+///
+/// What the implementation actually writes is recorded, see
+/// [`Replay::recording`].
+///
+/// [`Replay::recording`]: #method.recording
+#[derive(Debug)]
+pub struct Replay {
+    /// Frames to read, with the number of frames that must be written first
+    pending: VecDeque<(usize, Bytes)>,
+
+    /// Remaining bytes of the frame being read
+    current: Bytes,
+
+    /// The number of frames that must be written before EOF
+    num_send: usize,
+
+    /// Frames written by the implementation
+    written: Frames,
+    num_written: usize,
+
+    /// Task blocked reading until more frames are written
+    task: Option<Task>,
+
+    recording: Recording,
+}
+
+/// Splits a byte stream into frames.
+#[derive(Debug)]
+struct Frames {
+    buf: BytesMut,
+
+    /// Number of bytes of the connection preface still to skip
+    preface: usize,
+}
+
+// ===== impl Session =====
+
+impl Session {
+    /// Creates an empty session, seen from `side`.
+    pub fn new(side: Side) -> Self {
+        Session {
+            side,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Returns the endpoint the session is seen from.
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    /// Returns the frames of the session, in the order they were read or
+    /// written.
+    pub fn frames(&self) -> &[RecordedFrame] {
+        &self.frames
+    }
+
+    /// Appends a frame to the session.
+    pub fn push(&mut self, frame: RecordedFrame) {
+        self.frames.push(frame);
+    }
+
+    /// Returns an I/O handle that replays the session's received frames to
+    /// an implementation of the session's side.
+    ///
+    /// For instance, a session recorded by a client is replayed to a client.
+    pub fn replay(&self) -> Replay {
+        let mut pending = VecDeque::new();
+        let mut num_send = 0;
+
+        for frame in &self.frames {
+            match frame.direction {
+                Direction::Recv => pending.push_back((num_send, frame.bytes.clone())),
+                Direction::Send => num_send += 1,
+            }
+        }
+
+        // A server reads the preface before any frame.
+        let current = match self.side {
+            Side::Client => Bytes::new(),
+            Side::Server => Bytes::from_static(&PREFACE),
+        };
+
+        Replay {
+            pending,
+            current,
+            num_send,
+            written: Frames::new(self.side == Side::Client),
+            num_written: 0,
+            task: None,
+            recording: Recording::new(self.side),
+        }
+    }
+}
+
+// ===== impl RecordedFrame =====
+
+impl RecordedFrame {
+    /// Creates a new recorded frame.
+    ///
+    /// `bytes` holds the whole frame, its 9 byte head included.
+    pub fn new(direction: Direction, elapsed: Option<Duration>, bytes: Bytes) -> Self {
+        RecordedFrame {
+            direction,
+            elapsed,
+            bytes,
+        }
+    }
+
+    /// Returns whether the frame was received or sent.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Returns the time between the start of the recording and the frame, if
+    /// it is known.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.elapsed
+    }
+
+    /// Returns the frame type, or `None` if the bytes do not hold a frame
+    /// head whose length matches the rest of the frame.
+    ///
+    /// Frames recorded by a `Capture` are always complete, but a frame may
+    /// be created, or deserialized, from arbitrary bytes.
+    pub fn kind(&self) -> Option<u8> {
+        if self.bytes.len() < frame::HEADER_LEN {
+            return None;
+        }
+
+        let len = BigEndian::read_uint(&self.bytes[..3], 3) as usize;
+
+        if len != self.bytes.len() - frame::HEADER_LEN {
+            return None;
+        }
+
+        Some(self.bytes[3])
+    }
+
+    /// Returns the bytes of the frame, its head included.
+    pub fn bytes(&self) -> &Bytes {
+        &self.bytes
+    }
+}
+
+// ===== impl Capture =====
+
+impl<T> Capture<T> {
+    /// Wraps `io`, the I/O handle of the connection's `side`.
+    pub fn new(io: T, side: Side) -> Self {
+        Capture {
+            io,
+            recv: Frames::new(side == Side::Server),
+            send: Frames::new(side == Side::Client),
+            recording: Recording::new(side),
+        }
+    }
+
+    /// Returns a handle to the recorded session.
+    ///
+    /// The handle stays valid once the `Capture` is moved into a connection.
+    pub fn recording(&self) -> Recording {
+        self.recording.clone()
+    }
+
+    /// Returns a reference to the wrapped I/O handle.
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the wrapped I/O handle.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Consumes the `Capture`, returning the wrapped I/O handle.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+impl<T: io::Read> io::Read for Capture<T> {
+    fn read(&mut self, dst: &mut [u8]) -> io::Result<usize> {
+        let n = self.io.read(dst)?;
+
+        // Frames are no longer buffered once recording has stopped.
+        if self.recording.is_stopped() {
+            return Ok(n);
+        }
+
+        for frame in self.recv.push(&dst[..n]) {
+            self.recording.record(Direction::Recv, frame);
+        }
+
+        Ok(n)
+    }
+}
+
+impl<T: AsyncRead> AsyncRead for Capture<T> {}
+
+impl<T: io::Write> io::Write for Capture<T> {
+    fn write(&mut self, src: &[u8]) -> io::Result<usize> {
+        let n = self.io.write(src)?;
+
+        if self.recording.is_stopped() {
+            return Ok(n);
+        }
+
+        for frame in self.send.push(&src[..n]) {
+            self.recording.record(Direction::Send, frame);
+        }
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.io.flush()
+    }
+}
+
+impl<T: AsyncWrite> AsyncWrite for Capture<T> {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        self.io.shutdown()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Capture<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Capture")
+            .field("io", &self.io)
+            .field("recording", &self.recording)
+            .finish()
+    }
+}
+
+// ===== impl Recording =====
+
+impl Recording {
+    fn new(side: Side) -> Self {
+        Recording {
+            inner: Arc::new(Mutex::new(RecordingInner {
+                session: Session::new(side),
+                start: Instant::now(),
+                max_frames: DEFAULT_MAX_FRAMES,
+                is_stopped: false,
+            })),
+        }
+    }
+
+    /// Returns the session recorded so far.
+    pub fn session(&self) -> Session {
+        self.inner.lock().unwrap().session.clone()
+    }
+
+    /// Sets the maximum number of frames recorded.
+    ///
+    /// Recording stops once the session holds `max` frames, or right away if
+    /// it already holds as many.
+    pub fn set_max_frames(&self, max: usize) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.max_frames = max;
+
+        if inner.session.frames.len() >= max {
+            inner.is_stopped = true;
+        }
+    }
+
+    /// Stops recording. The frames recorded so far are kept.
+    pub fn stop(&self) {
+        self.inner.lock().unwrap().is_stopped = true;
+    }
+
+    /// Returns true once recording has stopped, either because [`stop`] was
+    /// called or because the maximum number of frames was reached.
+    ///
+    /// [`stop`]: #method.stop
+    pub fn is_stopped(&self) -> bool {
+        self.inner.lock().unwrap().is_stopped
+    }
+
+    fn record(&self, direction: Direction, bytes: Bytes) {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.is_stopped {
+            return;
+        }
+
+        let elapsed = inner.start.elapsed();
+        inner.session.push(RecordedFrame::new(direction, Some(elapsed), bytes));
+
+        if inner.session.frames.len() >= inner.max_frames {
+            inner.is_stopped = true;
+        }
+    }
+}
+
+// ===== impl Replay =====
+
+impl Replay {
+    /// Returns a handle to the session as replayed: the frames read by the
+    /// implementation, and the frames it wrote.
+    pub fn recording(&self) -> Recording {
+        self.recording.clone()
+    }
+
+    fn wait(&mut self) -> io::Result<usize> {
+        self.task = Some(task::current());
+        Err(io::ErrorKind::WouldBlock.into())
+    }
+}
+
+impl io::Read for Replay {
+    fn read(&mut self, dst: &mut [u8]) -> io::Result<usize> {
+        if self.current.is_empty() {
+            let num_written = self.num_written;
+
+            match self.pending.front() {
+                Some(&(num_send, _)) if num_send > num_written => return self.wait(),
+                Some(_) => {},
+                None if self.num_send > num_written => return self.wait(),
+                None => return Ok(0),
+            }
+
+            let (_, frame) = self.pending.pop_front().unwrap();
+            self.recording.record(Direction::Recv, frame.clone());
+            self.current = frame;
+        }
+
+        let n = cmp::min(dst.len(), self.current.len());
+        dst[..n].copy_from_slice(&self.current.split_to(n));
+
+        Ok(n)
+    }
+}
+
+impl AsyncRead for Replay {}
+
+impl io::Write for Replay {
+    fn write(&mut self, src: &[u8]) -> io::Result<usize> {
+        for frame in self.written.push(src) {
+            self.recording.record(Direction::Send, frame);
+            self.num_written += 1;
+        }
+
+        if let Some(task) = self.task.take() {
+            task.notify();
+        }
+
+        Ok(src.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsyncWrite for Replay {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        Ok(Async::Ready(()))
+    }
+}
+
+// ===== impl Frames =====
+
+impl Frames {
+    fn new(has_preface: bool) -> Self {
+        Frames {
+            buf: BytesMut::new(),
+            preface: if has_preface { PREFACE.len() } else { 0 },
+        }
+    }
+
+    /// Buffers `src`, returning the frames that it completes.
+    fn push(&mut self, src: &[u8]) -> Vec<Bytes> {
+        self.buf.extend_from_slice(src);
+
+        if self.preface > 0 {
+            let n = cmp::min(self.preface, self.buf.len());
+            let _ = self.buf.split_to(n);
+            self.preface -= n;
+        }
+
+        let mut frames = vec![];
+
+        while self.buf.len() >= frame::HEADER_LEN {
+            let len = frame::HEADER_LEN + BigEndian::read_uint(&self.buf[..3], 3) as usize;
+
+            if self.buf.len() < len {
+                break;
+            }
+
+            frames.push(self.buf.split_to(len).freeze());
+        }
+
+        frames
+    }
+}
+
+// ===== serde =====
+
+#[cfg(feature = "serde")]
+mod hex {
+    use bytes::Bytes;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde::de::Error;
+
+    use std::str;
+
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    pub fn serialize<S: Serializer>(bytes: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut hex = String::with_capacity(bytes.len() * 2);
+
+        for &b in bytes.iter() {
+            hex.push(DIGITS[(b >> 4) as usize] as char);
+            hex.push(DIGITS[(b & 0xf) as usize] as char);
+        }
+
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+
+        if hex.len() % 2 != 0 {
+            return Err(D::Error::custom("odd number of hex digits"));
+        }
+
+        hex.as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                str::from_utf8(pair).ok()
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or_else(|| D::Error::custom("invalid hex digit"))
+            })
+            .collect::<Result<Vec<u8>, _>>()
+            .map(Bytes::from)
+    }
+}
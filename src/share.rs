@@ -1,5 +1,5 @@
 use codec::UserError;
-use frame::Reason;
+use frame::{Padding, Reason, UnknownSettings};
 use proto::{self, WindowSize};
 
 use bytes::{Bytes, IntoBuf};
@@ -214,6 +214,66 @@ pub struct Pong {
     _p: (),
 }
 
+/// A GOAWAY frame received from the remote peer.
+///
+/// A GOAWAY frame initiates the shutdown of a connection. Streams with an ID
+/// up to and including [`last_stream_id`] may still complete, while streams
+/// with a greater ID were not processed by the peer and may safely be retried
+/// on a new connection.
+///
+/// [`last_stream_id`]: #method.last_stream_id
+#[derive(Debug, Clone)]
+pub struct GoAway {
+    last_stream_id: StreamId,
+    reason: Reason,
+    debug_data: Bytes,
+}
+
+/// A frame of a type not defined by the HTTP/2.0 specification.
+///
+/// Extension frames are used to build protocol extensions on top of HTTP/2.0.
+/// Their type, flags and payload are opaque to `h2`, which only takes care of
+/// reading and writing them. As required by [Section 4.1], such frames never
+/// interrupt a header block.
+///
+/// The peer should not be sent extension frames unless it is known to support
+/// them, for example through a custom setting. Peers that don't understand an
+/// extension frame ignore it.
+///
+/// [Section 4.1]: http://httpwg.org/specs/rfc7540.html#FrameHeader
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ExtensionFrame {
+    inner: ::frame::Extension,
+}
+
+/// A handle to send and receive extension frames on a connection.
+///
+/// This type implements [`Stream`], yielding the extension frames received
+/// from the peer, on any stream. The stream ends once the connection is
+/// closed.
+///
+/// Received frames are buffered until they are polled, so this handle should
+/// be dropped if it is no longer polled; frames are then discarded. At most
+/// 128 frames are buffered, frames received while the buffer is full are
+/// dropped.
+///
+/// [`Stream`]: https://docs.rs/futures/0.1/futures/stream/trait.Stream.html
+// NOT Clone on purpose
+pub struct ExtensionFrames {
+    inner: proto::UserExtensions,
+}
+
+/// The settings advertised by the remote peer.
+///
+/// Values that the peer has not advertised are reported with the initial
+/// values defined by the HTTP/2.0 [specification].
+///
+/// [specification]: http://httpwg.org/specs/rfc7540.html#SettingValues
+#[derive(Debug, Clone)]
+pub struct RemoteSettings {
+    inner: ::frame::Settings,
+}
+
 // ===== impl SendStream =====
 
 impl<B: IntoBuf> SendStream<B> {
@@ -343,6 +403,31 @@ impl<B: IntoBuf> SendStream<B> {
         self.inner.send_trailers(trailers).map_err(Into::into)
     }
 
+    /// Sets the padding of frames sent on this stream.
+    ///
+    /// This overrides the connection's padding, as configured on the client or
+    /// server `Builder`, for all the frames of the stream that have not been
+    /// written to the connection yet, including queued ones.
+    ///
+    /// See [`Padding`] for how padding interacts with flow control.
+    ///
+    /// [`Padding`]: enum.Padding.html
+    pub fn set_padding(&mut self, padding: Padding) {
+        self.inner.set_padding(padding)
+    }
+
+    /// Requests the frames buffered on the connection to be flushed to the
+    /// socket, whatever the flush policy.
+    ///
+    /// This uncorks a connection using [`FlushPolicy::Manual`] from a stream
+    /// handle: the connection task is notified, and the frames are flushed
+    /// once it has nothing more to write.
+    ///
+    /// [`FlushPolicy::Manual`]: enum.FlushPolicy.html#variant.Manual
+    pub fn flush(&mut self) {
+        self.inner.request_flush();
+    }
+
     /// Resets the stream.
     ///
     /// This cancels the request / response exchange. If the response has not
@@ -387,6 +472,19 @@ impl StreamId {
         StreamId(id.into())
     }
 }
+
+impl From<::frame::StreamId> for StreamId {
+    fn from(src: ::frame::StreamId) -> Self {
+        StreamId::from_internal(src)
+    }
+}
+
+impl From<StreamId> for ::frame::StreamId {
+    fn from(src: StreamId) -> Self {
+        src.0.into()
+    }
+}
+
 // ===== impl RecvStream =====
 
 impl RecvStream {
@@ -618,3 +716,187 @@ impl fmt::Debug for Pong {
             .finish()
     }
 }
+
+// ===== impl GoAway =====
+
+impl GoAway {
+    pub(crate) fn from_internal(frame: &::frame::GoAway) -> Self {
+        GoAway {
+            last_stream_id: StreamId::from_internal(frame.last_stream_id()),
+            reason: frame.reason(),
+            debug_data: frame.debug_data().clone(),
+        }
+    }
+
+    /// Returns the ID of the last stream the peer may have processed.
+    pub fn last_stream_id(&self) -> StreamId {
+        self.last_stream_id.clone()
+    }
+
+    /// Returns the error code the peer closed the connection with.
+    pub fn reason(&self) -> Reason {
+        self.reason
+    }
+
+    /// Returns the opaque debug data sent by the peer.
+    ///
+    /// The debug data is intended for diagnostic purposes only and carries
+    /// no semantic value. It may be empty.
+    pub fn debug_data(&self) -> &Bytes {
+        &self.debug_data
+    }
+}
+
+// ===== impl RemoteSettings =====
+
+impl RemoteSettings {
+    pub(crate) fn from_internal(inner: ::frame::Settings) -> Self {
+        RemoteSettings { inner }
+    }
+
+    /// Returns the maximum size of the header compression table the peer
+    /// uses to decode header blocks (`SETTINGS_HEADER_TABLE_SIZE`).
+    pub fn header_table_size(&self) -> u32 {
+        self.inner
+            .header_table_size()
+            .unwrap_or(::frame::DEFAULT_SETTINGS_HEADER_TABLE_SIZE as u32)
+    }
+
+    /// Returns whether the peer accepts server push (`SETTINGS_ENABLE_PUSH`).
+    pub fn is_push_enabled(&self) -> bool {
+        self.inner.is_push_enabled()
+    }
+
+    /// Returns the maximum number of concurrent streams the peer allows us to
+    /// open (`SETTINGS_MAX_CONCURRENT_STREAMS`), or `None` if unlimited.
+    pub fn max_concurrent_streams(&self) -> Option<u32> {
+        self.inner.max_concurrent_streams()
+    }
+
+    /// Returns the peer's initial window size for stream level flow control
+    /// (`SETTINGS_INITIAL_WINDOW_SIZE`).
+    pub fn initial_window_size(&self) -> u32 {
+        self.inner
+            .initial_window_size()
+            .unwrap_or(::frame::DEFAULT_INITIAL_WINDOW_SIZE)
+    }
+
+    /// Returns the largest frame payload the peer is willing to receive
+    /// (`SETTINGS_MAX_FRAME_SIZE`).
+    pub fn max_frame_size(&self) -> u32 {
+        self.inner
+            .max_frame_size()
+            .unwrap_or(::frame::DEFAULT_MAX_FRAME_SIZE)
+    }
+
+    /// Returns the largest header list the peer is willing to accept
+    /// (`SETTINGS_MAX_HEADER_LIST_SIZE`), or `None` if unlimited.
+    pub fn max_header_list_size(&self) -> Option<u32> {
+        self.inner.max_header_list_size()
+    }
+
+    /// Returns the settings advertised by the peer that are not defined by
+    /// the HTTP/2.0 specification, as `(identifier, value)` pairs.
+    ///
+    /// The settings are in the order the peer first advertised them, each
+    /// with its most recent value. At most [`MAX_UNKNOWN_SETTINGS`] are kept;
+    /// identifiers advertised after that are ignored.
+    ///
+    /// [`MAX_UNKNOWN_SETTINGS`]: frame/constant.MAX_UNKNOWN_SETTINGS.html
+    pub fn custom_settings<'a>(&'a self) -> UnknownSettings<'a> {
+        self.inner.unknown_settings()
+    }
+
+    /// Returns the value of the custom setting `id`, or `None` if the peer has
+    /// not advertised it.
+    pub fn custom_setting(&self, id: u16) -> Option<u32> {
+        self.inner.unknown_setting(id)
+    }
+}
+
+// ===== impl ExtensionFrame =====
+
+impl ExtensionFrame {
+    /// Creates a new extension frame of type `kind`, sent on the stream with
+    /// ID `stream_id`.
+    ///
+    /// A stream ID of zero means that the frame applies to the connection as
+    /// a whole. The payload must not be longer than 16,384 bytes, so that it
+    /// fits in a single frame whatever the peer's maximum frame size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `kind` is one of the frame types defined by the HTTP/2.0
+    /// specification, or if `stream_id` is greater than 2^31-1.
+    pub fn new(kind: u8, flags: u8, stream_id: u32, payload: Bytes) -> Self {
+        ExtensionFrame {
+            inner: ::frame::Extension::new(kind, flags, stream_id.into(), payload),
+        }
+    }
+
+    pub(crate) fn from_internal(inner: ::frame::Extension) -> Self {
+        ExtensionFrame { inner }
+    }
+
+    pub(crate) fn into_internal(self) -> ::frame::Extension {
+        self.inner
+    }
+
+    /// Returns the frame type.
+    pub fn kind(&self) -> u8 {
+        self.inner.kind()
+    }
+
+    /// Returns the frame flags.
+    pub fn flags(&self) -> u8 {
+        self.inner.flags()
+    }
+
+    /// Returns the ID of the stream the frame was sent on.
+    pub fn stream_id(&self) -> StreamId {
+        StreamId::from_internal(self.inner.stream_id())
+    }
+
+    /// Returns the frame payload.
+    pub fn payload(&self) -> &Bytes {
+        self.inner.payload()
+    }
+}
+
+// ===== impl ExtensionFrames =====
+
+impl ExtensionFrames {
+    pub(crate) fn new(inner: proto::UserExtensions) -> Self {
+        ExtensionFrames {
+            inner,
+        }
+    }
+
+    /// Queues an extension frame to be sent to the peer.
+    ///
+    /// The frame is written the next time the connection is polled, after any
+    /// header block being written has been completed.
+    ///
+    /// Returns an error if the payload is too big or if the connection has
+    /// been closed.
+    pub fn send_extension_frame(&mut self, frame: ExtensionFrame) -> Result<(), ::Error> {
+        self.inner.send_extension_frame(frame.into_internal())
+    }
+}
+
+impl futures::Stream for ExtensionFrames {
+    type Item = ExtensionFrame;
+    type Error = ::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let frame = try_ready!(self.inner.poll_extension_frame());
+        Ok(frame.map(ExtensionFrame::from_internal).into())
+    }
+}
+
+impl fmt::Debug for ExtensionFrames {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("ExtensionFrames")
+            .finish()
+    }
+}
@@ -57,6 +57,14 @@ where
     Mock(frame::GoAway::new(id.into(), frame::Reason::NO_ERROR))
 }
 
+pub fn extension<T, B>(kind: u8, id: T, payload: B) -> Mock<frame::Extension>
+where
+    T: Into<StreamId>,
+    B: Into<Bytes>,
+{
+    Mock(frame::Extension::new(kind, 0, id.into(), payload.into()))
+}
+
 pub fn reset<T>(id: T) -> Mock<frame::Reset>
 where
     T: Into<StreamId>,
@@ -152,6 +160,11 @@ impl Mock<frame::Headers> {
         self
     }
 
+    pub fn padding(mut self, pad_len: u8) -> Self {
+        self.0.set_padding(frame::Padding::Fixed(pad_len));
+        self
+    }
+
     pub fn into_fields(self) -> HeaderMap {
         self.0.into_parts().1
     }
@@ -185,6 +198,11 @@ impl Mock<frame::Data> {
         self
     }
 
+    pub fn padding(mut self, pad_len: u8) -> Self {
+        self.0.set_padding(pad_len);
+        self
+    }
+
     pub fn eos(mut self) -> Self {
         self.0.set_end_stream(true);
         self
@@ -196,10 +214,13 @@ impl From<Mock<frame::Data>> for SendFrame {
         let id = src.0.stream_id();
         let eos = src.0.is_end_stream();
         let is_padded = src.0.is_padded();
+        let pad_len = src.0.pad_len();
         let payload = src.0.into_payload();
         let mut frame = frame::Data::new(id, payload.into_buf());
         frame.set_end_stream(eos);
-        if is_padded {
+        if let Some(pad_len) = pad_len {
+            frame.set_padding(pad_len);
+        } else if is_padded {
             frame.set_padded();
         }
         Frame::Data(frame)
@@ -279,10 +300,26 @@ impl Mock<frame::GoAway> {
         self.reason(frame::Reason::NO_ERROR)
     }
 
+    pub fn calm(self) -> Self {
+        self.reason(frame::Reason::ENHANCE_YOUR_CALM)
+    }
+
     pub fn reason(self, reason: frame::Reason) -> Self {
-        Mock(frame::GoAway::new(
+        Mock(frame::GoAway::with_debug_data(
             self.0.last_stream_id(),
             reason,
+            self.0.debug_data().clone(),
+        ))
+    }
+
+    pub fn data<I>(self, debug_data: I) -> Self
+    where
+        I: Into<Bytes>,
+    {
+        Mock(frame::GoAway::with_debug_data(
+            self.0.last_stream_id(),
+            self.0.reason(),
+            debug_data.into(),
         ))
     }
 }
@@ -355,6 +392,11 @@ impl Mock<frame::Settings> {
         self.0.set_max_header_list_size(Some(val));
         self
     }
+
+    pub fn custom(mut self, id: u16, val: u32) -> Self {
+        self.0.set_unknown_setting(id, val);
+        self
+    }
 }
 
 impl From<Mock<frame::Settings>> for frame::Settings {
@@ -384,6 +426,14 @@ impl From<Mock<frame::Ping>> for SendFrame {
     }
 }
 
+// ==== Extension helpers
+
+impl From<Mock<frame::Extension>> for SendFrame {
+    fn from(src: Mock<frame::Extension>) -> Self {
+        Frame::Extension(src.0)
+    }
+}
+
 // ==== "trait alias" for types that are HttpTryFrom and have Debug Errors ====
 
 pub trait HttpTryInto<T> {
@@ -157,6 +157,71 @@ fn request_stream_id_overflows() {
     h2.join(srv).wait().expect("wait");
 }
 
+#[test]
+fn request_stream_ids_exhausted_with_margin() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let first_id = (::std::u32::MAX >> 1) - 4;
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(first_id)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .recv_frame(
+            frames::headers(first_id + 2)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .send_frame(frames::headers(first_id).response(200).eos())
+        .send_frame(frames::headers(first_id + 2).response(200).eos())
+        .close();
+
+    let h2 = client::Builder::new()
+        .initial_stream_id(first_id)
+        .stream_id_exhaustion_margin(1)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(mut client, h2)| {
+            // Only two stream IDs are left before reaching the margin.
+            assert_eq!(client.available_streams(), 2);
+
+            let resp1 = client.get("https://example.com/");
+            assert_eq!(client.available_streams(), 1);
+
+            let resp2 = client.get("https://example.com/");
+            assert_eq!(client.available_streams(), 0);
+
+            // The connection needs to be replaced.
+            let poll_err = client.poll_ready().unwrap_err();
+            assert!(poll_err.is_stream_ids_exhausted());
+            assert!(poll_err.is_retryable());
+
+            let request = Request::builder()
+                .uri("https://example.com/")
+                .body(())
+                .unwrap();
+
+            let err = client.send_request(request, true).unwrap_err();
+            assert!(err.is_stream_ids_exhausted());
+
+            // Streams that were already open complete normally.
+            h2.drive(resp1.join(resp2)).and_then(move |(h2, (resp1, resp2))| {
+                assert_eq!(resp1.status(), StatusCode::OK);
+                assert_eq!(resp2.status(), StatusCode::OK);
+
+                drop(client);
+                h2.expect("h2")
+            })
+        });
+
+    h2.join(srv).wait().expect("wait");
+}
+
 #[test]
 fn client_builder_max_concurrent_streams() {
     let _ = ::env_logger::try_init();
@@ -777,6 +842,112 @@ fn recv_too_big_headers() {
 
 }
 
+#[test]
+fn send_header_field_across_continuation_frames() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    // Backslashes have a long huffman code, so the encoded field is several
+    // times the size of a frame.
+    let value = "\\".repeat(20_000);
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://http2.akamai.com/")
+                .field("x-big", &value[..])
+                .eos(),
+        )
+        .send_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let client = client::handshake(io)
+        .expect("handshake")
+        .and_then(move |(mut client, conn)| {
+            let request = Request::builder()
+                .uri("https://http2.akamai.com/")
+                .header("x-big", &value[..])
+                .body(())
+                .unwrap();
+
+            let req = client
+                .send_request(request, true)
+                .expect("send_request")
+                .0
+                .expect("response")
+                .map(|rsp| {
+                    assert_eq!(rsp.status(), StatusCode::OK);
+                });
+
+            conn.expect("client").join(req)
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+fn send_header_field_too_big() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .close();
+
+    let client = client::handshake(io)
+        .expect("handshake")
+        .and_then(|(mut client, conn)| {
+            let request = Request::builder()
+                .uri("https://http2.akamai.com/")
+                .header("x-big", &"a".repeat(16 << 20)[..])
+                .body(())
+                .unwrap();
+
+            let err = client.send_request(request, true).unwrap_err();
+            assert_eq!(err.to_string(), "user error: header too big");
+
+            conn.expect("client").map(|c| (client, c))
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+fn send_request_over_peer_max_header_list_size() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake_with_settings(
+            frames::settings()
+                .max_header_list_size(200))
+        .unwrap()
+        .recv_settings()
+        .close();
+
+    let client = client::Builder::new()
+        .wait_for_remote_settings(true)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(mut client, conn)| {
+            let request = Request::builder()
+                .uri("https://http2.akamai.com/")
+                .header("x-big", &"a".repeat(100)[..])
+                .body(())
+                .unwrap();
+
+            let err = client.send_request(request, true).unwrap_err();
+            assert!(err.is_header_list_too_big());
+            assert_eq!(err.to_string(), "user error: header list too big");
+
+            conn.expect("client").map(|c| (client, c))
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
 #[test]
 fn pending_send_request_gets_reset_by_peer_properly() {
     let _ = ::env_logger::try_init();
@@ -840,6 +1011,284 @@ fn pending_send_request_gets_reset_by_peer_properly() {
     client.join(srv).wait().expect("wait");
 }
 
+#[test]
+fn recv_refused_stream_is_retryable() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .recv_frame(
+            frames::headers(3)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .send_frame(frames::reset(1).refused())
+        .send_frame(frames::reset(3).cancel())
+        .recv_frame(frames::go_away(0))
+        .close();
+
+    let client = client::handshake(io)
+        .expect("handshake")
+        .and_then(|(mut client, conn)| {
+            let refused = client
+                .get("https://example.com/")
+                .expect_err("response")
+                .map(|err| {
+                    assert_eq!(err.reason(), Some(Reason::REFUSED_STREAM));
+                    assert!(err.is_retryable());
+                });
+
+            let canceled = client
+                .get("https://example.com/")
+                .expect_err("response")
+                .map(|err| {
+                    assert_eq!(err.reason(), Some(Reason::CANCEL));
+                    assert!(!err.is_retryable());
+                });
+
+            conn.drive(refused.join(canceled))
+                .and_then(|(conn, _)| conn.expect("client"))
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+fn sent_refused_stream_is_not_retryable() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        // The headers are cleared from the send queue by the reset.
+        .recv_frame(frames::reset(1).refused())
+        .recv_frame(frames::go_away(0))
+        .close();
+
+    let client = client::handshake(io)
+        .expect("handshake")
+        .and_then(|(mut client, conn)| {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("https://example.com/")
+                .body(())
+                .unwrap();
+
+            let (response, mut stream) = client.send_request(request, false).unwrap();
+            stream.send_reset(Reason::REFUSED_STREAM);
+
+            let response = response.expect_err("response").map(|err| {
+                assert_eq!(err.reason(), Some(Reason::REFUSED_STREAM));
+                assert!(!err.is_retryable());
+            });
+
+            conn.drive(response)
+                .and_then(|(conn, _)| conn.expect("client"))
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+fn handshake_waits_for_remote_settings() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake_with_settings(
+            frames::settings()
+                .max_concurrent_streams(5)
+                .initial_window_size(1_000))
+        .unwrap()
+        .recv_settings()
+        .close();
+
+    let client = client::Builder::new()
+        .wait_for_remote_settings(true)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(mut client, conn)| {
+            let settings = client.remote_settings().expect("remote settings");
+            assert_eq!(settings.max_concurrent_streams(), Some(5));
+            assert_eq!(settings.initial_window_size(), 1_000);
+            assert_eq!(settings.max_frame_size(), 16_384);
+            assert!(settings.is_push_enabled());
+
+            let settings = client.poll_remote_settings().expect("poll_remote_settings");
+            assert!(settings.is_ready());
+
+            drop(client);
+            conn.expect("client")
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+fn recv_updated_custom_settings() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake_with_settings(
+            frames::settings()
+                .custom(0xf000, 7)
+                .custom(0xf001, 8))
+        .unwrap()
+        .recv_custom_settings(
+            frames::settings()
+                .custom(0xf002, 1)
+        )
+        .send_frame(
+            frames::settings()
+                .custom(0xf003, 9)
+                .custom(0xf000, 10))
+        .recv_frame(frames::settings_ack())
+        .close();
+
+    let client = client::Builder::new()
+        .custom_setting(0xf002, 1)
+        .wait_for_remote_settings(true)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(client, conn)| {
+            let settings = client.remote_settings().expect("remote settings");
+            assert_eq!(
+                settings.custom_settings().collect::<Vec<_>>(),
+                [(0xf000, 7), (0xf001, 8)]
+            );
+
+            conn.expect("client").map(move |_| {
+                // Updated settings are replaced, new ones are added.
+                let settings = client.remote_settings().expect("remote settings");
+                assert_eq!(
+                    settings.custom_settings().collect::<Vec<_>>(),
+                    [(0xf000, 10), (0xf001, 8), (0xf003, 9)]
+                );
+                assert_eq!(settings.custom_setting(0xf003), Some(9));
+            })
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+fn recv_custom_settings_are_capped() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    // A SETTINGS frame with 40 custom settings.
+    let mut settings = vec![0, 0, 240, 4, 0, 0, 0, 0, 0];
+    for id in 0xf000..0xf028u16 {
+        settings.extend_from_slice(&[(id >> 8) as u8, id as u8, 0, 0, 0, 1]);
+    }
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_bytes(&settings)
+        .recv_frame(frames::settings_ack())
+        // Settings that are already known can still be updated.
+        .send_frame(
+            frames::settings()
+                .custom(0xf000, 2))
+        .recv_frame(frames::settings_ack())
+        .close();
+
+    let client = client::handshake(io)
+        .expect("handshake")
+        .and_then(|(client, conn)| {
+            conn.expect("client").map(move |_| {
+                let settings = client.remote_settings().expect("remote settings");
+                assert_eq!(settings.custom_settings().count(), frame::MAX_UNKNOWN_SETTINGS);
+                assert_eq!(settings.custom_setting(0xf000), Some(2));
+                assert_eq!(settings.custom_setting(0xf01f), Some(1));
+                assert_eq!(settings.custom_setting(0xf020), None);
+            })
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+#[should_panic]
+fn read_buffer_size_zero_panics() {
+    client::Builder::new().read_buffer_size(0);
+}
+
+#[test]
+fn send_request_available_streams() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake_with_settings(
+            frames::settings()
+                .max_concurrent_streams(2))
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .recv_frame(
+            frames::headers(3)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .send_frame(frames::headers(1).response(200).eos())
+        .recv_frame(
+            frames::headers(5)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .send_frame(frames::headers(3).response(200).eos())
+        .send_frame(frames::headers(5).response(200).eos())
+        .close();
+
+    let notify = MockNotify::new();
+
+    let client = client::Builder::new()
+        .wait_for_remote_settings(true)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(move |(mut client, conn)| {
+            assert_eq!(client.available_streams(), 2);
+
+            let resp1 = client.get("https://example.com/");
+            let resp3 = client.get("https://example.com/");
+            assert_eq!(client.available_streams(), 0);
+
+            let poll = notify.with(|| client.poll_available_streams());
+            assert!(poll.unwrap().is_not_ready());
+
+            conn.drive(resp1).and_then(move |(conn, resp)| {
+                assert_eq!(resp.status(), StatusCode::OK);
+
+                // The first stream completed, freeing up a slot.
+                assert!(notify.is_notified());
+                assert_eq!(client.available_streams(), 1);
+
+                let poll = notify.with(|| client.poll_available_streams());
+                assert_eq!(poll.unwrap(), futures::Async::Ready(1));
+
+                let resp5 = client.get("https://example.com/");
+                assert_eq!(client.available_streams(), 0);
+
+                conn.drive(resp3.join(resp5))
+                    .and_then(|(conn, _)| conn.expect("client"))
+            })
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
 #[test]
 fn request_without_path() {
     let _ = ::env_logger::try_init();
@@ -1198,6 +1647,110 @@ fn malformed_response_headers_dont_unlink_stream() {
     client.join(srv).wait().unwrap();
 }
 
+#[test]
+fn send_crumbled_cookies() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let mut cookies = HeaderMap::new();
+    cookies.append("cookie", "a=1".parse().unwrap());
+    cookies.append("cookie", "b=2".parse().unwrap());
+    cookies.append("cookie", "c=3".parse().unwrap());
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .fields(cookies)
+                .eos(),
+        )
+        .send_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let h2 = client::Builder::new()
+        .crumble_cookies(true)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(mut client, h2)| {
+            let request = Request::builder()
+                .method(Method::GET)
+                .uri("https://example.com/")
+                .header("cookie", "a=1; b=2")
+                .header("cookie", "c=3")
+                .body(())
+                .unwrap();
+
+            let (response, _) = client.send_request(request, true).unwrap();
+            h2.drive(response)
+                .map(move |(h2, _)| (client, h2))
+        });
+
+    h2.join(srv).wait().expect("wait");
+}
+
+#[test]
+fn hpack_stats() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .send_frame(frames::headers(1).response(200).eos())
+        .recv_frame(
+            frames::headers(3)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .send_frame(frames::headers(3).response(200).eos())
+        .close();
+
+    fn request() -> Request<()> {
+        Request::builder()
+            .method(Method::GET)
+            .uri("https://example.com/")
+            .body(())
+            .unwrap()
+    }
+
+    let h2 = client::handshake(io)
+        .expect("handshake")
+        .and_then(|(mut client, h2)| {
+            let (response, _) = client.send_request(request(), true).unwrap();
+            h2.drive(response)
+                .map(move |(h2, _)| (client, h2))
+        })
+        .and_then(|(mut client, h2)| {
+            let (response, _) = client.send_request(request(), true).unwrap();
+            h2.drive(response)
+                .map(move |(h2, _)| (client, h2))
+        })
+        .map(|(client, h2)| {
+            // The authority is taken from the dynamic table the second time
+            // around.
+            let sent = h2.hpack_encoder_stats();
+            assert_eq!(sent.headers(), 8);
+            assert_eq!(sent.dynamic_table_hits(), 1);
+            assert_eq!(sent.table_len(), 1);
+
+            // Both responses are a single static table index.
+            let received = h2.hpack_decoder_stats();
+            assert_eq!(received.headers(), 2);
+            assert_eq!(received.encoded_bytes(), 2);
+
+            (client, h2)
+        });
+
+    h2.join(srv).wait().expect("wait");
+}
+
 const SETTINGS: &'static [u8] = &[0, 0, 0, 4, 0, 0, 0, 0, 0];
 const SETTINGS_ACK: &'static [u8] = &[0, 0, 0, 4, 1, 0, 0, 0, 0];
 
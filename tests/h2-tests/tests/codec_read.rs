@@ -4,6 +4,7 @@ extern crate h2_support;
 use h2_support::prelude::*;
 
 use std::error::Error;
+use std::io;
 
 #[test]
 fn read_none() {
@@ -189,6 +190,183 @@ fn read_continuation_frames() {
 
 }
 
+#[test]
+fn read_continuation_splitting_header_field() {
+    let mut codec = raw_codec! {
+        read => [
+            // HEADERS, without END_HEADERS, ending in the middle of a
+            // literal header field name
+            0, 0, 11, 1, 0, 0, 0, 0, 1,
+            0x88, 0, 11, "x-long-n",
+            // CONTINUATION
+            0, 0, 14, 9, 0x4, 0, 0, 0, 1,
+            "ame", 10, "0123456789",
+            // Same, with fewer bytes left over than the size of a frame head
+            0, 0, 5, 1, 0, 0, 0, 0, 3,
+            0x88, 0, 3, "x-",
+            // CONTINUATION
+            0, 0, 12, 9, 0x4, 0, 0, 0, 3,
+            "b", 10, "9876543210",
+        ];
+    };
+
+    let headers = poll_frame!(Headers, codec);
+    assert_eq!(headers.stream_id(), 1);
+    assert!(headers.is_end_headers());
+    assert_eq!(headers.fields()["x-long-name"], "0123456789");
+
+    let headers = poll_frame!(Headers, codec);
+    assert_eq!(headers.stream_id(), 3);
+    assert_eq!(headers.fields()["x-b"], "9876543210");
+
+    assert_closed!(codec);
+}
+
+#[test]
+fn read_continuation_splitting_header_value() {
+    let mut codec = raw_codec! {
+        read => [
+            // HEADERS, without END_HEADERS, ending in the middle of a
+            // literal header field value
+            0, 0, 13, 1, 0, 0, 0, 0, 1,
+            0x88, 0, 4, "x-id", 10, "01234",
+            // CONTINUATION
+            0, 0, 5, 9, 0x4, 0, 0, 0, 1,
+            "56789",
+        ];
+    };
+
+    let headers = poll_frame!(Headers, codec);
+    assert_eq!(headers.stream_id(), 1);
+    assert_eq!(headers.fields()["x-id"], "0123456789");
+
+    assert_closed!(codec);
+}
+
+// ===== PRIORITY =====
+
+#[test]
+fn read_priority_frames() {
+    let mut codec = raw_codec! {
+        read => [
+            // PRIORITY, exclusive dependency on stream 1, weight 16
+            0, 0, 5, 2, 0, 0, 0, 0, 3,
+            0x80, 0, 0, 1, 15,
+            // HEADERS, END_STREAM | END_HEADERS | PRIORITY
+            0, 0, 6, 1, 0x25, 0, 0, 0, 5,
+            0, 0, 0, 3, 255,
+            0x88, // HPACK :status=200
+        ];
+    };
+
+    let priority = poll_frame!(Priority, codec);
+    assert_eq!(priority.stream_id(), 3);
+
+    let dependency = priority.dependency();
+    assert_eq!(dependency.dependency_id(), 1);
+    assert_eq!(dependency.weight(), 15);
+    assert!(dependency.is_exclusive());
+
+    let headers = poll_frame!(Headers, codec);
+    assert_eq!(headers.stream_id(), 5);
+    assert!(headers.is_end_stream());
+
+    let dependency = headers.stream_dependency().unwrap();
+    assert_eq!(dependency.dependency_id(), 3);
+    assert_eq!(dependency.weight(), 255);
+    assert!(!dependency.is_exclusive());
+
+    assert_eq!(headers.pseudo().status, Some(StatusCode::OK));
+
+    assert_closed!(codec);
+}
+
+// ===== Extension =====
+
+#[test]
+fn read_extension_frame() {
+    let mut codec = raw_codec! {
+        read => [
+            0, 0, 5, 0xf0, 0x3, 0, 0, 0, 1,
+            "hello",
+            0, 0, 0, 0xf1, 0, 0, 0, 0, 0,
+        ];
+    };
+
+    let ext = poll_frame!(Extension, codec);
+    assert_eq!(ext.kind(), 0xf0);
+    assert_eq!(ext.flags(), 0x3);
+    assert_eq!(ext.stream_id(), 1);
+    assert_eq!(ext.payload(), &b"hello"[..]);
+
+    let ext = poll_frame!(Extension, codec);
+    assert_eq!(ext.kind(), 0xf1);
+    assert_eq!(ext.stream_id(), 0);
+    assert!(ext.payload().is_empty());
+
+    assert_closed!(codec);
+}
+
+#[test]
+fn read_extension_frame_interrupting_header_block() {
+    let mut codec = raw_codec! {
+        read => [
+            // HEADERS, without END_HEADERS
+            0, 0, 1, 1, 0x1, 0, 0, 0, 1,
+            0x88,
+            // Extension frames may not be interleaved with a header block
+            0, 0, 5, 0xf0, 0, 0, 0, 0, 1,
+            "hello",
+        ];
+    };
+
+    poll_err!(codec);
+}
+
+#[test]
+fn read_frames_split_across_reads() {
+    let mut codec = raw_codec! {
+        read => [
+            0, 0, 5, 0, 0,
+        ];
+        read => [
+            0, 0, 0, 1,
+            "he",
+        ];
+        read => [
+            "llo",
+            0, 0, 3, 0, 0x1, 0, 0, 0, 3,
+            "abc",
+        ];
+    };
+
+    codec.set_read_buffer_size(2);
+    assert_eq!(codec.read_buffer_size(), 2);
+
+    let data = poll_frame!(Data, codec);
+    assert_eq!(data.stream_id(), 1);
+    assert_eq!(data.payload(), &b"hello"[..]);
+
+    let data = poll_frame!(Data, codec);
+    assert_eq!(data.stream_id(), 3);
+    assert_eq!(data.payload(), &b"abc"[..]);
+    assert!(data.is_end_stream());
+
+    assert_closed!(codec);
+}
+
+#[test]
+fn read_truncated_frame() {
+    let mut codec = raw_codec! {
+        read => [
+            0, 0, 5, 0, 0, 0, 0, 0, 1,
+            "he",
+        ];
+    };
+
+    poll_err!(codec);
+}
+
 #[test]
 fn update_max_frame_len_at_rest() {
     let _ = ::env_logger::try_init();
@@ -212,3 +390,68 @@ fn update_max_frame_len_at_rest() {
         "frame with invalid size"
     );
 }
+
+#[test]
+fn read_buffer_has_room_after_frame() {
+    let _ = ::env_logger::try_init();
+
+    // The first frame leaves 5 bytes of the default 8,192 byte buffer.
+    let mut first = vec![0, 0x1f, 0xf2, 0, 0, 0, 0, 0, 1];
+    first.extend_from_slice(&[b'a'; 8_178]);
+    let second = vec![0, 0, 3, 0, 0x1, 0, 0, 0, 3, b'a', b'b', b'c'];
+
+    let mut codec: Codec<_> = Codec::new(ChunkedIo {
+        chunks: vec![first, second].into(),
+        reads: vec![],
+    });
+    let read_buffer_size = codec.read_buffer_size();
+    assert_eq!(read_buffer_size, 8_192);
+
+    poll_frame!(Data, codec);
+    poll_frame!(Data, codec);
+
+    let reads = &codec.get_ref().reads;
+    assert_eq!(reads.len(), 2);
+    assert!(reads.iter().all(|&len| len >= read_buffer_size), "reads={:?}", reads);
+}
+
+/// An I/O handle that returns one chunk per read, recording the size of the
+/// buffer passed to each read.
+#[derive(Debug)]
+struct ChunkedIo {
+    chunks: ::std::collections::VecDeque<Vec<u8>>,
+    reads: Vec<usize>,
+}
+
+impl io::Read for ChunkedIo {
+    fn read(&mut self, dst: &mut [u8]) -> io::Result<usize> {
+        self.reads.push(dst.len());
+
+        match self.chunks.pop_front() {
+            Some(chunk) => {
+                assert!(chunk.len() <= dst.len(), "read buffer too small");
+                dst[..chunk.len()].copy_from_slice(&chunk);
+                Ok(chunk.len())
+            },
+            None => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+impl AsyncRead for ChunkedIo {}
+
+impl io::Write for ChunkedIo {
+    fn write(&mut self, src: &[u8]) -> io::Result<usize> {
+        Ok(src.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsyncWrite for ChunkedIo {
+    fn shutdown(&mut self) -> futures::Poll<(), io::Error> {
+        Ok(().into())
+    }
+}
@@ -1,7 +1,13 @@
+#[macro_use]
 extern crate h2_support;
+extern crate iovec;
 
 use h2_support::prelude::*;
 
+use iovec::IoVec;
+
+use std::io::{self, Cursor};
+
 #[test]
 fn write_continuation_frames() {
     // An invalid dependency ID results in a stream level error. The hpack
@@ -61,3 +67,120 @@ fn write_continuation_frames() {
 
     client.join(srv).wait().expect("wait");
 }
+
+#[test]
+fn write_priority_frames() {
+    use h2::frame::{Frame, Headers, Priority, StreamDependency};
+
+    let _ = ::env_logger::try_init();
+
+    let mut codec = raw_codec! {
+        write => [
+            // PRIORITY, exclusive dependency on stream 1, weight 16
+            0, 0, 5, 2, 0, 0, 0, 0, 3,
+            0x80, 0, 0, 1, 15,
+            // HEADERS, END_STREAM | END_HEADERS | PRIORITY
+            0, 0, 6, 1, 0x25, 0, 0, 0, 5,
+            0, 0, 0, 3, 255,
+            0x88, // HPACK :status=200
+        ];
+    };
+
+    let dependency = StreamDependency::new(1.into(), 15, true);
+    let priority = Priority::new(3.into(), dependency);
+    assert_eq!(priority.stream_id(), 3);
+    assert_eq!(priority.dependency().dependency_id(), 1);
+
+    let mut headers = Headers::response(5.into(), Response::new(()));
+    headers.set_stream_dependency(Some(StreamDependency::new(3.into(), 255, false)));
+    headers.set_end_stream();
+
+    assert!(codec.start_send(Frame::Priority(priority)).unwrap().is_ready());
+    assert!(codec.start_send(headers.into()).unwrap().is_ready());
+    assert!(codec.poll_complete().unwrap().is_ready());
+}
+
+#[test]
+fn write_data_frames_vectored() {
+    use h2::frame::{Data, Frame, Ping};
+
+    let _ = ::env_logger::try_init();
+
+    let mut codec: Codec<_> = Codec::new(VectoredIo::default());
+
+    let first = Bytes::from(vec![b'a'; 1_000]);
+    let second = Bytes::from(vec![b'b'; 1_000]);
+
+    let mut padded = Data::new(3.into(), Cursor::new(second.clone()));
+    padded.set_padding(4);
+
+    assert!(codec.start_send(Data::new(1.into(), Cursor::new(first.clone())).into()).unwrap().is_ready());
+    assert!(codec.start_send(padded.into()).unwrap().is_ready());
+    assert!(codec.start_send(Frame::Ping(Ping::new([1; 8]))).unwrap().is_ready());
+    assert!(codec.poll_complete().unwrap().is_ready());
+
+    let mut expected = vec![];
+    expected.extend_from_slice(&[0, 0x03, 0xe8, 0, 0, 0, 0, 0, 1]);
+    expected.extend_from_slice(&first);
+    // PADDED, with the pad length
+    expected.extend_from_slice(&[0, 0x03, 0xed, 0, 0x8, 0, 0, 0, 3, 4]);
+    expected.extend_from_slice(&second);
+    expected.extend_from_slice(&[0; 4]);
+    expected.extend_from_slice(&[0, 0, 8, 6, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1]);
+
+    let io = codec.get_ref();
+
+    // The payloads are not copied, and everything is written at once.
+    assert_eq!(io.writes, vec![5]);
+    assert_eq!(io.written, expected);
+}
+
+/// An I/O handle that only accepts vectored writes, recording the number of
+/// buffers of each.
+#[derive(Debug, Default)]
+struct VectoredIo {
+    writes: Vec<usize>,
+    written: Vec<u8>,
+}
+
+impl io::Read for VectoredIo {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::WouldBlock.into())
+    }
+}
+
+impl AsyncRead for VectoredIo {}
+
+impl io::Write for VectoredIo {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        panic!("unexpected non-vectored write");
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsyncWrite for VectoredIo {
+    fn shutdown(&mut self) -> futures::Poll<(), io::Error> {
+        Ok(().into())
+    }
+
+    fn write_buf<B: Buf>(&mut self, buf: &mut B) -> futures::Poll<usize, io::Error> {
+        let n = {
+            static DUMMY: &[u8] = &[0];
+            let mut bufs = [<&IoVec>::from(DUMMY); 64];
+            let n = buf.bytes_vec(&mut bufs);
+
+            for b in &bufs[..n] {
+                self.written.extend_from_slice(b);
+            }
+
+            self.writes.push(n);
+            bufs[..n].iter().map(|b| b.len()).sum()
+        };
+
+        buf.advance(n);
+        Ok(n.into())
+    }
+}
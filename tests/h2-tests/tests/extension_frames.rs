@@ -0,0 +1,165 @@
+extern crate h2_support;
+
+use h2_support::prelude::*;
+use h2::ExtensionFrame;
+
+#[test]
+fn recv_extension_frames() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(frames::extension(0xf0, 0, "hello"))
+        .send_frame(frames::extension(0xf1, 1, "world"))
+        .close();
+
+    let srv = server::handshake(io)
+        .expect("handshake")
+        .and_then(|mut srv| {
+            let frames = srv.extension_frames().expect("extension_frames");
+
+            // Can only be taken once
+            assert!(srv.extension_frames().is_none());
+
+            srv.into_future()
+                .unwrap()
+                .and_then(move |(req, _)| {
+                    assert!(req.is_none());
+
+                    // The connection has been dropped, ending the stream.
+                    frames.collect().unwrap()
+                })
+        })
+        .map(|frames| {
+            assert_eq!(frames.len(), 2);
+
+            assert_eq!(frames[0].kind(), 0xf0);
+            assert_eq!(frames[0].payload(), "hello");
+
+            assert_eq!(frames[1].kind(), 0xf1);
+            assert_eq!(frames[1].payload(), "world");
+        });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn recv_extension_frames_over_limit_dropped() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let mut client: Box<dyn Future<Item = _, Error = _>> = Box::new(
+        client
+            .assert_server_handshake()
+            .unwrap()
+            .recv_settings()
+    );
+
+    for i in 0..130u32 {
+        client = Box::new(client.send_frame(frames::extension(0xf0, 0, i.to_string())));
+    }
+
+    let client = client.close();
+
+    let srv = server::handshake(io)
+        .expect("handshake")
+        .and_then(|mut srv| {
+            let frames = srv.extension_frames().expect("extension_frames");
+
+            srv.into_future()
+                .unwrap()
+                .and_then(move |(req, _)| {
+                    assert!(req.is_none());
+                    frames.collect().unwrap()
+                })
+        })
+        .map(|frames| {
+            // Frames past the limit are dropped, the first ones are kept.
+            assert_eq!(frames.len(), 128);
+            assert_eq!(frames[0].payload(), "0");
+            assert_eq!(frames[127].payload(), "127");
+        });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn recv_extension_frames_ignored_by_default() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(frames::extension(0xf0, 0, "hello"))
+        .send_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .recv_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let srv = server::handshake(io).expect("handshake").and_then(|srv| {
+        srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+            let (req, mut stream) = reqstream.unwrap();
+            assert_eq!(req.method(), &http::Method::GET);
+
+            let rsp = Response::new(());
+            stream.send_response(rsp, true).unwrap();
+
+            srv.into_future().unwrap().map(|_| ())
+        })
+    });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn send_extension_frames() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(frames::extension(0xf0, 0, "hello"))
+        .recv_frame(frames::extension(0xf1, 1, "world"))
+        .close();
+
+    // Wait for the server's SETTINGS, so that they are acknowledged before the
+    // extension frames are sent.
+    let client = client::Builder::new()
+        .wait_for_remote_settings(true)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(client, mut conn)| {
+            let mut frames = conn.extension_frames().expect("extension_frames");
+
+            conn.send_extension_frame(ExtensionFrame::new(0xf0, 0, 0, "hello".into()))
+                .unwrap();
+
+            frames.send_extension_frame(ExtensionFrame::new(0xf1, 0, 1, "world".into()))
+                .unwrap();
+
+            // The payload must fit in a frame of the minimum size.
+            let err = frames
+                .send_extension_frame(ExtensionFrame::new(0xf0, 0, 0, vec![0; 16_385].into()))
+                .unwrap_err();
+            assert_eq!(err.to_string(), "user error: payload too big");
+
+            conn.expect("client").map(|c| (client, frames, c))
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+#[should_panic]
+fn extension_frame_with_known_type_panics() {
+    ExtensionFrame::new(0x0, 0, 1, "hello".into());
+}
@@ -0,0 +1,311 @@
+extern crate h2_support;
+
+use h2_support::prelude::*;
+use h2::FlushPolicy;
+
+use futures::{future, Poll};
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+
+/// Records the bytes written between flushes.
+#[derive(Debug)]
+struct Flushes<T> {
+    inner: T,
+    pending: Vec<u8>,
+    flushed: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl<T> Flushes<T> {
+    fn new(inner: T) -> (Self, Arc<Mutex<Vec<Vec<u8>>>>) {
+        let flushed = Arc::new(Mutex::new(vec![]));
+
+        let io = Flushes {
+            inner,
+            pending: vec![],
+            flushed: flushed.clone(),
+        };
+
+        (io, flushed)
+    }
+}
+
+impl<T: Read> Read for Flushes<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<T: AsyncRead> AsyncRead for Flushes<T> {}
+
+impl<T: Write> Write for Flushes<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.pending.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()?;
+
+        if !self.pending.is_empty() {
+            let pending = ::std::mem::replace(&mut self.pending, vec![]);
+            self.flushed.lock().unwrap().push(pending);
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: AsyncWrite> AsyncWrite for Flushes<T> {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        self.inner.shutdown()
+    }
+}
+
+/// Returns the number of frames in each flush.
+fn frames_per_flush(flushed: &[Vec<u8>]) -> Vec<usize> {
+    const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+    flushed.iter()
+        .map(|bytes| {
+            let mut bytes = &bytes[..];
+            let mut n = 0;
+
+            if bytes.starts_with(PREFACE) {
+                bytes = &bytes[PREFACE.len()..];
+            }
+
+            while !bytes.is_empty() {
+                let len = ((bytes[0] as usize) << 16) | ((bytes[1] as usize) << 8) | bytes[2] as usize;
+                bytes = &bytes[9 + len..];
+                n += 1;
+            }
+
+            n
+        })
+        .collect()
+}
+
+fn post<T>(client: client::Handshake<T>) -> Box<Future<Item = (), Error = ()>>
+where
+    T: AsyncRead + AsyncWrite + fmt::Debug + 'static,
+{
+    let fut = client
+        .expect("handshake")
+        .and_then(|(mut client, conn)| {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("https://http2.akamai.com/")
+                .body(())
+                .unwrap();
+
+            let (response, mut stream) = client.send_request(request, false).unwrap();
+            stream.send_data("hello".into(), true).unwrap();
+
+            conn.drive(response.expect("response"))
+                .and_then(|(conn, _)| conn.expect("client"))
+        });
+
+    Box::new(fut)
+}
+
+fn post_server(srv: mock::Handle) -> Box<Future<Item = mock::Handle, Error = ()>> {
+    let fut = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("POST", "https://http2.akamai.com/")
+        )
+        .recv_frame(frames::data(1, "hello").eos())
+        .send_frame(frames::headers(1).response(200).eos());
+
+    Box::new(fut)
+}
+
+#[test]
+fn flush_when_idle_by_default() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+    let (io, flushed) = Flushes::new(io);
+
+    post(client::handshake(io)).join(post_server(srv).close()).wait().expect("wait");
+
+    // HEADERS and DATA are flushed together.
+    let frames = frames_per_flush(&flushed.lock().unwrap());
+    assert!(frames.iter().any(|&n| n > 1), "frames={:?}", frames);
+}
+
+#[test]
+fn flush_immediately() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+    let (io, flushed) = Flushes::new(io);
+
+    let client = client::Builder::new()
+        .flush_policy(FlushPolicy::Immediate)
+        .handshake::<_, Bytes>(io);
+
+    post(client).join(post_server(srv).close()).wait().expect("wait");
+
+    // SETTINGS, HEADERS, DATA and the SETTINGS ack, in some order.
+    let frames = frames_per_flush(&flushed.lock().unwrap());
+    assert_eq!(frames, [1, 1, 1, 1]);
+}
+
+#[test]
+fn manual_flush() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+    let (io, flushed) = Flushes::new(io);
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://http2.akamai.com/")
+                .eos()
+        )
+        .send_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let client = client::Builder::new()
+        .flush_policy(FlushPolicy::Manual)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(move |(mut client, mut conn)| future::lazy(move || {
+            // The initial SETTINGS frame is flushed regardless.
+            assert!(conn.poll().unwrap().is_not_ready());
+            let num_flushed = flushed.lock().unwrap().len();
+
+            let request = Request::builder()
+                .uri("https://http2.akamai.com/")
+                .body(())
+                .unwrap();
+
+            let (response, _) = client.send_request(request, true).unwrap();
+
+            // The request is held back until the connection is flushed.
+            assert!(conn.poll().unwrap().is_not_ready());
+            assert_eq!(flushed.lock().unwrap().len(), num_flushed);
+
+            conn.flush();
+            assert!(conn.poll().unwrap().is_not_ready());
+            assert_eq!(flushed.lock().unwrap().len(), num_flushed + 1);
+
+            conn.drive(response.expect("response"))
+                .and_then(|(conn, _)| conn.expect("client"))
+        }));
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+fn manual_flush_sends_acks() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(frames::ping([1; 8]))
+        .recv_frame(frames::ping([1; 8]).pong())
+        .send_frame(frames::settings().max_concurrent_streams(10))
+        .recv_frame(frames::settings_ack())
+        .close();
+
+    // Nothing is flushed by the user, the acks must be sent regardless.
+    let client = client::Builder::new()
+        .flush_policy(FlushPolicy::Manual)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(client, conn)| {
+            conn.expect("client").map(move |()| drop(client))
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+fn manual_flush_from_request_handle() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://http2.akamai.com/")
+                .eos()
+        )
+        .send_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let client = client::Builder::new()
+        .flush_policy(FlushPolicy::Manual)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(mut client, conn)| {
+            let request = Request::builder()
+                .uri("https://http2.akamai.com/")
+                .body(())
+                .unwrap();
+
+            // The connection is driven on its own, as if it were spawned, so
+            // it can only be flushed through the request handle.
+            let response = future::lazy(move || {
+                let (response, _) = client.send_request(request, true).unwrap();
+                client.flush();
+
+                response.expect("response").map(move |_| drop(client))
+            });
+
+            conn.expect("client").join(response)
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+fn manual_flush_graceful_shutdown() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .recv_frame(frames::go_away(2147483647))
+        .recv_frame(frames::ping(frame::Ping::SHUTDOWN))
+        .recv_frame(frames::headers(1).response(200).eos())
+        .send_frame(frames::ping(frame::Ping::SHUTDOWN).pong())
+        .recv_frame(frames::go_away(1))
+        .recv_eof();
+
+    // Nothing is flushed by the user, the GOAWAY and PING frames must be
+    // sent regardless.
+    let srv = server::Builder::new()
+        .flush_policy(FlushPolicy::Manual)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|srv| srv.into_future().unwrap())
+        .and_then(|(reqstream, mut srv)| {
+            let (_, mut stream) = reqstream.unwrap();
+
+            srv.graceful_shutdown();
+            stream.send_response(Response::new(()), true).unwrap();
+
+            srv.into_future().unwrap().map(|(req, _)| assert!(req.is_none()))
+        });
+
+    srv.join(client).wait().expect("wait");
+}
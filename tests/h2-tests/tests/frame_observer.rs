@@ -0,0 +1,184 @@
+extern crate h2_support;
+
+use h2_support::prelude::*;
+use h2::{ConnectionEvent, FrameObserver, ObservedFrame, Redaction};
+
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Default)]
+struct Recorder {
+    redaction: Option<Redaction>,
+    recv: Arc<Mutex<Vec<ObservedFrame>>>,
+    send: Arc<Mutex<Vec<ObservedFrame>>>,
+    events: Arc<Mutex<Vec<ConnectionEvent>>>,
+}
+
+impl FrameObserver for Recorder {
+    fn recv_frame(&self, frame: &ObservedFrame) {
+        self.recv.lock().unwrap().push(frame.clone());
+    }
+
+    fn send_frame(&self, frame: &ObservedFrame) {
+        self.send.lock().unwrap().push(frame.clone());
+    }
+
+    fn event(&self, event: &ConnectionEvent) {
+        self.events.lock().unwrap().push(event.clone());
+    }
+
+    fn redaction(&self) -> Redaction {
+        self.redaction.unwrap_or(Redaction::Sensitive)
+    }
+}
+
+fn headers_of(frames: &[ObservedFrame]) -> Vec<(String, String)> {
+    let frame = frames.iter()
+        .find(|f| f.kind() == 0x1)
+        .expect("HEADERS frame");
+
+    frame.headers().expect("headers").to_vec()
+}
+
+fn header(name: &str, value: &str) -> (String, String) {
+    (name.to_string(), value.to_string())
+}
+
+#[test]
+fn client_observes_frames_and_events() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .field("authorization", "secret")
+                .field("x-visible", "yes")
+                .eos(),
+        )
+        .send_frame(frames::headers(1).response(200).eos())
+        .send_frame(frames::go_away(1))
+        .close();
+
+    let recorder = Recorder::default();
+
+    let client = client::Builder::new()
+        .frame_observer(recorder.clone())
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(mut client, conn)| {
+            let request = Request::builder()
+                .uri("https://example.com/")
+                .header("authorization", "secret")
+                .header("x-visible", "yes")
+                .body(())
+                .unwrap();
+
+            let response = client.send_request(request, true)
+                .unwrap()
+                .0
+                .map(|rsp| {
+                    assert_eq!(rsp.status(), StatusCode::OK);
+                });
+
+            conn.expect("client").join(response.expect("response"))
+        });
+
+    client.join(srv).wait().expect("wait");
+
+    let send = recorder.send.lock().unwrap();
+
+    // The preface SETTINGS frame goes first.
+    assert_eq!(send[0].kind(), 0x4);
+    assert_eq!(format!("{:?}", send[0].stream_id()), "StreamId(0)");
+
+    let headers = send.iter().find(|f| f.kind() == 0x1).unwrap();
+    assert_eq!(format!("{:?}", headers.stream_id()), "StreamId(1)");
+    // END_STREAM | END_HEADERS
+    assert_eq!(headers.flags(), 0x5);
+    assert!(headers.payload_len() > 0);
+
+    let sent = headers_of(&send);
+    assert!(sent.contains(&header(":method", "GET")));
+    assert!(sent.contains(&header(":path", "/")));
+    assert!(sent.contains(&header("authorization", "[redacted]")));
+    assert!(sent.contains(&header("x-visible", "yes")));
+
+    let recv = recorder.recv.lock().unwrap();
+    assert_eq!(headers_of(&recv), vec![header(":status", "200")]);
+
+    // GOAWAY
+    assert!(recv.iter().any(|f| f.kind() == 0x7));
+
+    let events = recorder.events.lock().unwrap();
+    assert!(events.iter().any(|e| match *e {
+        ConnectionEvent::LocalSettingsApplied => true,
+        _ => false,
+    }));
+    assert!(events.iter().any(|e| match *e {
+        ConnectionEvent::RemoteSettingsApplied => true,
+        _ => false,
+    }));
+    assert!(events.iter().any(|e| match *e {
+        ConnectionEvent::GoAwayReceived { reason, .. } => reason == Reason::NO_ERROR,
+        _ => false,
+    }));
+}
+
+#[test]
+fn server_redacts_all_header_values() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .field("x-visible", "yes")
+                .eos(),
+        )
+        .recv_frame(frames::headers(1).response(200))
+        .recv_frame(frames::data(1, "hello").eos())
+        .close();
+
+    let recorder = Recorder {
+        redaction: Some(Redaction::All),
+        .. Recorder::default()
+    };
+
+    let srv = server::Builder::new()
+        .frame_observer(recorder.clone())
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|srv| {
+            srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+                let (_, mut respond) = reqstream.unwrap();
+
+                let rsp = Response::new(());
+                let mut stream = respond.send_response(rsp, false).unwrap();
+                stream.send_data("hello".into(), true).unwrap();
+
+                srv.into_future().unwrap().map(|(req, _)| assert!(req.is_none()))
+            })
+        });
+
+    srv.join(client).wait().expect("wait");
+
+    let recv = recorder.recv.lock().unwrap();
+    let received = headers_of(&recv);
+    assert!(received.contains(&header(":method", "[redacted]")));
+    assert!(received.contains(&header("x-visible", "[redacted]")));
+
+    let send = recorder.send.lock().unwrap();
+    assert_eq!(headers_of(&send), vec![header(":status", "[redacted]")]);
+
+    let data = send.iter().find(|f| f.kind() == 0x0).expect("DATA frame");
+    assert_eq!(data.payload_len(), 5);
+    // END_STREAM
+    assert_eq!(data.flags(), 0x1);
+}
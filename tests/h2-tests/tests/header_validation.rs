@@ -0,0 +1,389 @@
+extern crate h2_support;
+
+use h2_support::prelude::*;
+
+fn request(id: u32) -> frames::Mock<frame::Headers> {
+    frames::headers(id).request("GET", "https://example.com/")
+}
+
+#[test]
+fn strict_rejects_host_mismatch() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(request(1).field("host", "example.org").eos())
+        .send_frame(request(3).field("host", "EXAMPLE.com").eos())
+        .recv_frame(frames::reset(1).protocol_error())
+        .recv_frame(frames::headers(3).response(200).eos())
+        .close();
+
+    let srv = server::Builder::new()
+        .header_validation(HeaderValidation::Strict)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|srv| {
+            srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+                let (req, mut stream) = reqstream.unwrap();
+
+                // Hosts are compared without regard to case.
+                assert_eq!(req.headers()["host"], "EXAMPLE.com");
+
+                let rsp = http::Response::builder().status(200).body(()).unwrap();
+                stream.send_response(rsp, true).unwrap();
+
+                srv.into_future().unwrap().map(|_| ())
+            })
+        });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn strict_rejects_missing_path() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let mut headers: frame::Headers = request(1).eos().into();
+    headers.pseudo_mut().path = None;
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(headers)
+        .recv_frame(frames::reset(1).protocol_error())
+        .close();
+
+    let srv = server::Builder::new()
+        .header_validation(HeaderValidation::Strict)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|srv| srv.into_future().unwrap().map(|_| ()));
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn strict_rejects_uppercase_names() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_bytes(&[
+            // 14 byte frame
+            0, 0, 14,
+            // type: HEADERS
+            1,
+            // flags: END_STREAM | END_HEADERS
+            5,
+            // stream identifier: 1
+            0, 0, 0, 1,
+            // :method GET, :scheme https, :path /
+            0x82, 0x87, 0x84,
+            // X-Foo: bar, without indexing
+            0, 5, b'X', b'-', b'F', b'o', b'o', 3, b'b', b'a', b'r',
+        ])
+        .recv_frame(frames::reset(1).protocol_error())
+        // Only the stream is reset, the connection is still usable.
+        .send_frame(request(3).eos())
+        .recv_frame(frames::headers(3).response(200).eos())
+        .close();
+
+    let srv = server::Builder::new()
+        .header_validation(HeaderValidation::Strict)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|srv| {
+            srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+                let (req, mut stream) = reqstream.unwrap();
+
+                assert_eq!(req.uri(), "https://example.com/");
+
+                let rsp = http::Response::builder().status(200).body(()).unwrap();
+                stream.send_response(rsp, true).unwrap();
+
+                srv.into_future().unwrap().map(|_| ())
+            })
+        });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn standard_accepts_host_mismatch() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(request(1).field("host", "example.org").eos())
+        .recv_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    // Only `HeaderValidation::Strict` checks the host.
+    let srv = server::handshake(io).expect("handshake").and_then(|srv| {
+        srv.for_each(|(req, mut stream)| {
+            assert_eq!(req.uri().authority_part().unwrap(), "example.com");
+
+            let rsp = http::Response::builder().status(200).body(()).unwrap();
+            stream.send_response(rsp, true).unwrap();
+
+            Ok(())
+        }).map_err(|e| panic!("err={:?}", e))
+    });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn lenient_accepts_request_without_scheme() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let mut headers: frame::Headers = request(1).eos().into();
+    headers.pseudo_mut().scheme = None;
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(headers)
+        .recv_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let srv = server::Builder::new()
+        .header_validation(HeaderValidation::Lenient)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|srv| {
+            srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+                let (req, mut stream) = reqstream.unwrap();
+
+                // The authority is kept in the host header.
+                assert_eq!(req.uri(), "/");
+                assert_eq!(req.headers()["host"], "example.com");
+
+                let rsp = http::Response::builder().status(200).body(()).unwrap();
+                stream.send_response(rsp, true).unwrap();
+
+                srv.into_future().unwrap().map(|_| ())
+            })
+        });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn lenient_strips_received_connection_headers() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(
+            request(1)
+                .field("connection", "keep-alive")
+                .field("keep-alive", "5")
+                .field("te", "gzip")
+                .field("x-custom", "hello")
+                .eos(),
+        )
+        .recv_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let srv = server::Builder::new()
+        .header_validation(HeaderValidation::Lenient)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|srv| {
+            srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+                let (req, mut stream) = reqstream.unwrap();
+
+                assert_eq!(req.headers().len(), 1);
+                assert_eq!(req.headers()["x-custom"], "hello");
+
+                let rsp = http::Response::builder().status(200).body(()).unwrap();
+                stream.send_response(rsp, true).unwrap();
+
+                srv.into_future().unwrap().map(|_| ())
+            })
+        });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn lenient_accepts_uppercase_names_and_late_pseudo_headers() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_bytes(&[
+            // 14 byte frame
+            0, 0, 14,
+            // type: HEADERS
+            1,
+            // flags: END_STREAM | END_HEADERS
+            5,
+            // stream identifier: 1
+            0, 0, 0, 1,
+            // :method GET, :scheme https
+            0x82, 0x87,
+            // X-Foo: bar, without indexing
+            0, 5, b'X', b'-', b'F', b'o', b'o', 3, b'b', b'a', b'r',
+            // :path /, after a regular header field
+            0x84,
+        ])
+        .recv_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let srv = server::Builder::new()
+        .header_validation(HeaderValidation::Lenient)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|srv| {
+            srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+                let (req, mut stream) = reqstream.unwrap();
+
+                assert_eq!(req.uri().path(), "/");
+                assert_eq!(req.headers()["x-foo"], "bar");
+
+                let rsp = http::Response::builder().status(200).body(()).unwrap();
+                stream.send_response(rsp, true).unwrap();
+
+                srv.into_future().unwrap().map(|_| ())
+            })
+        });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn strict_host_matches_default_port() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(request(1).field("host", "example.com:8443").eos())
+        .send_frame(request(3).field("host", "example.com:443").eos())
+        .recv_frame(frames::reset(1).protocol_error())
+        .recv_frame(frames::headers(3).response(200).eos())
+        .close();
+
+    let srv = server::Builder::new()
+        .header_validation(HeaderValidation::Strict)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|srv| {
+            srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+                let (req, mut stream) = reqstream.unwrap();
+
+                // 443 is the default port of https.
+                assert_eq!(req.headers()["host"], "example.com:443");
+
+                let rsp = http::Response::builder().status(200).body(()).unwrap();
+                stream.send_response(rsp, true).unwrap();
+
+                srv.into_future().unwrap().map(|_| ())
+            })
+        });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn strict_sends_host_mismatch() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    // Only received requests are checked, it's up to the peer to reject it.
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .field("host", "example.org")
+                .eos(),
+        )
+        .send_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let client = client::Builder::new()
+        .header_validation(HeaderValidation::Strict)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(mut client, h2)| {
+            let request = Request::builder()
+                .uri("https://example.com/")
+                .header("host", "example.org")
+                .body(())
+                .unwrap();
+
+            let (response, _) = client.send_request(request, true).unwrap();
+
+            h2.drive(response.expect("response"))
+                .and_then(|(h2, _)| h2.expect("client"))
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+fn lenient_strips_sent_connection_headers() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .field("te", "trailers")
+                .eos(),
+        )
+        .send_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let client = client::Builder::new()
+        .header_validation(HeaderValidation::Lenient)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(mut client, h2)| {
+            let request = Request::builder()
+                .uri("https://example.com/")
+                .header("connection", "keep-alive, te")
+                .header("keep-alive", "5")
+                .header("proxy-connection", "keep-alive")
+                .header("transfer-encoding", "chunked")
+                .header("upgrade", "h2c")
+                .header("te", "gzip")
+                .header("te", "trailers")
+                .body(())
+                .unwrap();
+
+            let (response, _) = client.send_request(request, true).unwrap();
+            h2.drive(response)
+                .map(move |(h2, _)| (client, h2))
+        });
+
+    client.join(srv).wait().expect("wait");
+}
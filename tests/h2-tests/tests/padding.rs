@@ -0,0 +1,213 @@
+extern crate h2_support;
+
+use h2_support::prelude::*;
+use h2::Padding;
+
+#[test]
+fn client_sends_padded_frames() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("POST", "https://example.com/")
+                .padding(4)
+        )
+        .recv_frame(frames::data(1, "hello").padding(4).eos())
+        .send_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let client = client::Builder::new()
+        .padding(Padding::Fixed(4))
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(mut client, conn)| {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("https://example.com/")
+                .body(())
+                .unwrap();
+
+            let (response, mut stream) = client.send_request(request, false).unwrap();
+            stream.send_data("hello".into(), true).unwrap();
+
+            conn.expect("client")
+                .join(response.expect("response").map(|rsp| {
+                    assert_eq!(rsp.status(), StatusCode::OK);
+                }))
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+fn server_rounds_up_padding() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        // 1 byte of pad length, 1 byte of headers and 14 bytes of padding.
+        .recv_frame(frames::headers(1).response(200).padding(14))
+        // 1 byte of pad length, 5 bytes of data and 10 bytes of padding.
+        .recv_frame(frames::data(1, "hello").padding(10).eos())
+        .close();
+
+    let srv = server::Builder::new()
+        .padding(Padding::RoundUp(16))
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|srv| {
+            srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+                let (_, mut respond) = reqstream.unwrap();
+
+                let rsp = Response::new(());
+                let mut stream = respond.send_response(rsp, false).unwrap();
+                stream.send_data("hello".into(), true).unwrap();
+
+                srv.into_future().unwrap().map(|(req, _)| assert!(req.is_none()))
+            })
+        });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn stream_padding_overrides_connection_padding() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .recv_frame(frames::headers(1).response(200))
+        .recv_frame(frames::data(1, "hello").eos())
+        .close();
+
+    let srv = server::Builder::new()
+        .padding(Padding::Fixed(8))
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|srv| {
+            srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+                let (_, mut respond) = reqstream.unwrap();
+
+                respond.set_padding(Padding::None);
+
+                let rsp = Response::new(());
+                let mut stream = respond.send_response(rsp, false).unwrap();
+                stream.send_data("hello".into(), true).unwrap();
+
+                srv.into_future().unwrap().map(|(req, _)| assert!(req.is_none()))
+            })
+        });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn padding_limited_by_send_window() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake_with_settings(
+            frames::settings()
+                .initial_window_size(10))
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("POST", "https://example.com/")
+                .padding(255)
+        )
+        // The data leaves room in the window for 4 bytes of padding only.
+        .recv_frame(frames::data(1, "hello").padding(4).eos())
+        .send_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let client = client::Builder::new()
+        .wait_for_remote_settings(true)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(mut client, conn)| {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("https://example.com/")
+                .body(())
+                .unwrap();
+
+            let (response, mut stream) = client.send_request(request, false).unwrap();
+            stream.set_padding(Padding::Fixed(255));
+            stream.send_data("hello".into(), true).unwrap();
+
+            conn.expect("client")
+                .join(response.expect("response").map(|rsp| {
+                    assert_eq!(rsp.status(), StatusCode::OK);
+                }))
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+fn recv_padding_counts_against_flow_control() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_custom_settings(
+            frames::settings()
+                .initial_window_size(20)
+        )
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos()
+        )
+        .send_frame(frames::headers(1).response(200))
+        .send_frame(frames::data(1, "hello").padding(10))
+        // The padding is released as soon as it is received.
+        .recv_frame(frames::window_update(1, 11))
+        .send_frame(frames::data(1, "world").eos())
+        .close();
+
+    let client = client::Builder::new()
+        .initial_window_size(20)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(mut client, conn)| {
+            let request = Request::builder()
+                .uri("https://example.com/")
+                .body(())
+                .unwrap();
+
+            let (response, _) = client.send_request(request, true).unwrap();
+
+            let response = response
+                .and_then(|rsp| rsp.into_body().concat2())
+                .map(|body| {
+                    assert_eq!(&body[..], b"helloworld");
+                });
+
+            conn.expect("client").join(response.expect("response"))
+        });
+
+    client.join(srv).wait().expect("wait");
+}
@@ -438,3 +438,87 @@ fn send_data_receive_window_update() {
 
     let _ = h2.join(mock).wait().unwrap();
 }
+
+#[test]
+fn coalesce_queued_data_frames() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("POST", "https://http2.akamai.com/")
+        )
+        // The empty chunk that ends the stream is folded into the frame.
+        .recv_frame(frames::data(1, "hello world").eos())
+        .send_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let client = client::Builder::new()
+        .coalesce_data_frames(true)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(mut client, conn)| {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("https://http2.akamai.com/")
+                .body(())
+                .unwrap();
+
+            let (response, mut stream) = client.send_request(request, false).unwrap();
+
+            stream.send_data("hello".into(), false).unwrap();
+            stream.send_data(" ".into(), false).unwrap();
+            stream.send_data("world".into(), false).unwrap();
+            stream.send_data("".into(), true).unwrap();
+
+            conn.drive(response.expect("response"))
+                .and_then(|(conn, _)| conn.expect("client"))
+        });
+
+    client.join(srv).wait().expect("wait");
+}
+
+#[test]
+fn coalesce_data_frames_up_to_max_frame_size() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("POST", "https://http2.akamai.com/")
+        )
+        .recv_frame(frames::data(1, vec![0; 16_000]))
+        // The next chunk does not fit in the first frame.
+        .recv_frame(frames::data(1, vec![1; 1_000]).eos())
+        .send_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let client = client::Builder::new()
+        .coalesce_data_frames(true)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|(mut client, conn)| {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("https://http2.akamai.com/")
+                .body(())
+                .unwrap();
+
+            let (response, mut stream) = client.send_request(request, false).unwrap();
+
+            stream.send_data(vec![0; 16_000].into(), false).unwrap();
+            stream.send_data(vec![1; 600].into(), false).unwrap();
+            stream.send_data(vec![1; 400].into(), true).unwrap();
+
+            conn.drive(response.expect("response"))
+                .and_then(|(conn, _)| conn.expect("client"))
+        });
+
+    client.join(srv).wait().expect("wait");
+}
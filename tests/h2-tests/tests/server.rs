@@ -108,6 +108,45 @@ fn serve_request() {
     srv.join(client).wait().expect("wait");
 }
 
+#[test]
+fn recv_split_cookies_are_joined() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let mut cookies = HeaderMap::new();
+    cookies.append("cookie", "a=1".parse().unwrap());
+    cookies.append("cookie", "b=2".parse().unwrap());
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .fields(cookies)
+                .eos(),
+        )
+        .recv_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let srv = server::handshake(io).expect("handshake").and_then(|srv| {
+        srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+            let (req, mut stream) = reqstream.unwrap();
+
+            let cookies: Vec<_> = req.headers().get_all("cookie").iter().collect();
+            assert_eq!(cookies, ["a=1; b=2"]);
+
+            let rsp = http::Response::builder().status(200).body(()).unwrap();
+            stream.send_response(rsp, true).unwrap();
+
+            srv.into_future().unwrap().map(|_| ())
+        })
+    });
+
+    srv.join(client).wait().expect("wait");
+}
+
 #[test]
 #[ignore]
 fn accept_with_pending_connections_after_socket_close() {}
@@ -339,6 +378,55 @@ fn graceful_shutdown() {
     srv.join(client).wait().expect("wait");
 }
 
+#[test]
+fn graceful_shutdown_with_reason_and_debug_data() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .recv_frame(frames::go_away(2147483647).calm().data("slow down"))
+        .recv_frame(frames::ping(frame::Ping::SHUTDOWN))
+        .recv_frame(frames::headers(1).response(200).eos())
+        .send_frame(frames::ping(frame::Ping::SHUTDOWN).pong())
+        .recv_frame(frames::go_away(1).calm().data("slow down"))
+        .recv_eof();
+
+    let srv = server::handshake(io)
+        .expect("handshake")
+        .and_then(|srv| {
+            srv.into_future().unwrap()
+        })
+        .and_then(|(reqstream, mut srv)| {
+            let (_req, mut stream) = reqstream.unwrap();
+
+            srv.graceful_shutdown_with(
+                Reason::ENHANCE_YOUR_CALM,
+                Bytes::from_static(b"slow down"),
+            );
+
+            let rsp = http::Response::builder()
+                .status(200)
+                .body(())
+                .unwrap();
+            stream.send_response(rsp, true).unwrap();
+
+            srv.into_future().unwrap()
+        })
+        .map(|(req, _srv)| {
+            assert!(req.is_none(), "unexpected request");
+        });
+
+    srv.join(client).wait().expect("wait");
+}
+
 #[test]
 fn sends_reset_cancel_when_res_body_is_dropped() {
     let _ = ::env_logger::try_init();
@@ -659,3 +747,295 @@ fn request_without_authority() {
 
     srv.join(client).wait().expect("wait");
 }
+
+#[test]
+fn handshake_waits_for_remote_settings() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake_with_settings(
+            frames::settings()
+                .max_header_list_size(100))
+        .unwrap()
+        .recv_settings()
+        .close();
+
+    let srv = server::Builder::new()
+        .wait_for_remote_settings(true)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|srv| {
+            let settings = srv.remote_settings().expect("remote settings");
+            assert_eq!(settings.max_header_list_size(), Some(100));
+            assert_eq!(settings.max_concurrent_streams(), None);
+            assert_eq!(settings.header_table_size(), 4_096);
+
+            srv.into_future().unwrap().map(|(req, _)| assert!(req.is_none()))
+        });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn send_headers_over_peer_max_header_list_size() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake_with_settings(
+            frames::settings()
+                .max_header_list_size(200))
+        .unwrap()
+        .recv_settings()
+        .send_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/"),
+        )
+        .recv_frame(frames::headers(1).response(200))
+        .recv_frame(frames::data(1, "").eos())
+        .close();
+
+    let srv = server::handshake(io)
+        .expect("handshake")
+        .and_then(|srv| {
+            srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+                let (_, mut respond) = reqstream.unwrap();
+
+                // 32 bytes of overhead, plus the name and value.
+                let rsp = Response::builder()
+                    .status(200)
+                    .header("x-big", &"a".repeat(150)[..])
+                    .body(())
+                    .unwrap();
+                let err = respond.send_response(rsp, false).unwrap_err();
+                assert!(err.is_header_list_too_big());
+
+                // Nothing was sent, so a smaller response can be sent instead.
+                let rsp = Response::new(());
+                let mut stream = respond.send_response(rsp, false).unwrap();
+
+                let mut trailers = HeaderMap::new();
+                trailers.insert("x-big", "a".repeat(200).parse().unwrap());
+                let err = stream.send_trailers(trailers).unwrap_err();
+                assert_eq!(err.to_string(), "user error: header list too big");
+
+                stream.send_data("".into(), true).unwrap();
+
+                srv.into_future().unwrap().map(|(req, _)| assert!(req.is_none()))
+            })
+        });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn send_and_recv_custom_settings() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake_with_settings(
+            frames::settings()
+                .custom(0xf001, 8)
+                .custom(0xf000, 7))
+        .unwrap()
+        .recv_custom_settings(
+            frames::settings()
+                .custom(0xf002, 1)
+        )
+        .close();
+
+    let srv = server::Builder::new()
+        .custom_setting(0xf002, 1)
+        // Settings defined by the specification are ignored.
+        .custom_setting(0x3, 1)
+        .wait_for_remote_settings(true)
+        .handshake::<_, Bytes>(io)
+        .expect("handshake")
+        .and_then(|srv| {
+            let settings = srv.remote_settings().expect("remote settings");
+            // In the order they were received.
+            assert_eq!(
+                settings.custom_settings().collect::<Vec<_>>(),
+                [(0xf001, 8), (0xf000, 7)]
+            );
+            assert_eq!(settings.custom_setting(0xf001), Some(8));
+            assert_eq!(settings.custom_setting(0xf002), None);
+
+            srv.into_future().unwrap().map(|(req, _)| assert!(req.is_none()))
+        });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+#[should_panic]
+fn read_buffer_size_zero_panics() {
+    server::Builder::new().read_buffer_size(0);
+}
+
+#[test]
+fn send_data_beyond_content_length_is_user_error() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .recv_frame(
+            frames::headers(1)
+                .response(200)
+                .field("content-length", "5"),
+        )
+        .recv_frame(frames::data(1, &b"hello"[..]).eos())
+        .close();
+
+    let srv = server::handshake(io).expect("handshake").and_then(|srv| {
+        srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+            let (_req, mut stream) = reqstream.unwrap();
+
+            let rsp = http::Response::builder()
+                .status(200)
+                .header("content-length", "5")
+                .body(())
+                .unwrap();
+            let mut stream = stream.send_response(rsp, false).unwrap();
+
+            let err = stream.send_data("hello world".into(), true).unwrap_err();
+            assert_eq!(err.to_string(), "user error: data exceeds content-length");
+
+            // The stream is still usable with the right length.
+            stream.send_data("hello".into(), true).unwrap();
+
+            srv.into_future().unwrap().map(|_| ())
+        })
+    });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn send_eos_before_content_length_resets_stream() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .send_frame(
+            frames::headers(3)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .send_frame(
+            frames::headers(5)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        // The reset clears the frames that were queued before it.
+        .recv_frame(frames::reset(1).protocol_error())
+        .recv_frame(frames::reset(3).protocol_error())
+        .recv_frame(frames::reset(5).protocol_error())
+        .close();
+
+    let rsp = || {
+        http::Response::builder()
+            .status(200)
+            .header("content-length", "11")
+            .body(())
+            .unwrap()
+    };
+
+    let srv = server::handshake(io).expect("handshake").and_then(move |srv| {
+        srv.into_future().unwrap().and_then(move |(reqstream, srv)| {
+            let (_req, mut stream) = reqstream.unwrap();
+
+            let err = stream.send_response(rsp(), true).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "user error: stream ended before content-length was reached"
+            );
+
+            srv.into_future().unwrap().and_then(move |(reqstream, srv)| {
+                let (_req, mut stream) = reqstream.unwrap();
+
+                let mut stream = stream.send_response(rsp(), false).unwrap();
+                let err = stream.send_data("hello".into(), true).unwrap_err();
+                assert_eq!(
+                    err.to_string(),
+                    "user error: stream ended before content-length was reached"
+                );
+
+                srv.into_future().unwrap().and_then(move |(reqstream, srv)| {
+                    let (_req, mut stream) = reqstream.unwrap();
+
+                    let mut stream = stream.send_response(rsp(), false).unwrap();
+                    stream.send_data("hello".into(), false).unwrap();
+
+                    let err = stream.send_trailers(HeaderMap::new()).unwrap_err();
+                    assert_eq!(
+                        err.to_string(),
+                        "user error: stream ended before content-length was reached"
+                    );
+
+                    srv.into_future().unwrap().map(|_| ())
+                })
+            })
+        })
+    });
+
+    srv.join(client).wait().expect("wait");
+}
+
+#[test]
+fn send_content_length_of_head_response_is_not_checked() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(
+            frames::headers(1)
+                .request("HEAD", "https://example.com/")
+                .eos(),
+        )
+        .recv_frame(
+            frames::headers(1)
+                .response(200)
+                .field("content-length", "11")
+                .eos(),
+        )
+        .close();
+
+    let srv = server::handshake(io).expect("handshake").and_then(|srv| {
+        srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+            let (_req, mut stream) = reqstream.unwrap();
+
+            let rsp = http::Response::builder()
+                .status(200)
+                .header("content-length", "11")
+                .body(())
+                .unwrap();
+            stream.send_response(rsp, true).unwrap();
+
+            srv.into_future().unwrap().map(|_| ())
+        })
+    });
+
+    srv.join(client).wait().expect("wait");
+}
@@ -0,0 +1,235 @@
+extern crate h2_support;
+extern crate serde_json;
+
+use h2_support::prelude::*;
+use h2::session::{Capture, Direction, Session, Side};
+
+use std::fmt;
+
+fn kinds(session: &Session, direction: Direction) -> Vec<u8> {
+    session.frames()
+        .iter()
+        .filter(|f| f.direction() == direction)
+        .map(|f| f.kind().expect("kind"))
+        .collect()
+}
+
+fn get<T>(client: client::Handshake<T>) -> Box<Future<Item = (), Error = ()>>
+where
+    T: AsyncRead + AsyncWrite + fmt::Debug + 'static,
+{
+    let fut = client
+        .expect("handshake")
+        .and_then(|(mut client, conn)| {
+            let request = Request::builder()
+                .uri("https://example.com/")
+                .body(())
+                .unwrap();
+
+            let response = client.send_request(request, true)
+                .unwrap()
+                .0
+                .and_then(|rsp| {
+                    assert_eq!(rsp.status(), StatusCode::OK);
+                    rsp.into_body().concat2()
+                })
+                .map(|body| {
+                    assert_eq!(&body[..], b"hello");
+                });
+
+            conn.expect("client")
+                .join(response.expect("response"))
+                .map(|_| ())
+        });
+
+    Box::new(fut)
+}
+
+#[test]
+fn capture_and_replay_client_session() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .send_frame(frames::headers(1).response(200))
+        .send_frame(frames::data(1, "hello").eos())
+        .close();
+
+    let io = Capture::new(io, Side::Client);
+    let recording = io.recording();
+
+    get(client::handshake(io)).join(srv).wait().expect("wait");
+
+    let session = recording.session();
+    assert_eq!(session.side(), Side::Client);
+    assert!(session.frames().iter().all(|f| f.elapsed().is_some()));
+
+    // SETTINGS, SETTINGS ack and HEADERS
+    assert_eq!(kinds(&session, Direction::Send), [0x4, 0x4, 0x1]);
+    // SETTINGS, SETTINGS ack, HEADERS and DATA
+    assert_eq!(kinds(&session, Direction::Recv), [0x4, 0x4, 0x1, 0x0]);
+
+    // Replaying the session to a new client yields the same exchange.
+    let replay = session.replay();
+    let replayed = replay.recording();
+
+    get(client::handshake(replay)).wait().expect("replay");
+
+    let replayed = replayed.session();
+    assert_eq!(kinds(&replayed, Direction::Send), kinds(&session, Direction::Send));
+    assert_eq!(kinds(&replayed, Direction::Recv), kinds(&session, Direction::Recv));
+}
+
+#[test]
+fn capture_stops_at_max_frames() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .send_frame(frames::headers(1).response(200))
+        .send_frame(frames::data(1, "hello").eos())
+        .close();
+
+    let io = Capture::new(io, Side::Client);
+    let recording = io.recording();
+    recording.set_max_frames(3);
+    assert!(!recording.is_stopped());
+
+    get(client::handshake(io)).join(srv).wait().expect("wait");
+
+    assert!(recording.is_stopped());
+    assert_eq!(recording.session().frames().len(), 3);
+}
+
+#[test]
+fn capture_stopped() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .send_frame(frames::headers(1).response(200))
+        .send_frame(frames::data(1, "hello").eos())
+        .close();
+
+    let io = Capture::new(io, Side::Client);
+    let recording = io.recording();
+    recording.stop();
+
+    get(client::handshake(io)).join(srv).wait().expect("wait");
+
+    assert!(recording.is_stopped());
+    assert!(recording.session().frames().is_empty());
+}
+
+fn serve<T>(io: T) -> Box<Future<Item = (), Error = ()>>
+where
+    T: AsyncRead + AsyncWrite + fmt::Debug + 'static,
+{
+    let fut = server::handshake(io)
+        .expect("handshake")
+        .and_then(|srv| {
+            srv.into_future().unwrap().and_then(|(reqstream, srv)| {
+                let (req, mut respond) = reqstream.unwrap();
+                assert_eq!(req.uri().path(), "/");
+
+                respond.send_response(Response::new(()), true).unwrap();
+
+                srv.into_future().unwrap().map(|(req, _)| assert!(req.is_none()))
+            })
+        });
+
+    Box::new(fut)
+}
+
+#[test]
+fn replay_server_session() {
+    let _ = ::env_logger::try_init();
+    let (io, client) = mock::new();
+
+    let client = client
+        .assert_server_handshake()
+        .unwrap()
+        .recv_settings()
+        .send_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .recv_frame(frames::headers(1).response(200).eos())
+        .close();
+
+    let io = Capture::new(io, Side::Server);
+    let recording = io.recording();
+
+    serve(io).join(client).wait().expect("wait");
+
+    let session = recording.session();
+    assert_eq!(kinds(&session, Direction::Recv), [0x4, 0x4, 0x1]);
+
+    let replay = session.replay();
+    let replayed = replay.recording();
+
+    serve(replay).wait().expect("replay");
+
+    assert_eq!(kinds(&replayed.session(), Direction::Send), kinds(&session, Direction::Send));
+}
+
+#[test]
+fn session_json_round_trip() {
+    let mut session = Session::new(Side::Client);
+
+    session.push(h2::session::RecordedFrame::new(
+        Direction::Recv,
+        None,
+        Bytes::from_static(b"\x00\x00\x00\x04\x01\x00\x00\x00\x00"),
+    ));
+
+    let json = serde_json::to_string(&session).unwrap();
+    assert_eq!(
+        json,
+        r#"{"side":"Client","frames":[{"direction":"Recv","bytes":"000000040100000000"}]}"#
+    );
+
+    let decoded: Session = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, session);
+    assert_eq!(decoded.frames()[0].kind(), Some(4));
+}
+
+#[test]
+fn kind_of_malformed_frame() {
+    let truncated = h2::session::RecordedFrame::new(
+        Direction::Recv,
+        None,
+        Bytes::from_static(b"\x00\x00"),
+    );
+    assert_eq!(truncated.kind(), None);
+
+    // The head announces a 4 byte payload, but there is none.
+    let short = h2::session::RecordedFrame::new(
+        Direction::Recv,
+        None,
+        Bytes::from_static(b"\x00\x00\x04\x04\x00\x00\x00\x00\x00"),
+    );
+    assert_eq!(short.kind(), None);
+}
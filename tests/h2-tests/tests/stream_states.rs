@@ -345,6 +345,76 @@ fn recv_goaway_finishes_processed_streams() {
     h2.join(srv).wait().expect("wait");
 }
 
+#[test]
+fn recv_goaway_with_debug_data() {
+    let _ = ::env_logger::try_init();
+    let (io, srv) = mock::new();
+
+    let srv = srv.assert_client_handshake()
+        .unwrap()
+        .recv_settings()
+        .recv_frame(
+            frames::headers(1)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .recv_frame(
+            frames::headers(3)
+                .request("GET", "https://example.com/")
+                .eos(),
+        )
+        .send_frame(frames::go_away(1).calm().data("slow down"))
+        .send_frame(frames::headers(1).response(200).eos())
+        .recv_frame(frames::go_away(0))
+        .close();
+
+    let h2 = client::handshake(io)
+        .expect("handshake")
+        .and_then(|(mut client, mut h2)| {
+            let req1 = client.get("https://example.com");
+            let last_stream_id = req1.stream_id();
+            assert_eq!(StreamId::from(last_stream_id.clone()), 1);
+            let req1 = req1
+                .expect("response")
+                .map(|resp| {
+                    assert_eq!(resp.status(), StatusCode::OK);
+                });
+
+            // stream 3 was not processed by the server
+            let req2 = client
+                .get("https://example.com/")
+                .then(|res| {
+                    let err = res.unwrap_err();
+                    assert!(err.is_go_away());
+                    assert!(err.is_retryable());
+                    assert_eq!(err.reason(), Some(Reason::ENHANCE_YOUR_CALM));
+                    assert_eq!(err.go_away_debug_data().unwrap(), "slow down");
+                    assert_eq!(err.go_away_last_stream_id(), Some(last_stream_id));
+                    Ok::<(), ()>(())
+                });
+
+            let conn = futures::future::poll_fn(move || {
+                let err = match h2.poll() {
+                    Ok(async) => return Ok(async),
+                    Err(err) => err,
+                };
+                assert!(err.is_go_away());
+                assert!(!err.is_retryable());
+                assert_eq!(err.reason(), Some(Reason::ENHANCE_YOUR_CALM));
+                assert_eq!(err.go_away_debug_data().unwrap(), "slow down");
+
+                let go_away = h2.received_go_away().expect("received GOAWAY");
+                assert_eq!(go_away.reason(), Reason::ENHANCE_YOUR_CALM);
+                assert_eq!(go_away.debug_data(), "slow down");
+                Ok::<_, ()>(().into())
+            });
+
+            conn.join3(req1, req2)
+        });
+
+    h2.join(srv).wait().expect("wait");
+}
+
 #[test]
 fn recv_next_stream_id_updated_by_malformed_headers() {
     let _ = ::env_logger::try_init();
@@ -1,264 +1,115 @@
-use std::cell::RefCell;
-
-struct Node {
-    id: Option<usize>,
-    left: Option<Box<Node>>,
-    right: Option<Box<Node>>,
-    terminal: Option<usize>,
-    maybe_eos: bool,
-    transitions: RefCell<Vec<Transition>>,
-}
-
-#[derive(Debug, Default, Copy, Clone)]
-struct Transition {
-    target: Option<usize>,
-    byte: Option<usize>,
-    maybe_eos: bool,
-}
-
-impl Node {
-    fn new(byte: usize, code: &[bool]) -> Box<Node> {
-        let mut node = Box::new(Node {
-            id: None,
-            left: None,
-            right: None,
-            terminal: None,
-            maybe_eos: false,
-            transitions: Default::default(),
-        });
-
-        node.insert(byte, code);
-        node
-    }
+/// Loads the code of each symbol, as (num-bits, bits)
+fn load_table() -> Vec<(usize, u64)> {
+    let mut lines = TABLE.lines();
+    let mut table = vec![];
 
-    fn insert(&mut self, byte: usize, code: &[bool]) {
-        if code.is_empty() {
-            self.terminal = Some(byte);
-            return;
-        }
+    // Skip the first line, which is empty
+    lines.next();
 
-        let (head, rest) = code.split_at(1);
+    for line in lines {
+        let mut nbits = 0;
 
-        if !head[0] {
-            match self.left {
-                Some(ref mut node) => {
-                    node.insert(byte, rest);
-                }
-                None => {
-                    self.left = Some(Node::new(byte, rest));
-                }
-            }
-        } else {
-            match self.right {
-                Some(ref mut node) => {
-                    node.insert(byte, rest);
-                }
-                None => {
-                    self.right = Some(Node::new(byte, rest));
-                }
+        for &b in &line.as_bytes()[12..45] {
+            match b {
+                b'1' | b'0' => nbits += 1,
+                b'|' | b' ' => {}
+                _ => panic!("unexpected byte; {:?}", b),
             }
         }
-    }
-
-    fn set_id(&mut self, next_id: &mut usize, prefix: &mut Vec<bool>) {
-        if self.terminal.is_some() {
-            return;
-        }
-
-        if prefix.len() <= 7 && prefix.iter().all(|i| *i) {
-            self.maybe_eos = true;
-        }
 
-        let id = *next_id;
-        *next_id = id + 1;
-        self.id = Some(id);
+        let code = u64::from_str_radix(line[50..59].trim(), 16).unwrap();
 
-        if let Some(ref mut node) = self.left {
-            prefix.push(false);
-            node.set_id(next_id, prefix);
-            prefix.pop();
-        }
-
-        if let Some(ref mut node) = self.right {
-            prefix.push(true);
-            node.set_id(next_id, prefix);
-            prefix.pop();
-        }
+        table.push((nbits, code));
     }
 
-    fn compute_transitions(&self, root: &Node) {
-        self.compute_transition(None, self, root, 4);
-
-        if let Some(ref node) = self.left {
-            node.compute_transitions(root);
-        }
-
-        if let Some(ref node) = self.right {
-            node.compute_transitions(root);
-        }
-    }
-
-    fn compute_transition(&self,
-                          byte: Option<usize>,
-                          start: &Node,
-                          root: &Node,
-                          steps_remaining: usize)
-    {
-        if steps_remaining == 0 {
-            let (byte, target) = match byte {
-                Some(256) => (None, None),
-                _ => {
-                    (byte, Some(self.id.unwrap_or(0)))
-                }
-            };
-
-            start.transitions.borrow_mut().push(Transition {
-                target: target,
-                byte: byte,
-                maybe_eos: self.maybe_eos,
-            });
-
-            return;
-        }
+    table
+}
 
-        let mut next = self;
+/// Number of bits looked up at once when decoding
+const FAST_BITS: usize = 8;
 
-        if self.terminal.is_some() {
-            next = root;
-        }
+/// Longest code
+const MAX_BITS: usize = 30;
 
-        assert!(next.left.is_some());
-        assert!(next.right.is_some());
+pub fn main() {
+    let table = load_table();
 
-        for node in &[next.left.as_ref().unwrap(), next.right.as_ref().unwrap()] {
-            let byte = match node.terminal {
-                Some(b) => {
-                    assert!(byte.is_none());
-                    Some(b)
-                }
-                None => byte,
-            };
+    println!("// !!! DO NOT EDIT !!! Generated by util/genhuff/src/main.rs");
+    println!("");
 
-            node.compute_transition(byte, start, root, steps_remaining - 1);
-        }
+    println!("// (num-bits, bits)");
+    println!("pub const ENCODE_TABLE: [(usize, u64); 257] = [");
+    for &(nbits, code) in &table {
+        println!("    ({}, 0x{:x}),", nbits, code);
     }
+    println!("];");
 
-    fn print(&self) {
-        const MAYBE_EOS: u8 = 1;
-        const DECODED: u8 = 2;
-        const ERROR: u8 = 4;
+    // Every symbol whose code fits in `FAST_BITS` is decoded with a single
+    // lookup of the next `FAST_BITS` bits.
+    let mut fast = vec![(0, 0); 1 << FAST_BITS];
 
-        if self.terminal.is_some() {
-            return;
+    for (byte, &(nbits, code)) in table.iter().enumerate() {
+        if nbits > FAST_BITS {
+            continue;
         }
 
-        println!("    // {}", self.id.unwrap());
-        println!("    [");
+        let shift = FAST_BITS - nbits;
+        let start = (code as usize) << shift;
 
-        for transition in self.transitions.borrow().iter() {
-            let mut flags = 0;
-            let mut out = 0;
-
-            let target = match transition.target {
-                Some(target) => target,
-                None => {
-                    flags |= ERROR;
-                    0
-                }
-            };
-
-            if let Some(byte) = transition.byte {
-                out = byte;
-                flags |= DECODED;
-
-                // TODO: Add other flags
-            }
-
-            if transition.maybe_eos {
-                flags |= MAYBE_EOS;
-            }
-
-            println!("        ({}, {}, 0x{:02x}),", target, out, flags);
+        for entry in &mut fast[start..start + (1 << shift)] {
+            *entry = (byte, nbits);
         }
-
-        println!("    ],");
-
-        self.left.as_ref().unwrap().print();
-        self.right.as_ref().unwrap().print();
     }
-}
 
-/// Returns root of tree
-fn load_table() -> (Vec<(usize, String)>, Box<Node>) {
-    let mut lines = TABLE.lines();
-    let mut root: Option<Box<Node>> = None;
+    println!("");
+    println!("pub const DECODE_FAST_BITS: usize = {};", FAST_BITS);
+    println!("");
+    println!("// (byte, num-bits) indexed by the next {} bits, num-bits is 0 for longer codes", FAST_BITS);
+    println!("pub const DECODE_FAST: [(u8, u8); {}] = [", 1 << FAST_BITS);
+    for &(byte, nbits) in &fast {
+        println!("    ({}, {}),", byte, nbits);
+    }
+    println!("];");
 
-    let mut encode = vec![];
+    // The code is canonical: the codes of a given length are consecutive, in
+    // the order of their symbols, so longer codes are decoded from the first
+    // code of each length.
+    let mut symbols: Vec<usize> = (0..table.len()).collect();
+    symbols.sort_by_key(|&sym| (table[sym].0, sym));
 
-    // Skip the first line, which is empty
-    lines.next();
+    let mut first = vec![0; MAX_BITS + 1];
+    let mut count = vec![0; MAX_BITS + 1];
+    let mut offset = vec![0; MAX_BITS + 1];
 
-    for (i, line) in lines.enumerate() {
-        let mut bits: Vec<bool> = vec![];
+    for (i, &sym) in symbols.iter().enumerate() {
+        let (nbits, code) = table[sym];
 
-        for &b in &line.as_bytes()[12..45] {
-            match b {
-                b'1' => bits.push(true),
-                b'0' => bits.push(false),
-                b'|' | b' ' => {}
-                _ => panic!("unexpected byte; {:?}", b),
-            }
+        if count[nbits] == 0 {
+            first[nbits] = code;
+            offset[nbits] = i;
         }
 
-        let hex = line[50..59].trim();
-
-        encode.push((bits.len(), hex.to_string()));
-
-        match root {
-            Some(ref mut node) => {
-                node.insert(i, &bits);
-            }
-            None => {
-                root = Some(Node::new(i, &bits));
-            }
-        }
+        assert_eq!(code, first[nbits] + count[nbits] as u64, "code is not canonical");
+        count[nbits] += 1;
     }
 
-    // Assign IDs to all state transition nodes
-    let mut root = root.unwrap();
-    let mut id = 0;
-    root.set_id(&mut id, &mut vec![]);
-
-    // Compute transitions for each node
-    root.compute_transitions(&root);
-
-    (encode, root)
-}
-
-pub fn main() {
-    let (encode, decode) = load_table();
-
-    println!("// !!! DO NOT EDIT !!! Generated by util/genhuff/src/main.rs");
     println!("");
-
-    println!("// (num-bits, bits)");
-    println!("pub const ENCODE_TABLE: [(usize, u64); 257] = [");
-    for (nbits, val) in encode {
-        println!("    ({}, 0x{}),", nbits, val);
+    println!("// (first-code, num-codes, offset into DECODE_SYMBOLS) indexed by num-bits");
+    println!("pub const DECODE_CODES: [(u32, u16, u16); {}] = [", MAX_BITS + 1);
+    for nbits in 0..MAX_BITS + 1 {
+        println!("    (0x{:x}, {}, {}),", first[nbits], count[nbits], offset[nbits]);
     }
     println!("];");
 
     println!("");
-    println!("// (next-state, byte, flags)");
-    println!("pub const DECODE_TABLE: [[(usize, u8, u8); 16]; 256] = [");
-
-    decode.print();
-
+    println!("// Symbols in the order of their codes");
+    println!("pub const DECODE_SYMBOLS: [u16; 257] = [");
+    for sym in symbols {
+        println!("    {},", sym);
+    }
     println!("];");
 }
 
-
-
 const TABLE: &'static str = r##"
     (  0)  |11111111|11000                             1ff8  [13]
     (  1)  |11111111|11111111|1011000                7fffd8  [23]